@@ -0,0 +1,786 @@
+//! Commands typed in the console opened with `:`.
+//!
+//! A command line is split into words like a shell does: words are separated by whitespace,
+//! single quotes keep their content as is, double quotes allow `\"` and `\\`, and a backslash
+//! outside quotes escapes the next character. The first word is the command, which may end
+//! with `!` to force it (e.g. `:q!`, `:delete!`).
+use std::fs as stdfs;
+use std::path::{Path, PathBuf};
+
+use dirs;
+
+use crate::config::options::{SortBy, BOOLEAN_OPTIONS, OPTION_NAMES};
+use crate::error::*;
+use crate::utils::bookmarks;
+use crate::utils::filter::{self, Criterion};
+
+/// A parsed command, ready to be run.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Changes the directory of the focused tab, to the home directory if none is given.
+    Cd(Option<String>),
+    /// Creates the given directories.
+    Mkdir(Vec<String>),
+    /// Creates the given empty files.
+    Touch(Vec<String>),
+    /// Renames the selected entry.
+    Rename(String),
+    /// Renames the marked entries, or every entry of the listing, in the editor.
+    BulkRename,
+    /// Renames the marked entries, or every entry of the listing, with a pattern.
+    BatchRename,
+    /// Moves the marked entries, or the selected one, to the trash, or deletes them if forced.
+    Delete { permanent: bool },
+    /// Archives the marked entries, or the selected one, into the given file, compressed
+    /// according to its extension.
+    Archive(String),
+    /// Marks (or unmarks) the entries of the current listing matching any of the criteria.
+    Mark { criteria: Vec<Criterion>, mark: bool },
+    /// Searches the entries under the current directory matching every criterion, and lists
+    /// them in place of the current directory. `query` holds the criteria as typed.
+    Find { criteria: Vec<Criterion>, query: String },
+    /// Searches the lines matching a pattern in the files under the current directory.
+    Grep(String),
+    /// Shows the bookmarks.
+    Bookmarks,
+    /// Sets a bookmark to the given directory, or to the current one if none is given.
+    Bookmark { key: char, dir: Option<String> },
+    /// Deletes the given bookmarks.
+    DelBookmark(Vec<char>),
+    /// Shows the directories visited in the focused tab.
+    History,
+    /// Goes to the best ranked visited directory holding the fragments, or lets the user pick
+    /// one of the visited directories if none are given.
+    Frecent(Vec<String>),
+    /// Sets options, as `(name, value)` pairs.
+    Set(Vec<(String, String)>),
+    /// Sorts the listings.
+    Sort { by: SortBy, reverse: bool },
+    /// Opens a new tab, in the current directory if none is given.
+    TabNew(Option<String>),
+    /// Quits, even with running jobs if forced.
+    Quit { force: bool },
+}
+
+/// Description of a command of the command table.
+pub struct CommandInfo {
+    /// Name of the command, as typed after `:`.
+    pub name: &'static str,
+    /// Other names of the command.
+    pub aliases: &'static [&'static str],
+    /// Arguments of the command, as shown in error messages.
+    pub usage: &'static str,
+    /// Minimum number of arguments.
+    pub min_args: usize,
+    /// Maximum number of arguments, if any.
+    pub max_args: Option<usize>,
+    /// Builds the command from its arguments and whether it was forced.
+    build: fn(Vec<String>, bool) -> Result<Command>,
+}
+
+/// Every built-in command.
+pub const COMMANDS: &[CommandInfo] = &[
+    CommandInfo {
+        name: "cd",
+        aliases: &[],
+        usage: "[directory]",
+        min_args: 0,
+        max_args: Some(1),
+        build: build_cd,
+    },
+    CommandInfo {
+        name: "mkdir",
+        aliases: &[],
+        usage: "<directory>...",
+        min_args: 1,
+        max_args: None,
+        build: build_mkdir,
+    },
+    CommandInfo {
+        name: "touch",
+        aliases: &[],
+        usage: "<file>...",
+        min_args: 1,
+        max_args: None,
+        build: build_touch,
+    },
+    CommandInfo {
+        name: "rename",
+        aliases: &[],
+        usage: "<new name>",
+        min_args: 1,
+        max_args: Some(1),
+        build: build_rename,
+    },
+    CommandInfo {
+        name: "bulkrename",
+        aliases: &[],
+        usage: "",
+        min_args: 0,
+        max_args: Some(0),
+        build: build_bulkrename,
+    },
+    CommandInfo {
+        name: "batchrename",
+        aliases: &[],
+        usage: "",
+        min_args: 0,
+        max_args: Some(0),
+        build: build_batchrename,
+    },
+    CommandInfo {
+        name: "delete",
+        aliases: &[],
+        usage: "",
+        min_args: 0,
+        max_args: Some(0),
+        build: build_delete,
+    },
+    CommandInfo {
+        name: "archive",
+        aliases: &[],
+        usage: "<file>",
+        min_args: 1,
+        max_args: Some(1),
+        build: build_archive,
+    },
+    CommandInfo {
+        name: "mark",
+        aliases: &[],
+        usage: "<pattern>...",
+        min_args: 1,
+        max_args: None,
+        build: build_mark,
+    },
+    CommandInfo {
+        name: "unmark",
+        aliases: &[],
+        usage: "<pattern>...",
+        min_args: 1,
+        max_args: None,
+        build: build_unmark,
+    },
+    CommandInfo {
+        name: "mark-newer",
+        aliases: &[],
+        usage: "<age>",
+        min_args: 1,
+        max_args: Some(1),
+        build: build_mark_newer,
+    },
+    CommandInfo {
+        name: "mark-older",
+        aliases: &[],
+        usage: "<age>",
+        min_args: 1,
+        max_args: Some(1),
+        build: build_mark_older,
+    },
+    CommandInfo {
+        name: "mark-larger",
+        aliases: &[],
+        usage: "<size>",
+        min_args: 1,
+        max_args: Some(1),
+        build: build_mark_larger,
+    },
+    CommandInfo {
+        name: "mark-smaller",
+        aliases: &[],
+        usage: "<size>",
+        min_args: 1,
+        max_args: Some(1),
+        build: build_mark_smaller,
+    },
+    CommandInfo {
+        name: "find",
+        aliases: &[],
+        usage: "<criterion>...",
+        min_args: 1,
+        max_args: None,
+        build: build_find,
+    },
+    CommandInfo {
+        name: "grep",
+        aliases: &[],
+        usage: "<pattern>",
+        min_args: 1,
+        max_args: None,
+        build: build_grep,
+    },
+    CommandInfo {
+        name: "bookmarks",
+        aliases: &[],
+        usage: "",
+        min_args: 0,
+        max_args: Some(0),
+        build: build_bookmarks,
+    },
+    CommandInfo {
+        name: "bookmark",
+        aliases: &[],
+        usage: "<key> [directory]",
+        min_args: 1,
+        max_args: Some(2),
+        build: build_bookmark,
+    },
+    CommandInfo {
+        name: "delbookmark",
+        aliases: &[],
+        usage: "<key>...",
+        min_args: 1,
+        max_args: None,
+        build: build_delbookmark,
+    },
+    CommandInfo {
+        name: "history",
+        aliases: &[],
+        usage: "",
+        min_args: 0,
+        max_args: Some(0),
+        build: build_history,
+    },
+    CommandInfo {
+        name: "z",
+        aliases: &[],
+        usage: "[fragment]...",
+        min_args: 0,
+        max_args: None,
+        build: build_z,
+    },
+    CommandInfo {
+        name: "set",
+        aliases: &[],
+        usage: "<option>[=<value>]...",
+        min_args: 1,
+        max_args: None,
+        build: build_set,
+    },
+    CommandInfo {
+        name: "sort",
+        aliases: &[],
+        usage: "<name|size|modified|extension> [reverse]",
+        min_args: 1,
+        max_args: Some(2),
+        build: build_sort,
+    },
+    CommandInfo {
+        name: "tabnew",
+        aliases: &[],
+        usage: "[directory]",
+        min_args: 0,
+        max_args: Some(1),
+        build: build_tabnew,
+    },
+    CommandInfo {
+        name: "quit",
+        aliases: &["q"],
+        usage: "",
+        min_args: 0,
+        max_args: Some(0),
+        build: build_quit,
+    },
+];
+
+/// Returns the command named `name`, or one of its aliases.
+pub fn find(name: &str) -> Option<&'static CommandInfo> {
+    COMMANDS
+        .iter()
+        .find(|info| info.name == name || info.aliases.contains(&name))
+}
+
+/// Parses a command line, with or without its leading `:`.
+pub fn parse(line: &str) -> Result<Command> {
+    let line = line.trim();
+    let line = line.strip_prefix(':').unwrap_or(line);
+    let mut words = split(line)?.into_iter();
+    let word = words.next().ok_or_else(|| invalid("No command given"))?;
+    let (name, force) = if word.ends_with('!') {
+        (&word[..word.len() - 1], true)
+    } else {
+        (&word[..], false)
+    };
+    let info = find(name).ok_or_else(|| invalid(&format!("Unknown command: {}", name)))?;
+    let args: Vec<String> = words.collect();
+    if args.len() < info.min_args || info.max_args.map_or(false, |max| args.len() > max) {
+        let usage = format!("Usage: :{} {}", info.name, info.usage);
+        return Err(invalid(usage.trim_end()));
+    }
+    (info.build)(args, force)
+}
+
+/// Checks that `name` can be the name of an entry of a directory.
+pub fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+        return Err(invalid(&format!("Invalid name: {:?}", name)));
+    }
+    Ok(())
+}
+
+/// Splits a command line into words, handling quotes and backslashes.
+pub fn split(line: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if let Some(word) = word.take() {
+                    words.push(word);
+                }
+            }
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(invalid("Unterminated single quote")),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ '"') | Some(c @ '\\') => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err(invalid("Unterminated double quote")),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err(invalid("Unterminated double quote")),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => return Err(invalid("Trailing backslash")),
+            },
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Resolves a path argument against `cwd`, expanding a leading `~` to the home directory.
+pub fn expand_path(arg: &str, cwd: &Path) -> PathBuf {
+    let home = dirs::home_dir();
+    let path = match home {
+        Some(ref home) if arg == "~" => home.clone(),
+        Some(ref home) if arg.starts_with("~/") => home.join(&arg[2..]),
+        _ => PathBuf::from(arg),
+    };
+    if path.is_absolute() {
+        path
+    } else {
+        cwd.join(path)
+    }
+}
+
+/// Returns the candidates completing the last word of a command line, along with the byte
+/// offset at which that word starts.
+///
+/// The first word completes to command names, arguments of `:set` and `:sort` to option
+/// names and sort keys, and any other argument to paths relative to `cwd`.
+pub fn complete(line: &str, cwd: &Path) -> (usize, Vec<String>) {
+    let start = word_start(line);
+    let word = &line[start..];
+    let first = line[..start].split_whitespace().next();
+    let first = first.map(|name| name.trim_start_matches(':').trim_end_matches('!'));
+    let mut candidates: Vec<String> = match first.map(|name| find(name).map(|i| i.name)) {
+        None => COMMANDS
+            .iter()
+            .flat_map(|info| ::std::iter::once(info.name).chain(info.aliases.iter().cloned()))
+            .filter(|name| name.starts_with(word.trim_start_matches(':')))
+            .map(|name| format!("{}{}", if word.starts_with(':') { ":" } else { "" }, name))
+            .collect(),
+        Some(Some("set")) => OPTION_NAMES
+            .iter()
+            .filter(|name| name.starts_with(word))
+            .map(|name| name.to_string())
+            .collect(),
+        Some(Some("sort")) => ["name", "size", "modified", "extension", "reverse"]
+            .iter()
+            .filter(|key| key.starts_with(word))
+            .map(|key| key.to_string())
+            .collect(),
+        Some(_) => return (start, complete_path(word, cwd)),
+    };
+    candidates.sort();
+    (start, candidates)
+}
+
+/// Returns the paths completing `word`, relative to `cwd`. Spaces and quotes in the names are
+/// escaped with a backslash, and directories end with `/`.
+///
+/// Hidden entries are only proposed if the name being completed starts with a dot.
+pub fn complete_path(word: &str, cwd: &Path) -> Vec<String> {
+    let typed = unescape(word);
+    let (dir, name) = match typed.rfind('/') {
+        Some(i) => (&typed[..=i], &typed[i + 1..]),
+        None => ("", typed.as_str()),
+    };
+    let dir_path = if dir.is_empty() {
+        cwd.to_path_buf()
+    } else {
+        expand_path(dir, cwd)
+    };
+    let entries = match stdfs::read_dir(&dir_path) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut candidates: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            if !file_name.starts_with(name) || (file_name.starts_with('.') && !name.starts_with('.'))
+            {
+                return None;
+            }
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{}{}{}", escape(dir), escape(&file_name), slash))
+        }).collect();
+    candidates.sort();
+    candidates
+}
+
+/// Returns the longest prefix shared by every candidate.
+pub fn common_prefix(candidates: &[String]) -> String {
+    let mut prefix = match candidates.first() {
+        Some(first) => first.clone(),
+        None => return String::new(),
+    };
+    for candidate in &candidates[1..] {
+        let common = prefix
+            .char_indices()
+            .zip(candidate.chars())
+            .find(|((_, a), b)| a != b)
+            .map(|((i, _), _)| i)
+            .unwrap_or_else(|| prefix.len().min(candidate.len()));
+        prefix.truncate(common);
+    }
+    prefix
+}
+
+/// Finds where the last word of `line` starts, skipping escaped whitespace.
+fn word_start(line: &str) -> usize {
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c.is_whitespace() {
+            start = i + c.len_utf8();
+        }
+    }
+    start
+}
+
+fn escape(word: &str) -> String {
+    let mut escaped = String::with_capacity(word.len());
+    for c in word.chars() {
+        if c.is_whitespace() || c == '\'' || c == '"' || c == '\\' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn unescape(word: &str) -> String {
+    let mut unescaped = String::with_capacity(word.len());
+    let mut chars = word.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+fn invalid(reason: &str) -> Error {
+    ErrorKind::InvalidCommand {
+        reason: reason.to_string(),
+    }.into()
+}
+
+fn build_cd(args: Vec<String>, _force: bool) -> Result<Command> {
+    Ok(Command::Cd(args.into_iter().next()))
+}
+
+fn build_mkdir(args: Vec<String>, _force: bool) -> Result<Command> {
+    Ok(Command::Mkdir(args))
+}
+
+fn build_touch(args: Vec<String>, _force: bool) -> Result<Command> {
+    Ok(Command::Touch(args))
+}
+
+fn build_rename(args: Vec<String>, _force: bool) -> Result<Command> {
+    let name = args.into_iter().next().unwrap_or_default();
+    check_name(&name)?;
+    Ok(Command::Rename(name))
+}
+
+fn build_bulkrename(_args: Vec<String>, _force: bool) -> Result<Command> {
+    Ok(Command::BulkRename)
+}
+
+fn build_batchrename(_args: Vec<String>, _force: bool) -> Result<Command> {
+    Ok(Command::BatchRename)
+}
+
+fn build_delete(_args: Vec<String>, force: bool) -> Result<Command> {
+    Ok(Command::Delete { permanent: force })
+}
+
+fn build_archive(args: Vec<String>, _force: bool) -> Result<Command> {
+    Ok(Command::Archive(args.into_iter().next().unwrap_or_default()))
+}
+
+/// Each pattern is a `Criterion`: a glob, or a condition such as `re:^tmp_` or `type:d`.
+fn build_mark(args: Vec<String>, _force: bool) -> Result<Command> {
+    Ok(Command::Mark {
+        criteria: parse_criteria(&args)?,
+        mark: true,
+    })
+}
+
+fn build_unmark(args: Vec<String>, _force: bool) -> Result<Command> {
+    Ok(Command::Mark {
+        criteria: parse_criteria(&args)?,
+        mark: false,
+    })
+}
+
+fn parse_criteria(args: &[String]) -> Result<Vec<Criterion>> {
+    args.iter().map(|arg| arg.parse()).collect()
+}
+
+fn build_mark_newer(args: Vec<String>, _force: bool) -> Result<Command> {
+    mark_one(Criterion::Newer(filter::parse_age(&args[0])?))
+}
+
+fn build_mark_older(args: Vec<String>, _force: bool) -> Result<Command> {
+    mark_one(Criterion::Older(filter::parse_age(&args[0])?))
+}
+
+fn build_mark_larger(args: Vec<String>, _force: bool) -> Result<Command> {
+    mark_one(Criterion::Larger(filter::parse_size(&args[0])?))
+}
+
+fn build_mark_smaller(args: Vec<String>, _force: bool) -> Result<Command> {
+    mark_one(Criterion::Smaller(filter::parse_size(&args[0])?))
+}
+
+fn mark_one(criterion: Criterion) -> Result<Command> {
+    Ok(Command::Mark {
+        criteria: vec![criterion],
+        mark: true,
+    })
+}
+
+/// Criteria are those of `:mark`, e.g. `*.rs larger:10k newer:1w`, and must all be met.
+fn build_find(args: Vec<String>, _force: bool) -> Result<Command> {
+    Ok(Command::Find {
+        criteria: parse_criteria(&args)?,
+        query: args.join(" "),
+    })
+}
+
+/// Words of the pattern are joined by single spaces.
+fn build_grep(args: Vec<String>, _force: bool) -> Result<Command> {
+    Ok(Command::Grep(args.join(" ")))
+}
+
+fn build_bookmarks(_args: Vec<String>, _force: bool) -> Result<Command> {
+    Ok(Command::Bookmarks)
+}
+
+fn build_bookmark(args: Vec<String>, _force: bool) -> Result<Command> {
+    let mut args = args.into_iter();
+    let key = parse_key(&args.next().unwrap_or_default())?;
+    bookmarks::check_key(key)?;
+    Ok(Command::Bookmark {
+        key,
+        dir: args.next(),
+    })
+}
+
+fn build_delbookmark(args: Vec<String>, _force: bool) -> Result<Command> {
+    let keys = args.iter().map(|arg| parse_key(arg)).collect::<Result<_>>()?;
+    Ok(Command::DelBookmark(keys))
+}
+
+fn build_history(_args: Vec<String>, _force: bool) -> Result<Command> {
+    Ok(Command::History)
+}
+
+fn build_z(args: Vec<String>, _force: bool) -> Result<Command> {
+    Ok(Command::Frecent(args))
+}
+
+fn parse_key(arg: &str) -> Result<char> {
+    let mut chars = arg.chars();
+    match (chars.next(), chars.next()) {
+        (Some(key), None) => Ok(key),
+        _ => Err(invalid(&format!("Bookmark keys are single characters: {}", arg))),
+    }
+}
+
+/// `name=value` sets a value, `name` sets a boolean option and `noname` clears it.
+/// `name=value` sets an option, while a bare `name` sets a boolean option and `noname`
+/// clears it.
+fn build_set(args: Vec<String>, _force: bool) -> Result<Command> {
+    let pairs = args
+        .into_iter()
+        .map(|arg| match arg.find('=') {
+            Some(i) => (arg[..i].to_string(), arg[i + 1..].to_string()),
+            None => match arg.strip_prefix("no") {
+                Some(name) if BOOLEAN_OPTIONS.contains(&name) => {
+                    (name.to_string(), String::from("false"))
+                }
+                _ => (arg, String::from("true")),
+            },
+        }).collect();
+    Ok(Command::Set(pairs))
+}
+
+fn build_sort(args: Vec<String>, force: bool) -> Result<Command> {
+    let by = args[0].parse()?;
+    let reverse = match args.get(1).map(|arg| arg.as_str()) {
+        None => force,
+        Some("reverse") | Some("rev") => true,
+        Some(arg) => return Err(invalid(&format!("Unknown sort order: {}", arg))),
+    };
+    Ok(Command::Sort { by, reverse })
+}
+
+fn build_tabnew(args: Vec<String>, _force: bool) -> Result<Command> {
+    Ok(Command::TabNew(args.into_iter().next()))
+}
+
+fn build_quit(_args: Vec<String>, force: bool) -> Result<Command> {
+    Ok(Command::Quit { force })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    fn reason(line: &str) -> String {
+        parse(line).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn splits_quoted_and_escaped_words() {
+        assert_eq!(split("  a  b ").unwrap(), words(&["a", "b"]));
+        assert_eq!(
+            split(r#"'a b' "c \"d\"" e\ f"#).unwrap(),
+            words(&["a b", "c \"d\"", "e f"])
+        );
+        assert_eq!(split(r#"x'y'"z" "\n""#).unwrap(), words(&["xyz", "\\n"]));
+        assert_eq!(split("'' next").unwrap(), words(&["", "next"]));
+        assert!(split("'open").is_err());
+        assert!(split("\"open").is_err());
+        assert!(split("end\\").is_err());
+    }
+
+    #[test]
+    fn parses_commands_and_aliases() {
+        assert_eq!(parse(":cd ~/src").unwrap(), Command::Cd(Some("~/src".to_string())));
+        assert_eq!(parse("  cd  ").unwrap(), Command::Cd(None));
+        assert_eq!(parse("mkdir a 'b c'").unwrap(), Command::Mkdir(words(&["a", "b c"])));
+        assert_eq!(parse(":q").unwrap(), Command::Quit { force: false });
+        assert_eq!(parse(":quit!").unwrap(), Command::Quit { force: true });
+        assert_eq!(parse("delete!").unwrap(), Command::Delete { permanent: true });
+        assert_eq!(
+            parse("sort size rev").unwrap(),
+            Command::Sort {
+                by: SortBy::Size,
+                reverse: true
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_commands_and_bad_arguments() {
+        assert_eq!(reason(":"), "No command given");
+        assert_eq!(reason(":frobnicate now"), "Unknown command: frobnicate");
+        assert_eq!(reason(":rename"), "Usage: :rename <new name>");
+        assert_eq!(reason(":rename a b"), "Usage: :rename <new name>");
+        assert_eq!(reason(":bulkrename now"), "Usage: :bulkrename");
+        assert_eq!(reason(":rename a/b"), "Invalid name: \"a/b\"");
+        assert!(parse(":sort size upwards").is_err());
+        assert!(parse(":mkdir 'a").is_err());
+    }
+
+    #[test]
+    fn sets_and_clears_options() {
+        let pairs = |line| match parse(line).unwrap() {
+            Command::Set(pairs) => pairs,
+            command => panic!("unexpected command {:?}", command),
+        };
+        let pair = |name: &str, value: &str| (name.to_string(), value.to_string());
+        assert_eq!(
+            pairs(":set show_hidden noconfirm tab_width=8"),
+            vec![
+                pair("show_hidden", "true"),
+                pair("confirm", "false"),
+                pair("tab_width", "8")
+            ]
+        );
+        // Only boolean options are cleared by `no`, so other names are kept as typed.
+        assert_eq!(pairs(":set nosize"), vec![pair("nosize", "true")]);
+        assert_eq!(pairs(":set nonsense"), vec![pair("nonsense", "true")]);
+        assert_eq!(pairs(":set no"), vec![pair("no", "true")]);
+    }
+
+    #[test]
+    fn expands_paths() {
+        let cwd = Path::new("/srv/data");
+        assert_eq!(expand_path("logs", cwd), PathBuf::from("/srv/data/logs"));
+        assert_eq!(expand_path("/etc", cwd), PathBuf::from("/etc"));
+        assert_eq!(expand_path("~user", cwd), PathBuf::from("/srv/data/~user"));
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!(expand_path("~", cwd), home);
+            assert_eq!(expand_path("~/notes", cwd), home.join("notes"));
+        }
+    }
+
+    #[test]
+    fn completes_names_options_and_paths() {
+        let root = Path::new("/");
+        let marks = [":mark", ":mark-larger", ":mark-newer", ":mark-older", ":mark-smaller"];
+        assert_eq!(complete(":ma", root), (0, words(&marks)));
+        assert_eq!(complete("q", root), (0, words(&["q", "quit"])));
+        let shows = ["show_hidden", "show_images", "show_popup"];
+        assert_eq!(complete(":set show_", root).1, words(&shows));
+        assert_eq!(complete(":sort mo", root), (6, words(&["modified"])));
+
+        let dir = ::std::env::temp_dir().join(format!("marcos-complete-{}", ::std::process::id()));
+        let _ = stdfs::remove_dir_all(&dir);
+        stdfs::create_dir_all(dir.join("sub dir")).unwrap();
+        stdfs::write(dir.join("summary.txt"), "").unwrap();
+        stdfs::write(dir.join(".secret"), "").unwrap();
+        assert_eq!(complete(":cd su", &dir), (4, words(&["sub\\ dir/", "summary.txt"])));
+        assert_eq!(complete(":cd sub\\ d", &dir).1, words(&["sub\\ dir/"]));
+        assert_eq!(complete(":touch ", &dir).1, words(&["sub\\ dir/", "summary.txt"]));
+        assert_eq!(complete(":touch .s", &dir).1, words(&[".secret"]));
+        assert_eq!(common_prefix(&words(&["summary", "sub", "suffix"])), "su");
+        stdfs::remove_dir_all(&dir).unwrap();
+    }
+}
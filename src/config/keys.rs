@@ -1,33 +1,71 @@
-use cursive::event::Event;
-use cursive::event::Key;
-use cursive::Cursive;
+use std::cmp::min;
+use std::str::FromStr;
+
+use cursive::event::{Event, Key};
 
 use crate::error::*;
 
+#[derive(Clone, Debug, PartialEq)]
 enum Modifier {
-    // Shift is not included, as Capital letter denotes shift key usage!
     Alt,
     Ctrl,
+    // Terminals send Shift with a letter as the capital letter, so `S-x` is read as `X`.
     Shift,
-    AltCtrl,
-    AltShift,
-    CtrlShift,
     NoMod,
     // Currently Meta bindings not supported by Cursive
     // Meta
 }
 
+/// A sequence of keys, as written in the `[KeyMaps]` table of `config.toml`.
+///
+/// A sequence is either a run of plain characters (`"za"`, `"dd"`), or a single character
+/// with a modifier: `"C-r"` for Ctrl, `"M-x"` (or `"A-x"`) for Alt and `"S-x"` for Shift.
+#[derive(Clone, Debug, PartialEq)]
 pub struct KeySequence {
     takes_count: bool,
-    max_count: Option<i8>,
+    max_count: Option<usize>,
     modifier: Modifier,
     key: Vec<char>,
-    operation: fn(&mut Cursive),
+}
+
+impl FromStr for KeySequence {
+    type Err = Error;
+
+    fn from_str(keys: &str) -> Result<Self> {
+        let (modifier, rest) = if keys.len() > 2 && keys.starts_with("C-") {
+            (Modifier::Ctrl, &keys[2..])
+        } else if keys.len() > 2 && (keys.starts_with("M-") || keys.starts_with("A-")) {
+            (Modifier::Alt, &keys[2..])
+        } else if keys.len() > 2 && keys.starts_with("S-") {
+            (Modifier::Shift, &keys[2..])
+        } else {
+            (Modifier::NoMod, keys)
+        };
+        let key: Vec<char> = rest.chars().collect();
+        if key.is_empty() || (modifier != Modifier::NoMod && key.len() != 1) {
+            return Err(ErrorKind::InvalidKeyBinding {
+                binding: keys.to_string(),
+            }.into());
+        }
+        Ok(Self {
+            takes_count: false,
+            max_count: None,
+            modifier,
+            key,
+        })
+    }
 }
 
 impl KeySequence {
+    /// Allows the sequence to be prefixed by a count, capped at `max_count` if given.
+    fn counted(mut self, max_count: Option<usize>) -> Self {
+        self.takes_count = true;
+        self.max_count = max_count;
+        self
+    }
+
     /// Funtion to emit a vector of `Event`s.
-    fn emit_sequence(self) -> (bool, Vec<Event>) {
+    fn emit_sequence(&self) -> (bool, Vec<Event>) {
         let mut seq: Vec<Event> = Vec::with_capacity(2);
         match self {
             KeySequence {
@@ -44,12 +82,14 @@ impl KeySequence {
                 key: c,
                 ..
             } => {
-                if !c.is_empty() {
-                    seq.push(Event::CtrlChar(c[0]))
+                match c.first().map(|c| c.to_ascii_lowercase()) {
+                    // Terminals send Tab for Ctrl-i.
+                    Some('i') => seq.push(Event::Key(Key::Tab)),
+                    Some(c) => seq.push(Event::CtrlChar(c)),
+                    None => {}
                 }
             }
             KeySequence {
-                takes_count: _,
                 modifier: Modifier::Shift,
                 key: c,
                 ..
@@ -58,88 +98,185 @@ impl KeySequence {
                     seq.push(Event::Char(c[0].to_ascii_uppercase()))
                 }
             }
-            // KeySequence{takes_count,
-            //     modifier:Modifier::AltCtrl,
-            //     key:c} => {seq.push(Event::CtrlAlt(_))},
-            // KeySequence{takes_count,
-            //     modifier:Modifier::AltShift,
-            //     key:c} => {seq.push(Event::CtrlAlt(c))},
-            // KeySequence{takes_count,
-            //     modifier:Modifier::CtrlShift,
-            //     key:c} => {seq.push(Event::AltChar(c))},
             KeySequence {
-                takes_count: _,
                 modifier: Modifier::NoMod,
                 key: c,
                 ..
             } => {
                 for i in c {
-                    seq.push(Event::Char(i))
+                    seq.push(Event::Char(*i))
                 }
             }
-            _ => {}
         }
         (self.takes_count, seq)
     }
 }
 
+#[derive(Clone, Debug)]
 pub enum KeyBindings {
     Quit(KeySequence),
     SelectUp(KeySequence),
     SelectDown(KeySequence),
     Back(KeySequence),
     Forward(KeySequence),
+    HistoryBack(KeySequence),
+    HistoryForward(KeySequence),
     Console(KeySequence),
     SelectFirst(KeySequence),
     SelectLast(KeySequence),
-    SelectN(KeySequence),
     ShowHidden(KeySequence),
     Yank(KeySequence),
     Cut(KeySequence),
     Paste(KeySequence),
     PasteReplace(KeySequence),
     Rename(KeySequence),
+    BulkRename(KeySequence),
+    BatchRename(KeySequence),
     DeleteWithConfirm(KeySequence),
+    DeletePermanent(KeySequence),
+    ShowTrash(KeySequence),
+    Undo(KeySequence),
+    Redo(KeySequence),
     NewFile(KeySequence),
     NewDir(KeySequence),
     CreateBookmark(KeySequence),
     JumpToBookmark(KeySequence),
     Search(KeySequence),
+    SearchReverse(KeySequence),
     NextMatch(KeySequence),
     PrevMatch(KeySequence),
+    FuzzyFind(KeySequence),
+    PreviewDown(KeySequence),
+    PreviewUp(KeySequence),
+    PreviewPageDown(KeySequence),
+    PreviewPageUp(KeySequence),
+    ToggleWrap(KeySequence),
+    HexView(KeySequence),
+    ToggleSelect(KeySequence),
+    InvertSelection(KeySequence),
+    ClearSelection(KeySequence),
     Visual(KeySequence),
     VisualAll(KeySequence),
     Refresh(KeySequence),
+    NewTab(KeySequence),
+    CloseTab(KeySequence),
+    NextTab(KeySequence),
+    PrevTab(KeySequence),
+    Jobs(KeySequence),
+}
+
+impl KeyBindings {
+    /// Returns the `KeySequence` which triggers this binding.
+    pub fn sequence(&self) -> &KeySequence {
+        match self {
+            KeyBindings::Quit(seq)
+            | KeyBindings::SelectUp(seq)
+            | KeyBindings::SelectDown(seq)
+            | KeyBindings::Back(seq)
+            | KeyBindings::Forward(seq)
+            | KeyBindings::HistoryBack(seq)
+            | KeyBindings::HistoryForward(seq)
+            | KeyBindings::Console(seq)
+            | KeyBindings::SelectFirst(seq)
+            | KeyBindings::SelectLast(seq)
+            | KeyBindings::ShowHidden(seq)
+            | KeyBindings::Yank(seq)
+            | KeyBindings::Cut(seq)
+            | KeyBindings::Paste(seq)
+            | KeyBindings::PasteReplace(seq)
+            | KeyBindings::Rename(seq)
+            | KeyBindings::BulkRename(seq)
+            | KeyBindings::BatchRename(seq)
+            | KeyBindings::DeleteWithConfirm(seq)
+            | KeyBindings::DeletePermanent(seq)
+            | KeyBindings::ShowTrash(seq)
+            | KeyBindings::Undo(seq)
+            | KeyBindings::Redo(seq)
+            | KeyBindings::NewFile(seq)
+            | KeyBindings::NewDir(seq)
+            | KeyBindings::CreateBookmark(seq)
+            | KeyBindings::JumpToBookmark(seq)
+            | KeyBindings::Search(seq)
+            | KeyBindings::SearchReverse(seq)
+            | KeyBindings::NextMatch(seq)
+            | KeyBindings::PrevMatch(seq)
+            | KeyBindings::FuzzyFind(seq)
+            | KeyBindings::PreviewDown(seq)
+            | KeyBindings::PreviewUp(seq)
+            | KeyBindings::PreviewPageDown(seq)
+            | KeyBindings::PreviewPageUp(seq)
+            | KeyBindings::ToggleWrap(seq)
+            | KeyBindings::HexView(seq)
+            | KeyBindings::ToggleSelect(seq)
+            | KeyBindings::InvertSelection(seq)
+            | KeyBindings::ClearSelection(seq)
+            | KeyBindings::Visual(seq)
+            | KeyBindings::VisualAll(seq)
+            | KeyBindings::Refresh(seq)
+            | KeyBindings::NewTab(seq)
+            | KeyBindings::CloseTab(seq)
+            | KeyBindings::NextTab(seq)
+            | KeyBindings::PrevTab(seq)
+            | KeyBindings::Jobs(seq) => seq,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct KeyMaps {
-    quit: String,
-    select_up: String,
-    select_down: String,
-    // select_first		= "gg"
-    // select_last 		= "G"
+    pub quit: String,
+    pub select_up: String,
+    pub select_down: String,
+    pub select_first: String,
+    pub select_last: String,
     // select_n		= "*g"
-    back: String,
-    forward: String,
-    prompt: String,
-    show_hidden: String,
-    yank: String,
-    cut: String,
-    paste: String,
-    paste_replace: String,
-    rename: String,
-    delete_with_cfm: String,
-    new_file: String,
-    new_folder: String,
-    // create bookmark
-    // jumpto bookmark
-    search: String,
-    next_match: String,
-    previous_match: String,
-    visual: String,
-    visual_all: String,
-    refresh: String,
+    pub back: String,
+    pub forward: String,
+    pub jump_back: String,
+    pub jump_forward: String,
+    pub history_back: String,
+    pub history_forward: String,
+    pub prompt: String,
+    pub show_hidden: String,
+    pub yank: String,
+    pub cut: String,
+    pub paste: String,
+    pub paste_replace: String,
+    pub rename: String,
+    pub bulk_rename: String,
+    pub batch_rename: String,
+    pub delete_with_cfm: String,
+    pub delete_permanent: String,
+    pub trash: String,
+    pub undo: String,
+    pub redo: String,
+    pub new_file: String,
+    pub new_folder: String,
+    pub create_bookmark: String,
+    pub jump_to_bookmark: String,
+    pub search: String,
+    pub search_reverse: String,
+    pub next_match: String,
+    pub previous_match: String,
+    pub fuzzy_find: String,
+    pub preview_down: String,
+    pub preview_up: String,
+    pub preview_page_down: String,
+    pub preview_page_up: String,
+    pub toggle_wrap: String,
+    pub hex_view: String,
+    pub toggle_select: String,
+    pub invert_selection: String,
+    pub clear_selection: String,
+    pub visual: String,
+    pub visual_all: String,
+    pub refresh: String,
+    pub tab_new: String,
+    pub tab_close: String,
+    pub tab_next: String,
+    pub tab_prev: String,
+    pub jobs: String,
 }
 
 impl Default for KeyMaps {
@@ -148,11 +285,15 @@ impl Default for KeyMaps {
             quit: "q".to_string(),
             select_up: "k".to_string(),
             select_down: "j".to_string(),
-            // select_first		= "gg"
-            // select_last 		= "G"
+            select_first: "gg".to_string(),
+            select_last: "G".to_string(),
             // select_n		= "*g"
             back: "h".to_string(),
             forward: "l".to_string(),
+            jump_back: "C-o".to_string(),
+            jump_forward: "C-i".to_string(),
+            history_back: "H".to_string(),
+            history_forward: "L".to_string(),
             prompt: ":".to_string(),
             show_hidden: "za".to_string(),
             yank: "y".to_string(),
@@ -160,17 +301,391 @@ impl Default for KeyMaps {
             paste: "p".to_string(),
             paste_replace: "P".to_string(),
             rename: "r".to_string(),
+            bulk_rename: "R".to_string(),
+            batch_rename: "gr".to_string(),
             delete_with_cfm: "dd".to_string(),
+            delete_permanent: "D".to_string(),
+            trash: "T".to_string(),
+            undo: "u".to_string(),
+            redo: "U".to_string(),
             new_file: "o".to_string(),
             new_folder: "O".to_string(),
-            // create bookmark
-            // jumpto bookmark
+            create_bookmark: "m".to_string(),
+            jump_to_bookmark: "`".to_string(),
             search: "/".to_string(),
+            search_reverse: "?".to_string(),
             next_match: "n".to_string(),
             previous_match: "N".to_string(),
+            fuzzy_find: "C-p".to_string(),
+            preview_down: "J".to_string(),
+            preview_up: "K".to_string(),
+            preview_page_down: "C-d".to_string(),
+            preview_page_up: "C-u".to_string(),
+            toggle_wrap: "zw".to_string(),
+            hex_view: "gx".to_string(),
+            toggle_select: " ".to_string(),
+            invert_selection: "*".to_string(),
+            clear_selection: "-".to_string(),
             visual: "v".to_string(),
             visual_all: "V".to_string(),
             refresh: "C-r".to_string(),
+            tab_new: "gn".to_string(),
+            tab_close: "gc".to_string(),
+            tab_next: "gt".to_string(),
+            tab_prev: "gT".to_string(),
+            jobs: "w".to_string(),
+        }
+    }
+}
+
+/// A key map as `(keys, takes_count, max_count, binding)`.
+type KeyMap<'a> = (&'a str, bool, Option<usize>, fn(KeySequence) -> KeyBindings);
+
+impl KeyMaps {
+    /// Parses every key map into its `KeyBindings`. An empty string leaves the action unbound.
+    pub fn bindings(&self) -> Result<Vec<KeyBindings>> {
+        let table: Vec<KeyMap> = vec![
+            (&self.quit, false, None, KeyBindings::Quit),
+            (&self.select_up, true, None, KeyBindings::SelectUp),
+            (&self.select_down, true, None, KeyBindings::SelectDown),
+            (&self.select_first, false, None, KeyBindings::SelectFirst),
+            (&self.select_last, true, None, KeyBindings::SelectLast),
+            (&self.back, true, None, KeyBindings::Back),
+            (&self.forward, false, None, KeyBindings::Forward),
+            (&self.jump_back, true, None, KeyBindings::HistoryBack),
+            (&self.jump_forward, true, None, KeyBindings::HistoryForward),
+            (&self.history_back, true, None, KeyBindings::HistoryBack),
+            (&self.history_forward, true, None, KeyBindings::HistoryForward),
+            (&self.prompt, false, None, KeyBindings::Console),
+            (&self.show_hidden, false, None, KeyBindings::ShowHidden),
+            (&self.yank, false, None, KeyBindings::Yank),
+            (&self.cut, false, None, KeyBindings::Cut),
+            (&self.paste, false, None, KeyBindings::Paste),
+            (&self.paste_replace, false, None, KeyBindings::PasteReplace),
+            (&self.rename, false, None, KeyBindings::Rename),
+            (&self.bulk_rename, false, None, KeyBindings::BulkRename),
+            (&self.batch_rename, false, None, KeyBindings::BatchRename),
+            (&self.delete_with_cfm, false, None, KeyBindings::DeleteWithConfirm),
+            (&self.delete_permanent, false, None, KeyBindings::DeletePermanent),
+            (&self.trash, false, None, KeyBindings::ShowTrash),
+            (&self.undo, false, None, KeyBindings::Undo),
+            (&self.redo, false, None, KeyBindings::Redo),
+            (&self.new_file, false, None, KeyBindings::NewFile),
+            (&self.new_folder, false, None, KeyBindings::NewDir),
+            (&self.create_bookmark, false, None, KeyBindings::CreateBookmark),
+            (&self.jump_to_bookmark, false, None, KeyBindings::JumpToBookmark),
+            (&self.search, false, None, KeyBindings::Search),
+            (&self.search_reverse, false, None, KeyBindings::SearchReverse),
+            (&self.next_match, true, Some(MAX_MATCH_COUNT), KeyBindings::NextMatch),
+            (&self.previous_match, true, Some(MAX_MATCH_COUNT), KeyBindings::PrevMatch),
+            (&self.fuzzy_find, false, None, KeyBindings::FuzzyFind),
+            (&self.preview_down, true, None, KeyBindings::PreviewDown),
+            (&self.preview_up, true, None, KeyBindings::PreviewUp),
+            (&self.preview_page_down, true, None, KeyBindings::PreviewPageDown),
+            (&self.preview_page_up, true, None, KeyBindings::PreviewPageUp),
+            (&self.toggle_wrap, false, None, KeyBindings::ToggleWrap),
+            (&self.hex_view, false, None, KeyBindings::HexView),
+            (&self.toggle_select, true, None, KeyBindings::ToggleSelect),
+            (&self.invert_selection, false, None, KeyBindings::InvertSelection),
+            (&self.clear_selection, false, None, KeyBindings::ClearSelection),
+            (&self.visual, false, None, KeyBindings::Visual),
+            (&self.visual_all, false, None, KeyBindings::VisualAll),
+            (&self.refresh, false, None, KeyBindings::Refresh),
+            (&self.tab_new, false, None, KeyBindings::NewTab),
+            (&self.tab_close, false, None, KeyBindings::CloseTab),
+            (&self.tab_next, true, None, KeyBindings::NextTab),
+            (&self.tab_prev, true, None, KeyBindings::PrevTab),
+            (&self.jobs, false, None, KeyBindings::Jobs),
+        ];
+        let mut bindings = Vec::with_capacity(table.len());
+        let mut events: Vec<(&str, Vec<Event>)> = Vec::with_capacity(table.len());
+        for (keys, takes_count, max_count, binding) in table {
+            if keys.is_empty() {
+                continue;
+            }
+            let mut seq: KeySequence = keys.parse()?;
+            if takes_count {
+                seq = seq.counted(max_count);
+            }
+            let seq_events = seq.emit_sequence().1;
+            if let Some(reason) = events
+                .iter()
+                .find_map(|(other, other_events)| clash(keys, &seq_events, other, other_events))
+            {
+                return Err(ErrorKind::InvalidKeyBinding { binding: reason }.into());
+            }
+            events.push((keys, seq_events));
+            bindings.push(binding(seq));
+        }
+        Ok(bindings)
+    }
+}
+
+/// Returns why two key sequences cannot both be bound, if they send the same events or if one
+/// is a prefix of the other: the dispatcher would run the shorter one as soon as it is typed.
+fn clash(keys: &str, events: &[Event], other: &str, other_events: &[Event]) -> Option<String> {
+    if events == other_events {
+        Some(format!("\"{}\" and \"{}\" are the same keys", other, keys))
+    } else if other_events.starts_with(events) {
+        Some(format!("\"{}\" is a prefix of \"{}\"", keys, other))
+    } else if events.starts_with(other_events) {
+        Some(format!("\"{}\" is a prefix of \"{}\"", other, keys))
+    } else {
+        None
+    }
+}
+
+/// Upper bound of the count accepted by `next_match` and `previous_match`.
+const MAX_MATCH_COUNT: usize = 999;
+
+/// Outcome of feeding an `Event` to the `KeyDispatcher`.
+pub enum Dispatch {
+    /// The event completed a key sequence, along with the count prefix if the binding takes one.
+    Matched(KeyBindings, Option<usize>),
+    /// The event is a count prefix or the start of a longer key sequence.
+    Pending,
+    /// The event does not continue any key sequence.
+    Unbound,
+}
+
+/// Matches incoming events against the configured `KeyBindings`, buffering multi-key sequences
+/// such as `gg` and `dd` and an optional count prefix such as the `5` in `5j`.
+pub struct KeyDispatcher {
+    bindings: Vec<(Vec<Event>, KeyBindings)>,
+    pending: Vec<Event>,
+    count: Option<usize>,
+}
+
+impl KeyDispatcher {
+    pub fn new(bindings: Vec<KeyBindings>) -> Self {
+        let bindings = bindings
+            .into_iter()
+            .map(|binding| (binding.sequence().emit_sequence().1, binding))
+            .filter(|(seq, _)| !seq.is_empty())
+            .collect();
+        Self {
+            bindings,
+            pending: Vec::new(),
+            count: None,
+        }
+    }
+
+    /// Returns every event the dispatcher is interested in, i.e. all events used in a key
+    /// sequence and the digits used as count prefix.
+    pub fn events(&self) -> Vec<Event> {
+        let mut events: Vec<Event> = (0..10)
+            .filter_map(|d| ::std::char::from_digit(d, 10))
+            .map(Event::Char)
+            .collect();
+        for (seq, _) in &self.bindings {
+            for event in seq {
+                if !events.contains(event) {
+                    events.push(event.clone());
+                }
+            }
+        }
+        events
+    }
+
+    /// Discards the pending key sequence and count.
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.count = None;
+    }
+
+    /// Feeds an event to the dispatcher.
+    pub fn dispatch(&mut self, event: Event) -> Dispatch {
+        if self.pending.is_empty() {
+            if let Event::Char(c) = event {
+                let digit = c.to_digit(10);
+                if let Some(d) = digit {
+                    let starts_count = d != 0 || self.count.is_some();
+                    if starts_count && !self.is_prefix(std::slice::from_ref(&event)) {
+                        let count = self.count.unwrap_or(0);
+                        self.count = Some(count.saturating_mul(10).saturating_add(d as usize));
+                        return Dispatch::Pending;
+                    }
+                }
+            }
+        }
+        self.pending.push(event);
+        let matched = self
+            .bindings
+            .iter()
+            .find(|(seq, _)| *seq == self.pending)
+            .map(|(_, binding)| binding.clone());
+        if let Some(binding) = matched {
+            let count = self.count.take();
+            self.pending.clear();
+            let count = match binding.sequence() {
+                KeySequence {
+                    takes_count: true,
+                    max_count: Some(max),
+                    ..
+                } => count.map(|c| min(c, *max)),
+                KeySequence {
+                    takes_count: true, ..
+                } => count,
+                _ => None,
+            };
+            return Dispatch::Matched(binding, count);
+        }
+        if self.is_prefix(&self.pending) {
+            return Dispatch::Pending;
+        }
+        self.reset();
+        Dispatch::Unbound
+    }
+
+    fn is_prefix(&self, events: &[Event]) -> bool {
+        self.bindings
+            .iter()
+            .any(|(seq, _)| seq.len() > events.len() && seq.starts_with(events))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the name of the action of `binding`, such as `SelectDown`.
+    fn action(binding: &KeyBindings) -> String {
+        let debug = format!("{:?}", binding);
+        debug.split('(').next().unwrap_or_default().to_string()
+    }
+
+    /// Feeds every character of `keys` and returns the last outcome.
+    fn feed(dispatcher: &mut KeyDispatcher, keys: &str) -> Dispatch {
+        let mut outcome = Dispatch::Unbound;
+        for c in keys.chars() {
+            outcome = dispatcher.dispatch(Event::Char(c));
         }
+        outcome
+    }
+
+    fn matched(outcome: Dispatch) -> Option<(String, Option<usize>)> {
+        match outcome {
+            Dispatch::Matched(binding, count) => Some((action(&binding), count)),
+            _ => None,
+        }
+    }
+
+    fn dispatcher() -> KeyDispatcher {
+        KeyDispatcher::new(KeyMaps::default().bindings().unwrap())
+    }
+
+    fn events(keys: &str) -> Vec<Event> {
+        keys.parse::<KeySequence>().unwrap().emit_sequence().1
+    }
+
+    #[test]
+    fn parses_modifiers() {
+        assert_eq!(events("C-r"), vec![Event::CtrlChar('r')]);
+        assert_eq!(events("C-i"), vec![Event::Key(Key::Tab)]);
+        assert_eq!(events("M-x"), vec![Event::AltChar('x')]);
+        assert_eq!(events("A-x"), vec![Event::AltChar('x')]);
+        assert_eq!(events("S-j"), vec![Event::Char('J')]);
+        assert_eq!(events("gg"), vec![Event::Char('g'), Event::Char('g')]);
+        // Too short to hold a modifier: plain characters.
+        assert_eq!(events("C-"), vec![Event::Char('C'), Event::Char('-')]);
+    }
+
+    #[test]
+    fn rejects_invalid_sequences() {
+        assert!("".parse::<KeySequence>().is_err());
+        assert!("C-ab".parse::<KeySequence>().is_err());
+        assert!("M-xy".parse::<KeySequence>().is_err());
+    }
+
+    #[test]
+    fn default_bindings_do_not_clash() {
+        assert!(KeyMaps::default().bindings().is_ok());
+    }
+
+    #[test]
+    fn rejects_prefixes_and_duplicates() {
+        let keymaps = KeyMaps {
+            select_first: "g".to_string(),
+            ..KeyMaps::default()
+        };
+        let error = keymaps.bindings().unwrap_err().to_string();
+        assert!(error.contains("\"g\" is a prefix of \"g"), "{}", error);
+
+        let keymaps = KeyMaps {
+            cut: "y".to_string(),
+            ..KeyMaps::default()
+        };
+        let error = keymaps.bindings().unwrap_err().to_string();
+        assert!(error.contains("\"y\" and \"y\" are the same keys"), "{}", error);
+
+        let keymaps = KeyMaps {
+            refresh: "S-j".to_string(),
+            preview_down: "J".to_string(),
+            ..KeyMaps::default()
+        };
+        let error = keymaps.bindings().unwrap_err().to_string();
+        assert!(error.contains("\"S-j\""), "{}", error);
+
+        // Unbound actions are skipped.
+        let keymaps = KeyMaps {
+            select_first: String::new(),
+            ..KeyMaps::default()
+        };
+        assert!(keymaps.bindings().is_ok());
+    }
+
+    #[test]
+    fn dispatches_sequences() {
+        let mut dispatcher = dispatcher();
+        assert_eq!(matched(feed(&mut dispatcher, "j")), Some(("SelectDown".into(), None)));
+        match dispatcher.dispatch(Event::Char('g')) {
+            Dispatch::Pending => {}
+            _ => panic!("g should start a sequence"),
+        }
+        assert_eq!(matched(feed(&mut dispatcher, "g")), Some(("SelectFirst".into(), None)));
+        assert_eq!(
+            matched(dispatcher.dispatch(Event::CtrlChar('r'))),
+            Some(("Refresh".into(), None))
+        );
+    }
+
+    #[test]
+    fn drops_unknown_continuations() {
+        let mut dispatcher = dispatcher();
+        match feed(&mut dispatcher, "g!") {
+            Dispatch::Unbound => {}
+            _ => panic!("g! is not bound"),
+        }
+        // The pending `g` was dropped.
+        assert_eq!(matched(feed(&mut dispatcher, "g")), None);
+        dispatcher.reset();
+        assert_eq!(matched(feed(&mut dispatcher, "j")), Some(("SelectDown".into(), None)));
+    }
+
+    #[test]
+    fn counts_prefixes() {
+        let mut dispatcher = dispatcher();
+        assert_eq!(matched(feed(&mut dispatcher, "5j")), Some(("SelectDown".into(), Some(5))));
+        assert_eq!(matched(feed(&mut dispatcher, "12k")), Some(("SelectUp".into(), Some(12))));
+        // A count before a multi-key sequence.
+        assert_eq!(matched(feed(&mut dispatcher, "3gt")), Some(("NextTab".into(), Some(3))));
+        // Bindings without a count ignore it.
+        assert_eq!(matched(feed(&mut dispatcher, "4p")), Some(("Paste".into(), None)));
+        // `0` does not start a count, but continues one.
+        assert_eq!(matched(feed(&mut dispatcher, "10j")), Some(("SelectDown".into(), Some(10))));
+        // The count is forgotten once used, or reset.
+        assert_eq!(matched(feed(&mut dispatcher, "j")), Some(("SelectDown".into(), None)));
+        feed(&mut dispatcher, "7");
+        dispatcher.reset();
+        assert_eq!(matched(feed(&mut dispatcher, "j")), Some(("SelectDown".into(), None)));
+    }
+
+    #[test]
+    fn clamps_counts() {
+        let mut dispatcher = dispatcher();
+        let outcome = feed(&mut dispatcher, "5000n");
+        assert_eq!(matched(outcome), Some(("NextMatch".into(), Some(MAX_MATCH_COUNT))));
+        let outcome = feed(&mut dispatcher, "99999999999999999999999j");
+        assert_eq!(matched(outcome), Some(("SelectDown".into(), Some(usize::MAX))));
     }
 }
@@ -1,36 +1,66 @@
+pub mod commands;
 pub mod keys;
 pub mod options;
 use crate::error::*;
 
 use dirs;
+use std::collections::BTreeMap;
 use std::fs as stdfs;
 use std::path::PathBuf;
 use toml;
 
-#[derive(Serialize, Deserialize)]
+/// Configuration of marcos, as read from `config.toml`.
+#[derive(Serialize, Deserialize, Default)]
 pub struct Config {
+    #[serde(default, rename = "KeyMaps")]
+    pub keymaps: keys::KeyMaps,
+    #[serde(default, rename = "Options")]
+    pub options: options::ConfigOptions,
+    /// Bookmarks available in every session, from a single character to a directory.
     #[serde(default)]
-    KeyMaps: keys::KeyMaps,
+    pub bookmarks: BTreeMap<String, String>,
     #[serde(default)]
-    Options: options::ConfigOptions,
+    pub frecency: FrecencyConfig,
+}
+
+/// Configuration of the frecency database, as read from the `[frecency]` table.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct FrecencyConfig {
+    /// Globs of the directories which are never recorded, e.g. `"/tmp/*"`.
+    pub exclude: Vec<String>,
 }
 
 impl Config {
+    /// Load the configuration from `$XDG_CONFIG_HOME/marcos/config.toml`, creating an empty
+    /// file if none exists.
+    ///
+    /// A file which cannot be created or read is reported as `ErrorKind::IoOnPath`. A
+    /// malformed file is reported as `ErrorKind::TomlDeError` along with the file name and the
+    /// position of the offending line.
     pub fn load() -> Result<Self> {
         let data_path: PathBuf = dirs::config_dir().ok_or(ErrorKind::DirNotFound {
             dirname: String::from("CONFIG_DIR"),
         })?;
         let data_path = data_path.join("marcos");
         if !data_path.exists() {
-            stdfs::create_dir_all(&data_path).expect("Cannot create data_dir");
+            stdfs::create_dir_all(&data_path).on_path(&data_path)?;
         }
         let config_file = data_path.join("config.toml");
-        debug!("Loading theme from file: {:?}", config_file);
+        debug!("Loading config from file: {:?}", config_file);
         if !config_file.is_file() {
-            stdfs::File::create(&config_file).expect("Failed to create asset file");
+            stdfs::File::create(&config_file).on_path(&config_file)?;
         }
-        let config_str = stdfs::read_to_string(config_file)?;
-        let config_data: Config = toml::from_str(config_str.as_str())?;
+        let config_str = stdfs::read_to_string(&config_file).on_path(&config_file)?;
+        let config_data: Config = toml::from_str(config_str.as_str()).map_err(|e| {
+            let (line, column) = e.line_col().map(|(l, c)| (l + 1, c + 1)).unwrap_or((0, 0));
+            ErrorKind::TomlDeError {
+                file: config_file.to_string_lossy().into_owned(),
+                line,
+                column,
+                cause: e,
+            }
+        })?;
         Ok(config_data)
     }
 }
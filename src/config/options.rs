@@ -1,27 +1,102 @@
-#[derive(Serialize, Deserialize)]
-enum Size {
+use std::str::FromStr;
+
+use crate::error::*;
+
+/// Metric used to display sizes of entries.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Size {
     Bits,
     Bytes,
 }
 
-#[derive(Serialize, Deserialize)]
-enum StatusPosition {
+/// Position of the status bar in the main layout.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusPosition {
     Top,
     Bottom,
 }
 
-#[derive(Serialize, Deserialize)]
+/// Key by which the entries of a directory are sorted.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    Name,
+    Size,
+    Modified,
+    Extension,
+}
+
+impl FromStr for SortBy {
+    type Err = Error;
+
+    fn from_str(key: &str) -> Result<Self> {
+        match key {
+            "name" => Ok(SortBy::Name),
+            "size" => Ok(SortBy::Size),
+            "modified" | "mtime" => Ok(SortBy::Modified),
+            "extension" | "ext" => Ok(SortBy::Extension),
+            _ => Err(ErrorKind::InvalidCommand {
+                reason: format!("Unknown sort key: {} (name, size, modified, extension)", key),
+            }.into()),
+        }
+    }
+}
+
+/// Names of the options, as accepted by `ConfigOptions::set`.
+pub const OPTION_NAMES: &[&str] = &[
+    "size",
+    "show_hidden",
+    "confirm",
+    "show_images",
+    "status_position",
+    "shorten_title",
+    "preview_max_size",
+    "grep_max_size",
+    "delay_idle",
+    "line_numbers",
+    "tab_width",
+    "preview_wrap",
+    "show_popup",
+    "sort",
+    "sort_reverse",
+    "search_regex",
+];
+
+/// Names of the options holding a boolean, which `:set` also accepts as `name` and `noname`.
+pub const BOOLEAN_OPTIONS: &[&str] = &[
+    "show_hidden",
+    "confirm",
+    "show_images",
+    "line_numbers",
+    "preview_wrap",
+    "show_popup",
+    "sort_reverse",
+    "search_regex",
+];
+
+/// Options read from the `[Options]` table of `config.toml`.
+/// Any missing option falls back to its default value.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
 pub struct ConfigOptions {
-    size: Size,
-    show_hidden: bool,
-    confirm: bool,
-    show_images: bool,
-    status_position: StatusPosition,
-    shorten_title: usize,
-    preview_max_size: usize,
-    delay_idle: usize,
-    line_numbers: bool,
-    show_popup: bool,
+    pub size: Size,
+    pub show_hidden: bool,
+    pub confirm: bool,
+    pub show_images: bool,
+    pub status_position: StatusPosition,
+    pub shorten_title: usize,
+    pub preview_max_size: usize,
+    pub grep_max_size: u64,
+    pub delay_idle: usize,
+    pub line_numbers: bool,
+    pub tab_width: usize,
+    pub preview_wrap: bool,
+    pub show_popup: bool,
+    pub sort: SortBy,
+    pub sort_reverse: bool,
+    pub search_regex: bool,
 }
 
 impl Default for ConfigOptions {
@@ -35,9 +110,70 @@ impl Default for ConfigOptions {
             status_position: StatusPosition::Bottom,
             shorten_title: 0,
             preview_max_size: 102400,
+            grep_max_size: 1048576,
             delay_idle: 2000,
             line_numbers: false,
+            tab_width: 4,
+            preview_wrap: true,
             show_popup: false,
+            sort: SortBy::Name,
+            sort_reverse: false,
+            search_regex: false,
+        }
+    }
+}
+
+impl ConfigOptions {
+    /// Sets the option `name` from its textual `value`, as given to `:set`.
+    ///
+    /// `status_position` and `delay_idle` only take effect on the next start.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        match name {
+            "size" => {
+                self.size = match value {
+                    "bits" => Size::Bits,
+                    "bytes" => Size::Bytes,
+                    _ => return Err(invalid_value(name, value)),
+                }
+            }
+            "show_hidden" => self.show_hidden = parse_value(name, value)?,
+            "confirm" => self.confirm = parse_value(name, value)?,
+            "show_images" => self.show_images = parse_value(name, value)?,
+            "status_position" => {
+                self.status_position = match value {
+                    "top" => StatusPosition::Top,
+                    "bottom" => StatusPosition::Bottom,
+                    _ => return Err(invalid_value(name, value)),
+                }
+            }
+            "shorten_title" => self.shorten_title = parse_value(name, value)?,
+            "preview_max_size" => self.preview_max_size = parse_value(name, value)?,
+            "grep_max_size" => self.grep_max_size = parse_value(name, value)?,
+            "delay_idle" => self.delay_idle = parse_value(name, value)?,
+            "line_numbers" => self.line_numbers = parse_value(name, value)?,
+            "tab_width" => self.tab_width = parse_value(name, value)?,
+            "preview_wrap" => self.preview_wrap = parse_value(name, value)?,
+            "show_popup" => self.show_popup = parse_value(name, value)?,
+            "sort" => self.sort = value.parse()?,
+            "sort_reverse" => self.sort_reverse = parse_value(name, value)?,
+            "search_regex" => self.search_regex = parse_value(name, value)?,
+            _ => {
+                return Err(ErrorKind::InvalidCommand {
+                    reason: format!("Unknown option: {}", name),
+                }.into())
+            }
         }
+        Ok(())
     }
 }
+
+/// Parses the value of an option of type `T`.
+fn parse_value<T: FromStr>(name: &str, value: &str) -> Result<T> {
+    value.parse().map_err(|_| invalid_value(name, value))
+}
+
+fn invalid_value(name: &str, value: &str) -> Error {
+    ErrorKind::InvalidCommand {
+        reason: format!("Invalid value for {}: {}", name, value),
+    }.into()
+}
@@ -1,37 +1,64 @@
 //! Module contains functions related to core functionalities of the app.
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::env;
 use std::fs as stdfs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process;
 use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 
-use cursive::event::{Event, EventResult, Key};
+use cursive::event::{Event, Key};
 #[allow(unused_imports)]
 use cursive::traits::{Boxable, Identifiable, Scrollable};
 use cursive::views::*;
 use cursive::align::*;
-use cursive::theme::*;
 use cursive::view::Position;
 use cursive::Cursive;
 
 use dirs;
 
-use alphanumeric_sort::compare_os_str;
-use walkdir;
-use walkdir::WalkDir;
-
-use mime_guess::guess_mime_type;
-use mime_guess::Mime;
-
 use crate::config;
+use crate::config::commands;
+use crate::config::commands::Command;
+use crate::config::keys::{Dispatch, KeyBindings, KeyDispatcher};
+use crate::config::options::{ConfigOptions, StatusPosition};
+use crate::config::Config;
+use crate::core::bookmarks as marks;
+use crate::core::finder;
+use crate::core::grep as hits;
+use crate::core::jobs;
+use crate::core::jobs::{JobKind, JobManager};
+use crate::core::jumps;
+use crate::core::preview;
+use crate::core::rename as batch;
+use crate::core::trash;
 use crate::error::*;
+use crate::fs::find;
+use crate::fs::grep::{self, Hit};
+use crate::fs::ops;
+use crate::fs::ops::{Clipboard, Conflict, Mode};
+use crate::fs::journal::{Journal, Operation};
+use crate::fs::rename::{self as bulk, Plan};
+use crate::fs::trash::Trash;
 use crate::fs::Entry;
-use crate::ui::MultiSelectView;
-use crate::ui::Tab;
-use crate::utils::{filter, info, logger};
+use crate::ui::console;
+use crate::ui::editor;
+use crate::ui::hex;
+use crate::ui::picker;
+use crate::ui::prompt;
+use crate::ui::{MultiSelectView, PreviewView};
+use crate::ui::tab;
+use crate::ui::{Found, Tab};
+use crate::utils::bookmarks::{self, Bookmarks};
+use crate::utils::frecency::Frecency;
+use crate::utils::history::History;
+use crate::utils::jumplist::JumpList;
+use crate::utils::search::{Matcher, Search};
+use crate::utils::{info, logger, sort};
 
 /// Create a new instance of marcos with the specified backend.
 ///
@@ -49,10 +76,22 @@ pub fn init(path: &str, log_file: Option<&str>, log_level: Option<&str>) -> Resu
         println!("Incorrect path or unaccessible directory! Please cheack PATH");
         process::exit(1);
     }
-    let _app_config = config::Config::load();
-    let mut app = App::new()?;
+    // A malformed config.toml stops marcos, while an unreadable one only falls back to the
+    // defaults, as it cannot be fixed from here.
+    let (app_config, problem) = match config::Config::load() {
+        Ok(app_config) => (app_config, None),
+        Err(e) => match e.kind() {
+            ErrorKind::TomlDeError { .. } => return Err(e),
+            _ => (Config::default(), Some(format!("Cannot load config.toml: {}", e))),
+        },
+    };
+    let mut app = App::new(app_config)?;
     app.add_tab(1, path)?;
-    app.load_bindings();
+    app.load_bindings()?;
+    if let Some(problem) = problem {
+        warn!("{}", problem);
+        set_status(&mut app.siv, &problem);
+    }
     Ok(app)
 }
 
@@ -61,44 +100,120 @@ pub fn init(path: &str, log_file: Option<&str>, log_level: Option<&str>) -> Resu
 pub struct App {
     /// The main application, the cursive instance.
     pub siv: Cursive,
-    /// The vector of tabs
-    // pub vec_tabs: HashMap<String, Tab>,
-    pub vec_tabs: Rc<RefCell<HashMap<u32, Tab>>>,
-    /// The index of focused tab starting from 0.
-    focused_tab: usize,
+    /// State shared with the callbacks registered on `siv`.
+    pub state: State,
     /// The index of focused entry starting from 0.
     focused_entry: usize,
 }
 
+/// State of the app shared between `App` and the callbacks registered on the cursive instance.
+#[derive(Clone)]
+pub struct State {
+    /// The tabs, indexed by their title.
+    // pub vec_tabs: HashMap<String, Tab>,
+    pub vec_tabs: Rc<RefCell<HashMap<u32, Tab>>>,
+    /// The title of the focused tab.
+    pub focused_tab: Rc<Cell<u32>>,
+    /// Configuration loaded from `config.toml`.
+    pub config: Rc<RefCell<Config>>,
+    /// Entries yanked or cut, waiting to be pasted.
+    /// Shared with the jobs moving them, which empty it once done.
+    pub clipboard: Arc<Mutex<Option<Clipboard>>>,
+    /// Background jobs running file operations.
+    pub jobs: JobManager,
+    /// Journal of the file operations, shared with the jobs recording them.
+    pub journal: Arc<Mutex<Journal>>,
+    /// Lines entered in the consoles.
+    pub history: Rc<RefCell<History>>,
+    /// The last search, repeated by `n` and `N`.
+    pub search: Rc<RefCell<Option<Search>>>,
+    /// Directories bookmarked with `mX`, including the position before the last jump.
+    pub bookmarks: Rc<RefCell<Bookmarks>>,
+    /// Directories visited, ranked by frecency for `:z`.
+    pub frecency: Rc<RefCell<Frecency>>,
+    /// Results of the `:find` jobs which are over, waiting to be shown.
+    pub finds: Arc<Mutex<Vec<FindResults>>>,
+    /// Results of the `:grep` jobs which are over, waiting to be shown.
+    pub greps: Arc<Mutex<Vec<GrepResults>>>,
+}
+
+/// Results of a `:find` job, to be shown by the tab it was run from.
+pub struct FindResults {
+    tab: u32,
+    dir: PathBuf,
+    found: Found,
+    truncated: bool,
+}
+
+/// Results of a `:grep` job, shown over the tab it was run from.
+pub struct GrepResults {
+    tab: u32,
+    dir: PathBuf,
+    matcher: Matcher,
+    hits: Vec<Hit>,
+    truncated: bool,
+}
+
 impl App {
     /// Create a new instance of cursive with default global callbacks.
     /// `q` is used to quit the cursive instance.
     ///
     /// TODO `:` is used to open the command box
-    pub fn new() -> Result<Self> {
+    pub fn new(config: Config) -> Result<Self> {
         let data_path: PathBuf = dirs::config_dir().ok_or(ErrorKind::DirNotFound {
             dirname: String::from("CONFIG_DIR"),
         })?;
         let data_path = data_path.join("marcos");
-        if !data_path.exists() {
-            stdfs::create_dir_all(&data_path).expect("Cannot create data_dir");
-        }
         let asset_file = data_path.join("style.toml");
         debug!("Loading theme from file: {:?}", asset_file);
         if !asset_file.is_file() {
-            stdfs::File::create(&asset_file).expect("Failed to create asset file");
+            let created =
+                stdfs::create_dir_all(&data_path).and_then(|_| stdfs::File::create(&asset_file));
+            if let Err(e) = created {
+                warn!("Cannot create {:?}: {}", asset_file, e);
+            }
         }
+        let home = dirs::home_dir().unwrap_or_default();
+        let predefined = config
+            .bookmarks
+            .iter()
+            .map(|(key, dir)| (key.clone(), commands::expand_path(dir, &home)))
+            .collect();
+        let bookmarks = Bookmarks::load(predefined).unwrap_or_else(|e| {
+            warn!("Cannot load the bookmarks: {}", e);
+            Bookmarks::default()
+        });
+        let frecency = Frecency::load(&config.frecency.exclude).unwrap_or_else(|e| {
+            warn!("Cannot load the frecency database: {}", e);
+            Frecency::default()
+        });
         let mut siv = Cursive::default();
+        let status_position = config.options.status_position;
+        let config = Rc::new(RefCell::new(config));
+
+        let vec_tabs = Rc::new(RefCell::new(HashMap::<u32, Tab>::new()));
+        let focused_tab = Rc::new(Cell::new(0));
 
         // Create empty views
         let p_widget = MultiSelectView::<PathBuf>::new().with_id("parent");
-        let c_widget = MultiSelectView::<PathBuf>::new().on_select(update_info);
+        let (c_config, c_tabs, c_focused) = (config.clone(), vec_tabs.clone(), focused_tab.clone());
+        let c_widget = MultiSelectView::<PathBuf>::new().on_select(move |s, entry| {
+            // The entry focused when the directory was left, to be highlighted in its preview.
+            let child = c_tabs.try_borrow().ok().and_then(|tabs| {
+                tabs.get(&c_focused.get())
+                    .and_then(|tab| tab.focused.get(entry).cloned())
+            });
+            update_info(s, entry, child, &c_config.borrow().options)
+        });
         let c_widget = OnEventView::new(c_widget).with_id("current");
-        let preview_widget = TextView::new("").with_id("preview");
+        let preview_widget = PreviewView::new().with_id("preview");
         let top_widget = LinearLayout::horizontal()
             .child(TextView::new(info::user_info())
                    .h_align(HAlign::Left)
                    .with_id("topbar/left"))
+            .child(TextView::new("")
+                   .h_align(HAlign::Left)
+                   .with_id("topbar/tabs"))
             .child(TextView::new(info::user_info())
                    .h_align(HAlign::Left)
                    .with_id("topbar/center").full_width())
@@ -107,7 +222,11 @@ impl App {
                    .with_id("topbar/right").full_width());
         // let top_bar = TextView::new(format!("{} {}", info::user_info(), info::disk_info("/")))
         //     .with_id("topbar");
-        let mut status_bar = HideableView::new(TextView::new("Status").with_id("status"));
+        let mut status_bar = HideableView::new(
+            LinearLayout::horizontal()
+                .child(TextView::new("Status").with_id("status").full_width())
+                .child(TextView::new("").with_id("status/jobs")),
+        );
         status_bar.unhide();
         // let console = EditView::new().filler(">").with_id("console");
         // let console = HideableView::new(console);
@@ -129,115 +248,99 @@ impl App {
                 .full_height(),
         );
         panes.add_child(Panel::new(preview_widget).full_width().full_height());
-        let h_panes = LinearLayout::vertical()
-            .child(top_widget.full_width())
-            .child(panes)
-            // .child(console)
-            .child(status_bar);
+        let h_panes = match status_position {
+            StatusPosition::Top => LinearLayout::vertical()
+                .child(top_widget.full_width())
+                .child(status_bar)
+                .child(panes),
+            StatusPosition::Bottom => LinearLayout::vertical()
+                .child(top_widget.full_width())
+                .child(panes)
+                // .child(console)
+                .child(status_bar),
+        };
 
         siv.add_layer(h_panes);
         siv.add_global_callback(Event::CtrlChar('w'), |s| s.quit());
-        siv.add_global_callback('q', |s| s.quit());
-        let vec_tabs = Rc::new(RefCell::new(HashMap::<u32, Tab>::new()));
+        let jobs = JobManager::new(siv.cb_sink().clone());
+        let journal = Journal::load().unwrap_or_else(|e| {
+            warn!("Cannot load the journal, undo history is lost: {}", e);
+            Journal::default()
+        });
+        let history = History::load().unwrap_or_else(|e| {
+            warn!("Cannot load the console history: {}", e);
+            History::default()
+        });
 
         debug!("Loading theme resource file");
-        siv.load_theme_file(asset_file).expect("Cannot find file!");
+        if let Err(e) = siv.load_theme_file(&asset_file) {
+            warn!("Cannot load the theme from {:?}: {:?}", asset_file, e);
+        }
         Ok(Self {
             siv,
-            vec_tabs,
+            state: State {
+                vec_tabs,
+                focused_tab,
+                config,
+                clipboard: Arc::new(Mutex::new(None)),
+                jobs,
+                journal: Arc::new(Mutex::new(journal)),
+                history: Rc::new(RefCell::new(history)),
+                search: Rc::new(RefCell::new(None)),
+                bookmarks: Rc::new(RefCell::new(bookmarks)),
+                frecency: Rc::new(RefCell::new(frecency)),
+                finds: Arc::new(Mutex::new(Vec::new())),
+                greps: Arc::new(Mutex::new(Vec::new())),
+            },
             focused_entry: 0,
-            focused_tab: 0,
         })
     }
 
-    /// Funtion to load key-bindings.
-    pub fn load_bindings(&mut self) {
-        let v_clone = self.vec_tabs.clone();
-        self.siv.add_global_callback('h', move |s: &mut Cursive| {
-            debug!("Inside global callback h");
-            // Get current_view selection index
-            let mut current_selection = None;
-            s.call_on_id(
-                "current",
-                |event_view: &mut OnEventView<MultiSelectView<PathBuf>>| {
-                    let view = event_view.get_inner();
-                    current_selection = view.selected_id();
-                },
-            );
-            if let Some(mut tab) = v_clone.borrow_mut().get_mut(&1) {
-                tab.focused
-                    .insert(PathBuf::from(&tab.c_view), current_selection.unwrap_or(0));
-                tab.go_back();
-                // tab.c_focused = current_selection;
-                App::update_tab(s, &mut tab);
-                s.call_on_id("topbar/center", |view: &mut TextView| {
-                    let mut text: TextContent = view.get_shared_content();
-                    text.set_content(format!(" {}", tab.c_view.to_str().unwrap()));
-                });
-            };
-        });
+    /// Loads key-bindings from the `[KeyMaps]` table of the configuration.
+    ///
+    /// Every event used by a key sequence is routed to a shared `KeyDispatcher`, which buffers
+    /// multi-key sequences and count prefixes, and runs the bound action once a sequence is
+    /// complete.
+    pub fn load_bindings(&mut self) -> Result<()> {
+        let bindings = self.state.config.borrow().keymaps.bindings()?;
+        let dispatcher = Rc::new(RefCell::new(KeyDispatcher::new(bindings)));
+        let events = dispatcher.borrow().events();
+        for event in events {
+            let d_clone = dispatcher.clone();
+            let s_clone = self.state.clone();
+            let e_clone = event.clone();
+            self.siv.add_global_callback(event, move |s: &mut Cursive| {
+                let dispatch = d_clone.borrow_mut().dispatch(e_clone.clone());
+                if let Dispatch::Matched(binding, count) = dispatch {
+                    debug!("Running key binding {:?} with count {:?}", binding, count);
+                    run_binding(s, &binding, count, &s_clone);
+                }
+            });
+        }
 
-        let v_clone2 = self.vec_tabs.clone();
-        self.siv.add_global_callback('l', move |s: &mut Cursive| {
-            s.call_on_id(
-                "current",
-                |event_view: &mut OnEventView<MultiSelectView<PathBuf>>| {
-                    let event = event_view.get_inner_mut();
-                    if let Some(path) = event.selection() {
-                        if path.is_dir() {
-                            if let Some(mut tab) = v_clone2.borrow_mut().get_mut(&1) {
-                                debug!("Moving forward to path {:?}", path);
-                                tab.go_forward(path.to_path_buf());
-                            };
-                        } // if
-                    };
-                },
-            );
-            if let Some(mut tab) = v_clone2.borrow_mut().get_mut(&1) {
-                App::update_tab(s, &mut tab);
-                s.call_on_id("topbar/center", |view: &mut TextView| {
-                    let mut text = view.get_shared_content();
-                    text.set_content(format!(" {}",tab.c_view.to_str().unwrap()));
-                });
-            }
-        });
+        // Listings may have changed once a background job is over.
+        let s_clone = self.state.clone();
+        self.siv
+            .add_global_callback(jobs::finished_event(), move |s: &mut Cursive| {
+                refresh(s, &s_clone);
+                show_found(s, &s_clone);
+                show_hits(s, &s_clone);
+            });
 
-        self.siv.call_on_id(
-            "current",
-            |event_view: &mut OnEventView<MultiSelectView<PathBuf>>| {
-                event_view.set_on_pre_event_inner('k', |s| {
-                    let cb = s.select_up(1);
-                    Some(EventResult::Consumed(Some(cb)))
+        // Alt-1 to Alt-9 jump to the tab with the given title.
+        for title in 1..10 {
+            let s_clone = self.state.clone();
+            if let Some(c) = ::std::char::from_digit(title, 10) {
+                self.siv.add_global_callback(Event::AltChar(c), move |s: &mut Cursive| {
+                    switch_tab(s, &s_clone, title);
                 });
-                event_view.set_on_pre_event_inner('j', |s| {
-                    let cb = s.select_down(1);
-                    Some(EventResult::Consumed(Some(cb)))
-                });
-            },
-        );
-
-        self.siv.add_global_callback('/', |c: &mut Cursive| {
-            debug!("You pressed search key");
-            c.add_layer(create_console("/"));
-            let s = c.screen_mut();
-            let l = LayerPosition::FromFront(0);
-            let pos = s.offset().saturating_add((9000, 9000));
-            let p = Position::absolute(pos);
-            s.reposition_layer(l, p);
-        });
-
-        self.siv.add_global_callback('?', |c: &mut Cursive| {
-            debug!("You pressed search key");
-            c.add_layer(create_console("?"));
-            let s = c.screen_mut();
-            let l = LayerPosition::FromFront(0);
-            let pos = s.offset().saturating_add((9000, 9000));
-            let p = Position::absolute(pos);
-            s.reposition_layer(l, p);
-        });
+            }
+        }
 
         // Cancels current action.
-        self.siv.add_global_callback(Event::Key(Key::Esc), |s: &mut Cursive| {
+        let d_clone = dispatcher.clone();
+        self.siv.add_global_callback(Event::Key(Key::Esc), move |s: &mut Cursive| {
+            d_clone.borrow_mut().reset();
             let mut exists: bool = false;
             {
                 let stack_view = s.screen_mut();
@@ -246,102 +349,37 @@ impl App {
                     exists = true;
                 }
             }
-            if exists {s.pop_layer();}
+            if exists {
+                s.pop_layer();
+            } else {
+                let visual = with_current(s, |view| {
+                    view.clear_highlight();
+                    let visual = view.is_visual();
+                    view.stop_visual();
+                    visual
+                });
+                if visual == Some(true) {
+                    show_marked(s);
+                }
+            }
         });
+        Ok(())
     }
 
-    /// [Experimental] Adds a new tab to the main view. Currently only single tab is supported
-    /// for the sake of simplicity. Multiple tabs support will land in near future.
+    /// Adds a new tab with the given title to the main view, showing `path`, and focuses it.
+    /// An existing tab with the same title is replaced.
     pub fn add_tab(&mut self, name: u32, path: PathBuf) -> Result<()> {
-        let mut tab = Tab::from(name, &path)?;
-        self.siv.call_on_id("topbar/center", |view: &mut TextView| {
-            let mut current_text: TextContent = view.get_shared_content();
-            current_text.set_content(format!(" {}", path.to_str().unwrap()));
-        });
-        self.siv.call_on_id(
-            "current",
-            |event_view: &mut OnEventView<MultiSelectView<PathBuf>>| {
-                let view = event_view.get_inner_mut();
-                view.clear();
-                for entry in App::get_path_iter(&tab.c_view)
-                    .filter_entry(|e| e.path().is_dir() && !filter::is_hidden(e))
-                {
-                    let entry = entry.unwrap();
-                    match entry.file_name().to_str() {
-                        Some(c) => {
-                            view.add_item(format!(r"  {}", c), PathBuf::from(entry.path()))
-                        }
-                        None => {}
-                    }
-                }
-                for entry in App::get_path_iter(&tab.c_view)
-                    .filter_entry(|e| e.path().is_file() && !filter::is_hidden(e))
-                {
-                    let entry = entry.unwrap();
-                    match entry.file_name().to_str() {
-                        Some(c) => {
-                            view.add_item(format!(r"  {}", c), PathBuf::from(entry.path()))
-                        }
-                        None => {}
-                    };
-                }
-                view.set_selection(0);
-            },
-        );
-
-        let mut i: usize = 0;
-        self.siv
-            .call_on_id("parent", |view: &mut MultiSelectView<PathBuf>| {
-                view.clear();
-                debug!("siv call on id parent {:?}", tab.p_view.to_str());
-                match tab.p_view.to_str() {
-                    Some("root") => {
-                        view.add_item("/", PathBuf::from("/"));
-                        view.set_enabled(false);
-                        view.set_selection(0);
-                    }
-                    Some(_) | None => {
-                        for (index, entry) in App::get_path_iter(&tab.p_view)
-                            .filter_entry(|e| e.path().is_dir() && !filter::is_hidden(e))
-                            .enumerate()
-                        {
-                            let entry = entry.unwrap();
-                            if entry.path() == &tab.c_view {
-                                i = index;
-                            }
-                            match entry.file_name().to_str() {
-                                Some(c) => view
-                                    .add_item(format!("  {}", c), PathBuf::from(entry.path())),
-                                None => {}
-                            };
-                        }
-                        for entry in App::get_path_iter(&tab.p_view)
-                            .filter_entry(|e| e.path().is_file() && !filter::is_hidden(e))
-                        {
-                            let entry = entry.unwrap();
-                            match entry.file_name().to_str() {
-                                Some(c) => view
-                                    .add_item(format!("  {}", c), PathBuf::from(entry.path())),
-                                None => {}
-                            };
-                        }
-                        view.set_selection(i);
-                        view.set_enabled(false);
-                    } // None
-                };
-            });
-        tab.focused.insert(PathBuf::from(&tab.p_view), i);
-        // tab.p_focused = i;
-        debug!("Value of tab: {:?}", tab);
-        self.vec_tabs.borrow_mut().insert(1, tab);
-        self.focused_entry = i;
-        debug!("Value of arr: {:?}", self.vec_tabs.borrow());
+        open_tab(&mut self.siv, &self.state, name, path)?;
+        if let Some(tab) = self.state.vec_tabs.borrow().get(&name) {
+            self.focused_entry = *tab.focused.get(&tab.p_view).unwrap_or(&0);
+        }
+        debug!("Value of arr: {:?}", self.state.vec_tabs.borrow());
         Ok(())
     }
 
     /// Funtion which updates the content of `Tab` when you go forward or
     /// backward in a hierarchy.
-    fn update_tab(siv: &mut Cursive, tab: &mut Tab) {
+    fn update_tab(siv: &mut Cursive, tab: &mut Tab, options: &ConfigOptions) {
         // let focused = if !forward { tab.p_focused } else {
         //     if let Some(c) = tab.c_focused {c}
         //     else {0}
@@ -352,29 +390,26 @@ impl App {
                 let c_focused = tab.focused.get(&tab.c_view).unwrap_or(&0usize);
                 debug!("Got current selection of: {:?}", c_focused);
                 let view = event_view.get_inner_mut();
+                // Entries stay marked when the same directory is listed again.
+                let marked = view.selected_items();
                 view.clear();
-                for entry in App::get_path_iter(&tab.c_view)
-                    .filter_entry(|e| e.path().is_dir() && !filter::is_hidden(e))
-                {
-                    let entry = entry.unwrap();
-                    match entry.file_name().to_str() {
-                        Some(c) => {
-                            view.add_item(format!(r"  {}", c), PathBuf::from(entry.path()))
-                        }
-                        None => {}
+                if let Some(ref mut found) = tab.found {
+                    // Entries deleted or moved away since the search are dropped.
+                    found.paths.retain(|path| path.symlink_metadata().is_ok());
+                    for path in &found.paths {
+                        let name = path.strip_prefix(&tab.c_view).unwrap_or(path);
+                        view.add_item(tab::label(path, &name.to_string_lossy()), path.clone());
                     }
+                    view.select_where(true, |path| marked.iter().any(|m| **m == *path));
+                    view.set_selection(found.focused);
+                    return;
                 }
-                for entry in App::get_path_iter(&tab.c_view)
-                    .filter_entry(|e| e.path().is_file() && !filter::is_hidden(e))
-                {
-                    let entry = entry.unwrap();
-                    match entry.file_name().to_str() {
-                        Some(c) => {
-                            view.add_item(format!(r"  {}", c), PathBuf::from(entry.path()))
-                        }
-                        None => {}
-                    };
+                for entry in sort::listing(&tab.c_view, options) {
+                    if let Some(name) = entry.file_name().to_str() {
+                        view.add_item(tab::label(entry.path(), name), PathBuf::from(entry.path()))
+                    }
                 }
+                view.select_where(true, |path| marked.iter().any(|m| **m == *path));
                 // TODO keep last selection
                 view.set_selection(*c_focused);
                 //view.set_selection(focused);
@@ -384,37 +419,21 @@ impl App {
         let mut i: usize = 0;
         siv.call_on_id("parent", |view: &mut MultiSelectView<PathBuf>| {
             view.clear();
+            view.set_enabled(false);
             match tab.p_view.to_str() {
                 Some("root") => {
                     view.add_item("/", PathBuf::from("/"));
                     view.set_selection(0);
                 }
                 Some(_) | None => {
-                    for (index, entry) in App::get_path_iter(&tab.p_view)
-                        .filter_entry(|e| e.path().is_dir() && !filter::is_hidden(e))
-                        .enumerate()
-                    {
-                        let entry = entry.unwrap();
-                        if entry.path() == &tab.c_view {
+                    for (index, entry) in sort::listing(&tab.p_view, options).iter().enumerate() {
+                        let path = entry.path();
+                        if path == tab.c_view.as_path() {
                             i = index;
                         }
-                        match entry.file_name().to_str() {
-                            Some(c) => {
-                                view.add_item(format!("  {}", c), PathBuf::from(entry.path()))
-                            }
-                            None => {}
-                        };
-                    }
-                    for entry in App::get_path_iter(&tab.p_view)
-                        .filter_entry(|e| e.path().is_file() && !filter::is_hidden(e))
-                    {
-                        let entry = entry.unwrap();
-                        match entry.file_name().to_str() {
-                            Some(c) => {
-                                view.add_item(format!("  {}", c), PathBuf::from(entry.path()))
-                            }
-                            None => {}
-                        };
+                        if let Some(name) = entry.file_name().to_str() {
+                            view.add_item(tab::label(path, name), path.to_path_buf())
+                        }
                     }
                     view.set_selection(i);
                 }
@@ -423,14 +442,14 @@ impl App {
         // tab.p_focused = i;
         tab.focused.insert(PathBuf::from(&tab.p_view), i);
         debug!("Updated focused for parent: {:?}", tab);
-    }
-
-    fn get_path_iter(path: &PathBuf) -> walkdir::IntoIter {
-        WalkDir::new(path)
-            .max_depth(1)
-            .min_depth(1)
-            .sort_by(|a, b| compare_os_str(&a.file_name(), &b.file_name()))
-            .into_iter()
+        siv.call_on_id("topbar/center", |view: &mut TextView| {
+            let mut text: TextContent = view.get_shared_content();
+            let title = format_title(&tab.c_view, options.shorten_title);
+            match tab.found {
+                Some(ref found) => text.set_content(format!(" {} [find {}]", title, found.query)),
+                None => text.set_content(format!(" {}", title)),
+            }
+        });
     }
 
     #[allow(dead_code)]
@@ -440,44 +459,1468 @@ impl App {
 
     /// Funtion to handle the event loop.
     ///
-    /// Currently does a naive call to `siv.run()`
+    /// Currently does a naive call to `siv.run()`, once the checks for changes of the current
+    /// directory are started.
     pub fn run(&mut self) {
+        self.watch_changes();
         self.siv.run();
     }
+
+    /// Lists the current directory again whenever it is modified on disk. A thread sends
+    /// `idle_event` every `delay_idle` milliseconds, and the modification time of the directory
+    /// is compared with that of the previous check. `0` turns the checks off.
+    fn watch_changes(&mut self) {
+        let delay = self.state.config.borrow().options.delay_idle as u64;
+        if delay == 0 {
+            return;
+        }
+        let (s_clone, checked) = (self.state.clone(), RefCell::new(None));
+        self.siv.add_global_callback(idle_event(), move |s: &mut Cursive| {
+            let dir = match current_dir(&s_clone) {
+                Some(dir) => dir,
+                None => return,
+            };
+            let mtime = dir.metadata().and_then(|metadata| metadata.modified()).ok();
+            let changed = match checked.replace(Some((dir.clone(), mtime))) {
+                Some((previous, time)) => previous == dir && time != mtime,
+                None => false,
+            };
+            if changed {
+                refresh(s, &s_clone);
+            }
+        });
+        let sink = self.siv.cb_sink().clone();
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_millis(delay));
+            sink.send(Box::new(|s: &mut Cursive| s.on_event(idle_event())));
+        });
+    }
 }
 
-/// Funtion to update status bar content and preview widget content on selection change.
-/// First the status bar is updated to show relevant permission details and size of file.
-/// Then preview is updated to reflect details about the selected entry.
-fn update_info(siv: &mut Cursive, entry: &PathBuf) {
-    siv.call_on_id("preview", |view: &mut TextView| {
-        if !entry.is_dir() {
-            let data: Mime = guess_mime_type(entry);
-            view.set_content(format!("{}/{}", data.type_(), data.subtype()))
+/// Event sent to the cursive instance every `delay_idle` milliseconds, to check whether the
+/// current directory changed.
+fn idle_event() -> Event {
+    Event::Unknown(b"marcos:idle".to_vec())
+}
+
+/// Runs the action bound to a key sequence.
+fn run_binding(s: &mut Cursive, binding: &KeyBindings, count: Option<usize>, state: &State) {
+    match binding {
+        KeyBindings::Quit(_) => quit(s, state, false),
+        KeyBindings::SelectUp(_) => {
+            let cb = with_current(s, |view| view.select_up(count.unwrap_or(1)));
+            if let Some(cb) = cb {
+                cb(s);
+            }
+        }
+        KeyBindings::SelectDown(_) => {
+            let cb = with_current(s, |view| view.select_down(count.unwrap_or(1)));
+            if let Some(cb) = cb {
+                cb(s);
+            }
+        }
+        KeyBindings::SelectFirst(_) => {
+            let cb = with_current(s, |view| view.set_selection(0));
+            if let Some(cb) = cb {
+                cb(s);
+            }
+        }
+        KeyBindings::SelectLast(_) => {
+            let cb = with_current(s, |view| {
+                let last = view.len().saturating_sub(1);
+                view.set_selection(count.map(|n| n.saturating_sub(1)).unwrap_or(last))
+            });
+            if let Some(cb) = cb {
+                cb(s);
+            }
+        }
+        KeyBindings::Back(_) => {
+            for _ in 0..count.unwrap_or(1) {
+                go_back(s, state);
+            }
+        }
+        KeyBindings::Forward(_) => go_forward(s, state),
+        KeyBindings::HistoryBack(_) => {
+            if !walk_jumps(s, state, |jumps| jumps.back(count.unwrap_or(1))) {
+                set_status(s, "Already at the oldest directory");
+            }
+        }
+        KeyBindings::HistoryForward(_) => {
+            if !walk_jumps(s, state, |jumps| jumps.forward(count.unwrap_or(1))) {
+                set_status(s, "Already at the newest directory");
+            }
+        }
+        KeyBindings::Console(_) => show_console(s, ":", state),
+        KeyBindings::Search(_) => show_console(s, "/", state),
+        KeyBindings::SearchReverse(_) => show_console(s, "?", state),
+        KeyBindings::ShowHidden(_) => {
+            {
+                let mut config = state.config.borrow_mut();
+                config.options.show_hidden = !config.options.show_hidden;
+            }
+            refresh(s, state);
+        }
+        KeyBindings::Refresh(_) => refresh(s, state),
+        KeyBindings::NewTab(_) => {
+            let path = current_dir(state);
+            let title = next_tab_title(&state.vec_tabs.borrow());
+            if let Some(path) = path {
+                if let Err(e) = open_tab(s, state, title, path) {
+                    error!("Cannot open tab: {}", e);
+                }
+            }
+        }
+        KeyBindings::CloseTab(_) => close_tab(s, state),
+        KeyBindings::NextTab(_) => match count {
+            Some(title) => switch_tab(s, state, title as u32),
+            None => cycle_tab(s, state, true),
+        },
+        KeyBindings::PrevTab(_) => {
+            for _ in 0..count.unwrap_or(1) {
+                cycle_tab(s, state, false);
+            }
+        }
+        KeyBindings::Yank(_) => yank(s, state, Mode::Copy),
+        KeyBindings::Cut(_) => yank(s, state, Mode::Move),
+        KeyBindings::Paste(_) => paste(s, state, false),
+        KeyBindings::PasteReplace(_) => paste(s, state, true),
+        KeyBindings::DeleteWithConfirm(_) => delete(s, state, false),
+        KeyBindings::DeletePermanent(_) => delete(s, state, true),
+        KeyBindings::ShowTrash(_) => {
+            let options = state.config.borrow().options.clone();
+            trash::show_trash(s, &state.jobs, options.confirm, options.show_popup);
+        }
+        KeyBindings::Rename(_) => ask_rename(s, state),
+        KeyBindings::NewFile(_) => ask_create(s, state, false),
+        KeyBindings::NewDir(_) => ask_create(s, state, true),
+        KeyBindings::BulkRename(_) => {
+            if let Err(e) = bulk_rename(s, state) {
+                set_status(s, &e.to_string());
+            }
+        }
+        KeyBindings::BatchRename(_) => batch_rename(s, state),
+        KeyBindings::CreateBookmark(_) => {
+            let dir = match current_dir(state) {
+                Some(dir) => dir,
+                None => return,
+            };
+            let content = marks::describe(&state.bookmarks.borrow());
+            let popup = state.config.borrow().options.show_popup;
+            let s_state = state.clone();
+            prompt::key(s, "Bookmark as:", &content, popup, move |s, key| {
+                let result = s_state.bookmarks.borrow_mut().set(key, &dir);
+                match result {
+                    Ok(()) => set_status(s, &format!("Bookmarked {} as {}", dir.display(), key)),
+                    Err(e) => set_status(s, &e.to_string()),
+                }
+            });
+        }
+        KeyBindings::JumpToBookmark(_) => {
+            let content = marks::describe(&state.bookmarks.borrow());
+            let popup = state.config.borrow().options.show_popup;
+            let s_state = state.clone();
+            prompt::key(s, "Jump to:", &content, popup, move |s, key| {
+                if let Err(e) = jump_to_bookmark(s, &s_state, key) {
+                    set_status(s, &e.to_string());
+                }
+            });
+        }
+        KeyBindings::Undo(_) => undo(s, state, true),
+        KeyBindings::Redo(_) => undo(s, state, false),
+        KeyBindings::NextMatch(_) => next_match(s, state, count.unwrap_or(1), false),
+        KeyBindings::PrevMatch(_) => next_match(s, state, count.unwrap_or(1), true),
+        KeyBindings::FuzzyFind(_) => {
+            if let Some(root) = current_dir(state) {
+                let show_hidden = state.config.borrow().options.show_hidden;
+                let s_state = state.clone();
+                finder::show_finder(s, root, show_hidden, move |s, path| {
+                    if let Err(e) = reveal(s, &s_state, &path) {
+                        set_status(s, &e.to_string());
+                    }
+                });
+            }
+        }
+        KeyBindings::ToggleSelect(_) => {
+            let cb = with_current(s, |view| {
+                let focus = view.selected_id()?;
+                let end = (focus + count.unwrap_or(1)).min(view.len());
+                for id in focus..end {
+                    view.toggle_selected(id);
+                }
+                Some(view.set_selection(end))
+            }).and_then(|cb| cb);
+            if let Some(cb) = cb {
+                cb(s);
+            }
+            show_marked(s);
+        }
+        KeyBindings::InvertSelection(_) => {
+            with_current(s, |view| view.invert_selection());
+            show_marked(s);
+        }
+        KeyBindings::ClearSelection(_) => {
+            with_current(s, |view| view.clear_selection());
+            show_marked(s);
+        }
+        KeyBindings::Visual(_) => {
+            let visual = with_current(s, |view| {
+                if view.is_visual() {
+                    view.stop_visual();
+                } else {
+                    view.start_visual();
+                }
+                view.is_visual()
+            });
+            if visual == Some(true) {
+                set_status(s, "-- VISUAL --");
+            } else {
+                show_marked(s);
+            }
+        }
+        KeyBindings::VisualAll(_) => {
+            with_current(s, |view| view.select_all());
+            show_marked(s);
+        }
+        KeyBindings::PreviewDown(_) => preview::scroll(s, count.unwrap_or(1), true, false),
+        KeyBindings::PreviewUp(_) => preview::scroll(s, count.unwrap_or(1), false, false),
+        KeyBindings::PreviewPageDown(_) => preview::scroll(s, count.unwrap_or(1), true, true),
+        KeyBindings::PreviewPageUp(_) => preview::scroll(s, count.unwrap_or(1), false, true),
+        KeyBindings::ToggleWrap(_) => {
+            let wrap = {
+                let mut config = state.config.borrow_mut();
+                config.options.preview_wrap = !config.options.preview_wrap;
+                config.options.preview_wrap
+            };
+            preview::set_wrap(s, wrap);
+        }
+        KeyBindings::HexView(_) => {
+            let path = match with_current(s, |view| view.selection()).and_then(|path| path) {
+                Some(path) => path.to_path_buf(),
+                None => return,
+            };
+            let popup = state.config.borrow().options.show_popup;
+            if let Err(e) = hex::show(s, &path, popup) {
+                set_status(s, &e.to_string());
+            }
+        }
+        KeyBindings::Jobs(_) => jobs::show_jobs(s, &state.jobs),
+    }
+}
+
+/// Calls `f` on the `MultiSelectView` of the current column.
+fn with_current<F, R>(s: &mut Cursive, f: F) -> Option<R>
+where
+    F: FnOnce(&mut MultiSelectView<PathBuf>) -> R,
+{
+    s.call_on_id(
+        "current",
+        |event_view: &mut OnEventView<MultiSelectView<PathBuf>>| f(event_view.get_inner_mut()),
+    )
+}
+
+/// Returns the entries an operation applies to: the marked entries of the current
+/// column if there are some, the focused entry otherwise.
+fn targets(s: &mut Cursive) -> Vec<PathBuf> {
+    let paths = with_current(s, |view| {
+        let marked = view.selected_items();
+        if marked.is_empty() {
+            view.selection().into_iter().collect()
         } else {
-            view.set_content("This is a directory!".to_string())
+            marked
+        }
+    }).unwrap_or_default();
+    paths.iter().map(|path| path.to_path_buf()).collect()
+}
+
+/// Shows the number of marked entries of the current column in the status bar.
+fn show_marked(s: &mut Cursive) {
+    let count = with_current(s, |view| view.selected_count()).unwrap_or(0);
+    set_status(s, &format!("{} marked", count));
+}
+
+/// Returns the directory shown by the focused tab.
+fn current_dir(state: &State) -> Option<PathBuf> {
+    state
+        .vec_tabs
+        .borrow()
+        .get(&state.focused_tab.get())
+        .map(|tab| tab.c_view.clone())
+}
+
+/// Remembers the entry focused in the current column of the focused tab, so that it
+/// is focused again when the directory is shown later.
+fn save_selection(s: &mut Cursive, state: &State) {
+    let current_selection = with_current(s, |view| view.selected_id()).and_then(|id| id);
+    if let Some(tab) = state.vec_tabs.borrow_mut().get_mut(&state.focused_tab.get()) {
+        match tab.found {
+            Some(ref mut found) => found.focused = current_selection.unwrap_or(0),
+            None => {
+                tab.focused
+                    .insert(PathBuf::from(&tab.c_view), current_selection.unwrap_or(0));
+            }
+        }
+    }
+}
+
+/// Moves the tab one level up in the hierarchy, remembering the focused entry.
+/// Results of `:find` are left for the directory they were found in.
+fn go_back(s: &mut Cursive, state: &State) {
+    save_selection(s, state);
+    if let Some(tab) = state.vec_tabs.borrow_mut().get_mut(&state.focused_tab.get()) {
+        if tab.found.take().is_some() {
+            App::update_tab(s, tab, &state.config.borrow().options);
+            return;
+        }
+        let previous = tab.c_view.clone();
+        tab.go_back();
+        // tab.c_focused = current_selection;
+        App::update_tab(s, tab, &state.config.borrow().options);
+        if tab.c_view != previous {
+            visited(state, &tab.c_view);
+        }
+    };
+}
+
+/// Enters the selected directory.
+fn go_forward(s: &mut Cursive, state: &State) {
+    let selection = with_current(s, |view| view.selection()).and_then(|path| path);
+    if let Some(path) = selection {
+        if path.is_dir() {
+            save_selection(s, state);
+            if let Some(tab) = state.vec_tabs.borrow_mut().get_mut(&state.focused_tab.get()) {
+                debug!("Moving forward to path {:?}", path);
+                tab.go_forward(path.to_path_buf());
+                App::update_tab(s, tab, &state.config.borrow().options);
+                visited(state, &tab.c_view);
+            };
+        }
+    };
+}
+
+/// Reloads the listings of the focused tab, e.g. after changing options.
+fn refresh(s: &mut Cursive, state: &State) {
+    save_selection(s, state);
+    if let Some(tab) = state.vec_tabs.borrow_mut().get_mut(&state.focused_tab.get()) {
+        App::update_tab(s, tab, &state.config.borrow().options);
+    }
+}
+
+/// Returns the smallest title which is not used by any tab.
+fn next_tab_title(tabs: &HashMap<u32, Tab>) -> u32 {
+    (1..).find(|title| !tabs.contains_key(title)).unwrap_or(1)
+}
+
+/// Opens a tab with the given title showing `path` and focuses it.
+fn open_tab(s: &mut Cursive, state: &State, title: u32, path: PathBuf) -> Result<()> {
+    let mut tab = Tab::from(title, &path)?;
+    save_selection(s, state);
+    App::update_tab(s, &mut tab, &state.config.borrow().options);
+    debug!("Value of tab: {:?}", tab);
+    state.vec_tabs.borrow_mut().insert(title, tab);
+    state.focused_tab.set(title);
+    update_tabbar(s, state);
+    Ok(())
+}
+
+/// Focuses the tab with the given title, if it exists.
+fn switch_tab(s: &mut Cursive, state: &State, title: u32) {
+    if title == state.focused_tab.get() || !state.vec_tabs.borrow().contains_key(&title) {
+        return;
+    }
+    save_selection(s, state);
+    state.focused_tab.set(title);
+    if let Some(tab) = state.vec_tabs.borrow_mut().get_mut(&title) {
+        App::update_tab(s, tab, &state.config.borrow().options);
+    }
+    update_tabbar(s, state);
+}
+
+/// Focuses the next (or previous) tab, wrapping around.
+fn cycle_tab(s: &mut Cursive, state: &State, forward: bool) {
+    let mut titles: Vec<u32> = state.vec_tabs.borrow().keys().cloned().collect();
+    titles.sort();
+    let focused = state.focused_tab.get();
+    let position = titles.iter().position(|title| *title == focused).unwrap_or(0);
+    let next = if forward {
+        (position + 1) % titles.len()
+    } else {
+        (position + titles.len() - 1) % titles.len()
+    };
+    switch_tab(s, state, titles[next]);
+}
+
+/// Closes the focused tab and focuses its neighbour. Closing the last tab quits.
+fn close_tab(s: &mut Cursive, state: &State) {
+    let focused = state.focused_tab.get();
+    state.vec_tabs.borrow_mut().remove(&focused);
+    let next = {
+        let tabs = state.vec_tabs.borrow();
+        let after = tabs.keys().filter(|title| **title > focused).min().cloned();
+        after.or_else(|| tabs.keys().max().cloned())
+    };
+    match next {
+        Some(title) => {
+            state.focused_tab.set(title);
+            if let Some(tab) = state.vec_tabs.borrow_mut().get_mut(&title) {
+                App::update_tab(s, tab, &state.config.borrow().options);
+            }
+            update_tabbar(s, state);
         }
+        None => s.quit(),
+    }
+}
+
+/// Updates the tab bar, showing the title of every tab with the focused one in
+/// brackets.
+fn update_tabbar(s: &mut Cursive, state: &State) {
+    let mut titles: Vec<u32> = state.vec_tabs.borrow().keys().cloned().collect();
+    titles.sort();
+    let focused = state.focused_tab.get();
+    let text: String = titles
+        .iter()
+        .map(|title| {
+            if *title == focused {
+                format!("[{}]", title)
+            } else {
+                format!(" {} ", title)
+            }
+        }).collect();
+    s.call_on_id("topbar/tabs", |view: &mut TextView| {
+        view.set_content(format!(" {} ", text));
     });
-    siv.call_on_id("status", |view: &mut TextView| {
-        view.set_content(
-            Entry::from(PathBuf::from(entry))
-                .permission_string()
-                .unwrap(),
-        );
+}
+
+/// Shows a message in the status bar.
+fn set_status(s: &mut Cursive, message: &str) {
+    s.call_on_id("status", |view: &mut TextView| {
+        view.set_content(message);
     });
 }
 
-fn create_console(prefix: &str) -> LinearLayout {
-    let prefix_view = TextView::new(prefix);
-    let edit_view = EditView::new().filler(" ")
-        .on_submit(|s, content| {
-            debug!("You entered {}", content);
+/// Puts the marked entries, or the selected one, in the clipboard, to be copied or
+/// moved on paste. The marks are cleared.
+fn yank(s: &mut Cursive, state: &State, mode: Mode) {
+    let paths = targets(s);
+    if paths.is_empty() {
+        return;
+    }
+    let verb = if mode == Mode::Copy { "Yanked" } else { "Cut" };
+    set_status(s, &format!("{} {}", verb, describe(&paths)));
+    with_current(s, |view| view.clear_selection());
+    *state.clipboard.lock().unwrap() = Some(Clipboard { mode, paths });
+}
+
+/// Describes `paths` in messages: the path itself if there is only one.
+fn describe(paths: &[PathBuf]) -> String {
+    match paths {
+        [path] => path.display().to_string(),
+        _ => format!("{} entries", paths.len()),
+    }
+}
+
+/// Pastes the clipboard into the directory of the focused tab.
+///
+/// If some entries already exist and `confirm` is set, the user is asked whether to skip,
+/// rename or overwrite them (or only whether to overwrite them, if `replace` is set).
+/// Otherwise existing entries are renamed, or overwritten if `replace` is set.
+fn paste(s: &mut Cursive, state: &State, replace: bool) {
+    let clipboard = match state.clipboard.lock().unwrap().clone() {
+        Some(clipboard) => clipboard,
+        None => return set_status(s, "Nothing to paste"),
+    };
+    let dest = match current_dir(state) {
+        Some(dest) => dest,
+        None => return,
+    };
+    let conflicts = clipboard.conflicts(&dest);
+    let (confirm, popup) = {
+        let options = &state.config.borrow().options;
+        (options.confirm, options.show_popup)
+    };
+    if conflicts.is_empty() || !confirm {
+        let conflict = if replace {
+            Conflict::Overwrite
+        } else {
+            Conflict::Rename
+        };
+        return paste_into(s, state, &dest, conflict);
+    }
+    let message = format!("{} entries already exist in {}", conflicts.len(), dest.display());
+    if replace {
+        let s_clone = state.clone();
+        prompt::confirm(s, &format!("{}. Replace?", message), popup, move |s| {
+            paste_into(s, &s_clone, &dest, Conflict::Overwrite)
+        });
+    } else {
+        let choices = vec![
+            ('s', "Skip", Conflict::Skip),
+            ('r', "Rename", Conflict::Rename),
+            ('o', "Overwrite", Conflict::Overwrite),
+        ];
+        let choices = choices
+            .into_iter()
+            .map(|(key, label, conflict)| {
+                let s_clone = state.clone();
+                let dest = dest.clone();
+                let cb: Rc<dyn Fn(&mut Cursive)> =
+                    Rc::new(move |s| paste_into(s, &s_clone, &dest, conflict));
+                (key, label, cb)
+            }).collect();
+        prompt::choose(s, &message, popup, choices);
+    }
+}
+
+/// Copies or moves the clipboard into `dest` in a background job. The clipboard is
+/// emptied once a move succeeds, unless something else was yanked in the meantime.
+fn paste_into(s: &mut Cursive, state: &State, dest: &Path, conflict: Conflict) {
+    let clipboard = match state.clipboard.lock().unwrap().clone() {
+        Some(clipboard) => clipboard,
+        None => return,
+    };
+    let kind = match clipboard.mode {
+        Mode::Copy => JobKind::Copy,
+        Mode::Move => JobKind::Move,
+    };
+    let description = format!("{} entries to {}", clipboard.paths.len(), dest.display());
+    set_status(s, &format!("Started {:?} of {}", kind, description));
+    let dest = dest.to_path_buf();
+    let (journal, shared) = (state.journal.clone(), state.clipboard.clone());
+    state.jobs.spawn(kind, description, move |worker| {
+        let (files, bytes) = ops::measure(&clipboard.paths);
+        worker.set_totals(files, bytes);
+        // Entries pasted before a failure are still recorded, so they can be undone.
+        let (pasted, result) = clipboard.paste(&dest, conflict, worker);
+        if !pasted.pairs.is_empty() || !pasted.replaced.is_empty() {
+            record(&journal, Operation::pasted(clipboard.mode, pasted));
+        }
+        if result.is_ok() && clipboard.mode == Mode::Move {
+            let mut shared = shared.lock().unwrap();
+            if shared.as_ref() == Some(&clipboard) {
+                *shared = None;
+            }
+        }
+        result
+    });
+}
+
+/// Moves the marked entries, or the selected one, to the trash, or deletes them
+/// if `permanent` is set.
+///
+/// The user is asked for confirmation first if `confirm` is set.
+fn delete(s: &mut Cursive, state: &State, permanent: bool) {
+    let paths = targets(s);
+    if paths.is_empty() {
+        return;
+    }
+    let options = state.config.borrow().options.clone();
+    let description = describe(&paths);
+    let message = if permanent {
+        format!("Permanently delete {}?", description)
+    } else {
+        format!("Move {} to the trash?", description)
+    };
+    let jobs = state.jobs.clone();
+    let journal = state.journal.clone();
+    let action = move |s: &mut Cursive| {
+        let paths = paths.clone();
+        set_status(s, &format!("Deleting {}", description));
+        if permanent {
+            jobs.spawn(JobKind::Delete, description.clone(), move |worker| {
+                let (files, bytes) = ops::measure(&paths);
+                worker.set_totals(files, bytes);
+                for path in &paths {
+                    ops::remove_path(path, worker)?;
+                }
+                Ok(())
+            });
+        } else {
+            let journal = journal.clone();
+            jobs.spawn(JobKind::Trash, description.clone(), move |worker| {
+                let (files, bytes) = ops::measure(&paths);
+                worker.set_totals(files, bytes);
+                let trash = Trash::home()?;
+                let mut entries = Vec::with_capacity(paths.len());
+                // Entries trashed before a failure are still recorded, so they can be restored.
+                let result = paths
+                    .iter()
+                    .try_for_each(|path| trash.put(path, worker).map(|entry| entries.push(entry)));
+                if !entries.is_empty() {
+                    record(&journal, Operation::trashed(&entries));
+                }
+                result
+            });
+        }
+    };
+    if options.confirm {
+        prompt::confirm(s, &message, options.show_popup, action);
+    } else {
+        action(s);
+    }
+}
+
+/// Archives the marked entries, or the selected one, into `dest` in a background job.
+fn archive(s: &mut Cursive, state: &State, dest: PathBuf) -> Result<()> {
+    let paths = targets(s);
+    if paths.is_empty() {
+        return Err(ErrorKind::InvalidOperation {
+            reason: String::from("Nothing selected"),
+        }.into());
+    }
+    if dest.symlink_metadata().is_ok() {
+        return Err(ErrorKind::InvalidOperation {
+            reason: format!("{} already exists", dest.display()),
+        }.into());
+    }
+    let description = format!("{} to {}", describe(&paths), dest.display());
+    set_status(s, &format!("Archiving {}", description));
+    let journal = state.journal.clone();
+    state.jobs.spawn(JobKind::Archive, description, move |worker| {
+        let (files, _) = ops::measure(&paths);
+        worker.set_totals(files, 0);
+        ops::archive(&paths, &dest, worker)?;
+        record(&journal, Operation::Archive { paths, archive: dest });
+        Ok(())
+    });
+    Ok(())
+}
+
+/// Records a finished operation in the journal. Failing to save the journal is
+/// not an error of the operation itself, so it is only logged.
+fn record(journal: &Arc<Mutex<Journal>>, operation: Operation) {
+    if let Err(e) = journal.lock().unwrap().record(operation) {
+        warn!("Cannot save the journal: {}", e);
+    }
+}
+
+/// Undoes the most recent operation of the journal, or redoes the most recently
+/// undone one if `undo` is not set. The operation runs as a background job.
+///
+/// If it fails, the part of the operation not done yet is put back so that it can be retried.
+fn undo(s: &mut Cursive, state: &State, undo: bool) {
+    let operation = {
+        let mut journal = state.journal.lock().unwrap();
+        if undo {
+            journal.pop_undo()
+        } else {
+            journal.pop_redo()
+        }
+    };
+    let operation = match operation {
+        Some(operation) => operation,
+        None if undo => return set_status(s, "Nothing to undo"),
+        None => return set_status(s, "Nothing to redo"),
+    };
+    let description = operation.describe();
+    set_status(s, &format!("{} {}", if undo { "Undoing" } else { "Redoing" }, description));
+    let kind = if undo { JobKind::Undo } else { JobKind::Redo };
+    let journal = state.journal.clone();
+    state.jobs.spawn(kind, description, move |worker| {
+        let (outcome, result) = if undo {
+            operation.revert(worker)
+        } else {
+            operation.replay(worker)
+        };
+        // The part done can be redone (or undone again), while the part left after a failure
+        // is put back so that it can be retried.
+        let mut journal = journal.lock().unwrap();
+        let mut saved = Ok(());
+        if let Some(done) = outcome.done {
+            saved = if undo {
+                journal.push_redo(done)
+            } else {
+                journal.push_undo(done)
+            };
+        }
+        if let Some(left) = outcome.left {
+            saved = saved.and(if undo {
+                journal.push_undo(left)
+            } else {
+                journal.push_redo(left)
+            });
+        }
+        if let Err(e) = saved {
+            warn!("Cannot save the journal: {}", e);
+        }
+        result
+    });
+}
+
+/// Quits, asking for confirmation first if jobs are running and `force` is not set.
+fn quit(s: &mut Cursive, state: &State, force: bool) {
+    let running = state.jobs.running();
+    if force || running == 0 {
+        return s.quit();
+    }
+    let popup = state.config.borrow().options.show_popup;
+    let message = format!("{} job(s) still running. Quit anyway?", running);
+    prompt::confirm(s, &message, popup, |s| s.quit());
+}
+
+/// Parses and runs a command typed in the console, reporting errors in the status
+/// bar.
+fn run_command(s: &mut Cursive, state: &State, line: &str) {
+    if line.trim().is_empty() {
+        return;
+    }
+    debug!("Running command {:?}", line);
+    if let Err(e) = commands::parse(line).and_then(|command| execute(s, state, command)) {
+        set_status(s, &e.to_string());
+    }
+}
+
+/// Runs a parsed command. Relative paths are resolved against the directory of
+/// the focused tab.
+fn execute(s: &mut Cursive, state: &State, command: Command) -> Result<()> {
+    let cwd = current_dir(state).ok_or_else(|| ErrorKind::InvalidOperation {
+        reason: String::from("No tab is open"),
+    })?;
+    match command {
+        Command::Cd(dir) => {
+            let path = match dir {
+                Some(dir) => commands::expand_path(&dir, &cwd),
+                None => dirs::home_dir().ok_or(ErrorKind::DirNotFound {
+                    dirname: String::from("HOME"),
+                })?,
+            };
+            change_dir(s, state, path)?;
+        }
+        Command::Mkdir(names) => {
+            for name in names {
+                create(state, commands::expand_path(&name, &cwd), true)?;
+            }
+            refresh(s, state);
+        }
+        Command::Touch(names) => {
+            for name in names {
+                create(state, commands::expand_path(&name, &cwd), false)?;
+            }
+            refresh(s, state);
+        }
+        Command::Rename(name) => {
+            let path = with_current(s, |view| view.selection())
+                .and_then(|path| path)
+                .map(|path| path.to_path_buf())
+                .ok_or_else(|| ErrorKind::InvalidOperation {
+                    reason: String::from("Nothing selected"),
+                })?;
+            rename(s, state, &path, &name)?;
+        }
+        Command::BulkRename => bulk_rename(s, state)?,
+        Command::BatchRename => batch_rename(s, state),
+        Command::Delete { permanent } => delete(s, state, permanent),
+        Command::Archive(name) => archive(s, state, commands::expand_path(&name, &cwd))?,
+        Command::Mark { criteria, mark } => {
+            let count = with_current(s, |view| {
+                view.select_where(mark, |path| criteria.iter().any(|c| c.matches(path)))
+            }).unwrap_or(0);
+            let verb = if mark { "Marked" } else { "Unmarked" };
+            set_status(s, &format!("{} {} entries", verb, count));
+        }
+        Command::Find { criteria, query } => {
+            let show_hidden = state.config.borrow().options.show_hidden;
+            let (tab, finds) = (state.focused_tab.get(), state.finds.clone());
+            let description = format!("{} in {}", query, cwd.display());
+            state.jobs.spawn(JobKind::Find, description, move |worker| {
+                let (paths, truncated) = find::find(&cwd, &criteria, show_hidden, worker)?;
+                finds.lock().unwrap().push(FindResults {
+                    tab,
+                    dir: cwd,
+                    found: Found {
+                        query,
+                        paths,
+                        focused: 0,
+                    },
+                    truncated,
+                });
+                Ok(())
+            });
+        }
+        Command::Grep(pattern) => {
+            let (show_hidden, max_size, regex) = {
+                let config = state.config.borrow();
+                let options = &config.options;
+                (options.show_hidden, options.grep_max_size, options.search_regex)
+            };
+            let matcher = Matcher::new(&pattern, regex)?;
+            let (tab, greps) = (state.focused_tab.get(), state.greps.clone());
+            let description = format!("{} in {}", pattern, cwd.display());
+            state.jobs.spawn(JobKind::Grep, description, move |worker| {
+                let files = grep::walk(&cwd, show_hidden, max_size, worker)?;
+                let bytes = files.iter().map(|(_, size)| size).sum();
+                worker.set_totals(files.len() as u64, bytes);
+                let (hits, truncated) = grep::grep(files, &matcher, worker)?;
+                greps.lock().unwrap().push(GrepResults {
+                    tab,
+                    dir: cwd,
+                    matcher,
+                    hits,
+                    truncated,
+                });
+                Ok(())
+            });
+        }
+        Command::Bookmarks => {
+            let s_state = state.clone();
+            marks::show_bookmarks(s, &state.bookmarks, move |s, dir| {
+                if let Err(e) = change_dir(s, &s_state, dir) {
+                    set_status(s, &e.to_string());
+                }
+            });
+        }
+        Command::Bookmark { key, dir } => {
+            let path = dir
+                .map(|dir| commands::expand_path(&dir, &cwd))
+                .unwrap_or(cwd);
+            let path = path.canonicalize().map_err(|e| io_failure("open", &path, e))?;
+            if !path.is_dir() {
+                return Err(ErrorKind::DirNotFound {
+                    dirname: path.to_string_lossy().into_owned(),
+                }.into());
+            }
+            state.bookmarks.borrow_mut().set(key, &path)?;
+            set_status(s, &format!("Bookmarked {} as {}", path.display(), key));
+        }
+        Command::DelBookmark(keys) => {
+            for key in &keys {
+                state.bookmarks.borrow_mut().remove(*key)?;
+            }
+            set_status(s, &format!("Deleted {} bookmark(s)", keys.len()));
+        }
+        Command::History => {
+            let (dirs, current) = {
+                let tabs = state.vec_tabs.borrow();
+                let jumps = &tabs[&state.focused_tab.get()].jumps;
+                let dirs: Vec<(usize, PathBuf)> = jumps
+                    .recent()
+                    .into_iter()
+                    .map(|(index, dir)| (index, dir.to_path_buf()))
+                    .collect();
+                (dirs, jumps.position())
+            };
+            let s_state = state.clone();
+            jumps::show_history(s, dirs, current, move |s, index| {
+                walk_jumps(s, &s_state, |jumps| jumps.go_to(index));
+            });
+        }
+        Command::Frecent(fragments) => {
+            if fragments.is_empty() {
+                let items = state
+                    .frecency
+                    .borrow()
+                    .ranked()
+                    .into_iter()
+                    .map(|dir| (dir.to_string_lossy().into_owned(), dir))
+                    .collect();
+                let s_state = state.clone();
+                picker::pick(s, "Frecent directories", items, move |s, dir| {
+                    if let Err(e) = change_dir(s, &s_state, dir) {
+                        set_status(s, &e.to_string());
+                    }
+                });
+            } else {
+                let best = state.frecency.borrow().find(&fragments).into_iter().next();
+                let dir = best.ok_or_else(|| ErrorKind::InvalidOperation {
+                    reason: format!("No visited directory matches {}", fragments.join(" ")),
+                })?;
+                change_dir(s, state, dir)?;
+            }
+        }
+        Command::Set(pairs) => {
+            {
+                let mut config = state.config.borrow_mut();
+                for (name, value) in pairs {
+                    config.options.set(&name, &value)?;
+                }
+            }
+            refresh(s, state);
+        }
+        Command::Sort { by, reverse } => {
+            {
+                let mut config = state.config.borrow_mut();
+                config.options.sort = by;
+                config.options.sort_reverse = reverse;
+            }
+            refresh(s, state);
+        }
+        Command::TabNew(dir) => {
+            let path = dir
+                .map(|dir| commands::expand_path(&dir, &cwd))
+                .unwrap_or(cwd);
+            if !path.is_dir() {
+                return Err(ErrorKind::DirNotFound {
+                    dirname: path.to_string_lossy().into_owned(),
+                }.into());
+            }
+            let title = next_tab_title(&state.vec_tabs.borrow());
+            open_tab(s, state, title, path)?;
+        }
+        Command::Quit { force } => quit(s, state, force),
+    }
+    Ok(())
+}
+
+/// Lists the results of the `:find` jobs which are over in their tabs, unless the
+/// tabs went to another directory meanwhile.
+fn show_found(s: &mut Cursive, state: &State) {
+    let results: Vec<FindResults> = state.finds.lock().unwrap().drain(..).collect();
+    for result in results {
+        let count = result.found.paths.len();
+        if count == 0 {
+            set_status(s, &format!("Nothing matches {}", result.found.query));
+            continue;
+        }
+        let focused = result.tab == state.focused_tab.get();
+        if focused {
+            save_selection(s, state);
+        }
+        if let Some(tab) = state.vec_tabs.borrow_mut().get_mut(&result.tab) {
+            if tab.c_view != result.dir {
+                continue;
+            }
+            tab.found = Some(result.found);
+            if focused {
+                App::update_tab(s, tab, &state.config.borrow().options);
+            }
+        }
+        let more = if result.truncated {
+            format!(" (stopped at {})", find::MAX_RESULTS)
+        } else {
+            String::new()
+        };
+        set_status(s, &format!("{} entries found{}", count, more));
+    }
+}
+
+/// Lists the hits of the `:grep` jobs which are over, if their tab is still focused.
+/// `Enter` shows the file of a hit in the tab.
+fn show_hits(s: &mut Cursive, state: &State) {
+    let results: Vec<GrepResults> = state.greps.lock().unwrap().drain(..).collect();
+    for result in results {
+        if result.hits.is_empty() {
+            set_status(s, "No line matches");
+            continue;
+        }
+        if result.tab != state.focused_tab.get() {
+            continue;
+        }
+        let more = if result.truncated {
+            format!(" (stopped at {})", grep::MAX_HITS)
+        } else {
+            String::new()
+        };
+        set_status(s, &format!("{} lines match{}", result.hits.len(), more));
+        let s_state = state.clone();
+        let tab_width = state.config.borrow().options.tab_width;
+        hits::show_hits(s, &result.dir, result.hits, result.matcher, tab_width, move |s, hit| {
+            match reveal(s, &s_state, &hit.path) {
+                Ok(()) => set_status(s, &format!("{}:{}", hit.path.display(), hit.line)),
+                Err(e) => set_status(s, &e.to_string()),
+            }
+        });
+    }
+}
+
+/// Describes a failed file operation for the status bar.
+fn io_failure(action: &str, path: &Path, e: ::std::io::Error) -> Error {
+    ErrorKind::InvalidOperation {
+        reason: format!("Cannot {} {}: {}", action, path.display(), e),
+    }.into()
+}
+
+/// Shows `path` in the focused tab. The directory shown until then is remembered
+/// as the last position, bookmark `` ` ``.
+fn change_dir(s: &mut Cursive, state: &State, path: PathBuf) -> Result<()> {
+    let path = path.canonicalize().map_err(|e| io_failure("open", &path, e))?;
+    if !path.is_dir() {
+        return Err(ErrorKind::DirNotFound {
+            dirname: path.to_string_lossy().into_owned(),
+        }.into());
+    }
+    if let Some(previous) = current_dir(state) {
+        leave(state, &previous, &path);
+    }
+    save_selection(s, state);
+    if let Some(tab) = state.vec_tabs.borrow_mut().get_mut(&state.focused_tab.get()) {
+        tab.change_dir(&path)?;
+        tab.jumps.visit(path);
+        App::update_tab(s, tab, &state.config.borrow().options);
+        visited(state, &tab.c_view);
+    }
+    Ok(())
+}
+
+/// Shows the directory holding `path` in the focused tab, with `path` focused.
+fn reveal(s: &mut Cursive, state: &State, path: &Path) -> Result<()> {
+    let parent = path.parent().ok_or_else(|| ErrorKind::DirNotFound {
+        dirname: format!("Parent for {:?}", path),
+    })?;
+    change_dir(s, state, parent.to_path_buf())?;
+    focus(s, path);
+    Ok(())
+}
+
+/// Focuses the entry `path` in the current column, if it is listed there.
+fn focus(s: &mut Cursive, path: &Path) {
+    let name = path.file_name();
+    let cb = with_current(s, |view| {
+        let index = (0..view.len()).find(|&i| {
+            view.get_item(i)
+                .map_or(false, |(_, entry)| entry.file_name() == name)
+        })?;
+        Some(view.set_selection(index))
+    }).and_then(|cb| cb);
+    if let Some(cb) = cb {
+        cb(s);
+    }
+}
+
+/// Marks `previous` as the last position when leaving it for `next`.
+fn leave(state: &State, previous: &Path, next: &Path) {
+    if previous != next {
+        if let Err(e) = state.bookmarks.borrow_mut().set_last(previous) {
+            warn!("Cannot save the last position: {}", e);
+        }
+    }
+}
+
+/// Records a visit to `dir` in the frecency database.
+fn visited(state: &State, dir: &Path) {
+    if let Err(e) = state.frecency.borrow_mut().visit(dir) {
+        warn!("Cannot record the visit to {:?}: {}", dir, e);
+    }
+}
+
+/// Shows the directory `walk` moves to in the jump list of the focused tab, with
+/// the entry focused there at the time. Returns `false` if `walk` found no directory.
+fn walk_jumps<F>(s: &mut Cursive, state: &State, walk: F) -> bool
+where
+    F: FnOnce(&mut JumpList) -> Option<PathBuf>,
+{
+    save_selection(s, state);
+    if let Some(tab) = state.vec_tabs.borrow_mut().get_mut(&state.focused_tab.get()) {
+        if let Some(dir) = walk(&mut tab.jumps) {
+            let previous = tab.c_view.clone();
+            match tab.change_dir(&dir) {
+                Ok(()) => {
+                    leave(state, &previous, &dir);
+                    visited(state, &dir);
+                }
+                Err(e) => error!("Cannot show {:?}: {}", dir, e),
+            }
+            App::update_tab(s, tab, &state.config.borrow().options);
+            return true;
+        }
+    }
+    false
+}
+
+/// Shows the directory of the bookmark `key` in the focused tab.
+fn jump_to_bookmark(s: &mut Cursive, state: &State, key: char) -> Result<()> {
+    let dir = state.bookmarks.borrow().get(key).map(|dir| dir.to_path_buf());
+    let dir = dir.ok_or_else(|| ErrorKind::InvalidOperation {
+        reason: if key == bookmarks::LAST {
+            String::from("No last position yet")
+        } else {
+            format!("No bookmark {}", key)
+        },
+    })?;
+    change_dir(s, state, dir)
+}
+
+/// Renames `path` to `name` within its directory, recording it in the journal.
+fn rename(s: &mut Cursive, state: &State, path: &Path, name: &str) -> Result<()> {
+    let dest = path.with_file_name(name);
+    if dest.symlink_metadata().is_ok() {
+        return Err(ErrorKind::InvalidOperation {
+            reason: format!("{} already exists", dest.display()),
+        }.into());
+    }
+    stdfs::rename(path, &dest).map_err(|e| io_failure("rename", path, e))?;
+    record(
+        &state.journal,
+        Operation::Move {
+            pairs: vec![(path.to_path_buf(), dest.clone())],
+            replaced: Vec::new(),
+        },
+    );
+    track_moves(state, &[(path.to_path_buf(), dest.clone())]);
+    set_status(s, &format!("Renamed to {}", dest.display()));
+    refresh(s, state);
+    Ok(())
+}
+
+/// Asks for a new name for the selected entry, with the cursor placed before its
+/// extension.
+fn ask_rename(s: &mut Cursive, state: &State) {
+    let path = match with_current(s, |view| view.selection()).and_then(|path| path) {
+        Some(path) => path.to_path_buf(),
+        None => return,
+    };
+    let name = file_name(&path);
+    let cursor = match name.rfind('.') {
+        Some(i) if i > 0 => i,
+        _ => name.len(),
+    };
+    let popup = state.config.borrow().options.show_popup;
+    let (s_state, current) = (state.clone(), name.clone());
+    prompt::input(s, "Rename:", &name, cursor, popup, move |s, line| {
+        if line == current {
+            return;
+        }
+        let result = commands::check_name(line).and_then(|_| rename(s, &s_state, &path, line));
+        if let Err(e) = result {
+            set_status(s, &e.to_string());
+        }
+    });
+}
+
+/// Creates the directory, or the empty file, `path` and records it in the journal.
+fn create(state: &State, path: PathBuf, dir: bool) -> Result<()> {
+    if dir {
+        stdfs::create_dir(&path).map_err(|e| io_failure("create", &path, e))?;
+    } else {
+        stdfs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(|e| io_failure("create", &path, e))?;
+    }
+    record(&state.journal, Operation::Create { path, dir });
+    Ok(())
+}
+
+/// Asks for the name of a new directory, or empty file, to create in the current directory,
+/// and focuses it once created.
+fn ask_create(s: &mut Cursive, state: &State, dir: bool) {
+    let cwd = match current_dir(state) {
+        Some(cwd) => cwd,
+        None => return,
+    };
+    let message = if dir { "New directory:" } else { "New file:" };
+    let popup = state.config.borrow().options.show_popup;
+    let s_state = state.clone();
+    prompt::input(s, message, "", 0, popup, move |s, line| {
+        let path = cwd.join(line);
+        match commands::check_name(line).and_then(|_| create(&s_state, path.clone(), dir)) {
+            Ok(()) => {
+                refresh(s, &s_state);
+                focus(s, &path);
+            }
+            Err(e) => set_status(s, &e.to_string()),
+        }
+    });
+}
+
+/// Renames the marked entries, or every entry of the listing, by editing their
+/// names in the editor. The renames are checked and previewed before anything is renamed.
+fn bulk_rename(s: &mut Cursive, state: &State) -> Result<()> {
+    let paths = marked_or_all(s);
+    if paths.is_empty() {
+        return Ok(());
+    }
+    let text = bulk::names(&paths)?;
+    let file = env::temp_dir().join(format!("marcos-rename-{}.txt", process::id()));
+    stdfs::write(&file, text).map_err(|e| io_failure("write", &file, e))?;
+    let edited = editor::edit(s, &file).and_then(|_| {
+        stdfs::read_to_string(&file).map_err(|e| io_failure("read", &file, e))
+    });
+    if let Err(e) = stdfs::remove_file(&file) {
+        warn!("Cannot remove {:?}: {}", file, e);
+    }
+    let plan = bulk::plan(&paths, &edited?)?;
+    if plan.renames.is_empty() {
+        set_status(s, "Nothing to rename");
+        return Ok(());
+    }
+    let mut preview = plan
+        .renames
+        .iter()
+        .map(|(src, dest)| format!("{} -> {}", file_name(src), file_name(dest)))
+        .collect::<Vec<String>>()
+        .join("\n");
+    if plan.cycles > 0 {
+        preview.push_str(&format!(
+            "\n\nCycles broken with a temporary name: {}",
+            plan.cycles
+        ));
+    }
+    let s_state = state.clone();
+    let title = format!("Rename {} entries", plan.renames.len());
+    let dialog = Dialog::around(TextView::new(preview).scrollable().min_width(60))
+        .title(title)
+        .button("Rename", move |s| {
             s.pop_layer();
+            apply_renames(s, &s_state, &plan);
+        }).dismiss_button("Cancel");
+    s.add_layer(dialog);
+    Ok(())
+}
+
+/// Renames the marked entries, or every entry of the listing, with a pattern typed
+/// in a dialog which previews the new names.
+fn batch_rename(s: &mut Cursive, state: &State) {
+    let paths = marked_or_all(s);
+    if paths.is_empty() {
+        return;
+    }
+    let s_state = state.clone();
+    batch::show_rename(s, paths, move |s, plan| apply_renames(s, &s_state, plan));
+}
+
+/// Returns the marked entries of the current column, or all its entries if none is
+/// marked.
+fn marked_or_all(s: &mut Cursive) -> Vec<PathBuf> {
+    with_current(s, |view| {
+        let marked = view.selected_items();
+        if marked.is_empty() {
+            (0..view.len())
+                .filter_map(|i| view.get_item(i).map(|(_, path)| path.clone()))
+                .collect()
+        } else {
+            marked.iter().map(|path| path.to_path_buf()).collect()
+        }
+    }).unwrap_or_default()
+}
+
+/// Runs the renames of `plan` and records them in the journal.
+fn apply_renames(s: &mut Cursive, state: &State, plan: &Plan) {
+    if plan.renames.is_empty() {
+        return set_status(s, "Nothing to rename");
+    }
+    let result = plan.apply();
+    if result.is_ok() {
+        record(
+            &state.journal,
+            Operation::Move {
+                pairs: plan.steps.clone(),
+                replaced: Vec::new(),
+            },
+        );
+        track_moves(state, &plan.steps);
+        with_current(s, |view| view.clear_selection());
+    }
+    refresh(s, state);
+    match result {
+        Ok(()) => set_status(s, &format!("Renamed {} entries", plan.renames.len())),
+        Err(e) => set_status(s, &e.to_string()),
+    }
+}
+
+/// Keeps the results of `:find` listed by the focused tab up to date after entries
+/// were moved, `pairs` holding the sources and destinations in the order they were moved.
+fn track_moves(state: &State, pairs: &[(PathBuf, PathBuf)]) {
+    if let Some(tab) = state.vec_tabs.borrow_mut().get_mut(&state.focused_tab.get()) {
+        if let Some(ref mut found) = tab.found {
+            for (from, to) in pairs {
+                for path in found.paths.iter_mut() {
+                    let moved = match path.strip_prefix(from) {
+                        Ok(rest) if rest.as_os_str().is_empty() => to.clone(),
+                        Ok(rest) => to.join(rest),
+                        Err(_) => continue,
+                    };
+                    *path = moved;
+                }
+            }
+        }
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Opens the console at the bottom of the screen with the given prefix.
+///
+/// `:` runs commands, while `/` and `?` search the current listing downwards and upwards,
+/// moving the focus to the first match as the pattern is typed. `Esc` cancels the search and
+/// focuses the entry focused beforehand.
+fn show_console(s: &mut Cursive, prefix: &str, state: &State) {
+    debug!("Opening console with prefix {}", prefix);
+    let cwd = current_dir(state).unwrap_or_default();
+    let history = state.history.clone();
+    let console = if prefix == ":" {
+        let c_state = state.clone();
+        console::create_console(prefix, cwd, history, |_, _| (), move |s, line| {
+            run_command(s, &c_state, line)
+        })
+    } else {
+        let forward = prefix != "?";
+        let origin = with_current(s, |view| view.selected_id())
+            .and_then(|id| id)
+            .unwrap_or(0);
+        let (e_state, c_state, x_state) = (state.clone(), state.clone(), state.clone());
+        console::create_console(
+            prefix,
+            cwd,
+            history,
+            move |s, line| preview_search(s, &e_state, line, origin, forward),
+            move |s, line| submit_search(s, &c_state, line, origin, forward),
+        ).on_pre_event(Key::Esc, move |s| {
+            s.pop_layer();
+            cancel_search(s, &x_state, origin);
         })
-        .style(ColorStyle::new(ColorType::from(PaletteColor::Background),
-        ColorType::from(PaletteColor::Primary))).with_id("console");
-    let layout = LinearLayout::horizontal()
-        .child(prefix_view)
-        .child(edit_view.full_width());
-    layout
+    };
+    s.add_layer(console);
+    let screen = s.screen_mut();
+    let l = LayerPosition::FromFront(0);
+    let pos = screen.offset().saturating_add((9000, 9000));
+    let p = Position::absolute(pos);
+    screen.reposition_layer(l, p);
+}
+
+/// Returns the index from which to search for the match after (or before) `index`.
+fn search_start(index: usize, forward: bool, len: usize) -> usize {
+    match (forward, len) {
+        (_, 0) => 0,
+        (true, _) => (index + 1) % len,
+        (false, _) => (index + len - 1) % len,
+    }
+}
+
+/// Highlights the matches of `matcher` in the labels of `view`.
+fn highlight_matches(view: &mut MultiSelectView<PathBuf>, matcher: Matcher) {
+    view.set_highlight(move |label| {
+        // Labels are indented, matches are searched in the name only.
+        let name = label.trim_start();
+        let indent = label.len() - name.len();
+        matcher
+            .ranges(name)
+            .into_iter()
+            .map(|(start, end)| (start + indent, end + indent))
+            .collect()
+    });
+}
+
+/// Focuses the first entry matching `matcher` from `start`, wrapping around, and
+/// highlights the matches. Returns whether the search wrapped, or `None` if nothing matched.
+fn jump_to_match(s: &mut Cursive, matcher: &Matcher, start: usize, forward: bool) -> Option<bool> {
+    let found = with_current(s, |view| {
+        highlight_matches(view, matcher.clone());
+        view.find(start, forward, |label| matcher.is_match(label.trim_start()))
+            .map(|(index, wrapped)| (view.set_selection(index), wrapped))
+    }).and_then(|found| found);
+    found.map(|(cb, wrapped)| {
+        cb(s);
+        wrapped
+    })
+}
+
+/// Moves the focus to the first match while the search pattern is typed.
+fn preview_search(s: &mut Cursive, state: &State, line: &str, origin: usize, forward: bool) {
+    if line.is_empty() {
+        return cancel_search(s, state, origin);
+    }
+    let regex = state.config.borrow().options.search_regex;
+    // An incomplete regex is common while typing, so it is not reported.
+    if let Ok(matcher) = Matcher::new(line, regex) {
+        let len = with_current(s, |view| view.len()).unwrap_or(0);
+        if jump_to_match(s, &matcher, search_start(origin, forward, len), forward).is_none() {
+            if let Some(cb) = with_current(s, |view| view.set_selection(origin)) {
+                cb(s);
+            }
+        }
+    }
+}
+
+/// Focuses back `origin` and restores the highlighting of the last search.
+fn cancel_search(s: &mut Cursive, state: &State, origin: usize) {
+    if let Some(cb) = with_current(s, |view| view.set_selection(origin)) {
+        cb(s);
+    }
+    let last = state.search.borrow().clone();
+    match last {
+        Some(search) => {
+            with_current(s, move |view| highlight_matches(view, search.matcher));
+        }
+        None => {
+            with_current(s, |view| view.clear_highlight());
+        }
+    }
+}
+
+/// Runs the search typed in the console and remembers it for `n` and `N`.
+/// An empty pattern repeats the last search in the direction of the console.
+fn submit_search(s: &mut Cursive, state: &State, line: &str, origin: usize, forward: bool) {
+    let pattern = if line.is_empty() {
+        match state.search.borrow().as_ref() {
+            Some(search) => search.pattern.clone(),
+            None => return cancel_search(s, state, origin),
+        }
+    } else {
+        line.to_string()
+    };
+    let regex = state.config.borrow().options.search_regex;
+    let matcher = match Matcher::new(&pattern, regex) {
+        Ok(matcher) => matcher,
+        Err(e) => {
+            cancel_search(s, state, origin);
+            return set_status(s, &e.to_string());
+        }
+    };
+    *state.search.borrow_mut() = Some(Search {
+        pattern,
+        matcher,
+        forward,
+    });
+    if let Some(cb) = with_current(s, |view| view.set_selection(origin)) {
+        cb(s);
+    }
+    next_match(s, state, 1, false);
+}
+
+/// Focuses the `count`th next match of the last search, or the previous one if
+/// `reverse` is set, wrapping around the listing.
+fn next_match(s: &mut Cursive, state: &State, count: usize, reverse: bool) {
+    let search = match state.search.borrow().clone() {
+        Some(search) => search,
+        None => return set_status(s, "No previous search"),
+    };
+    let forward = search.forward != reverse;
+    let mut wrapped = false;
+    for _ in 0..count {
+        let (focus, len) = with_current(s, |view| (view.selected_id().unwrap_or(0), view.len()))
+            .unwrap_or((0, 0));
+        match jump_to_match(s, &search.matcher, search_start(focus, forward, len), forward) {
+            Some(w) => wrapped |= w,
+            None => return set_status(s, &format!("Pattern not found: {}", search.pattern)),
+        }
+    }
+    if wrapped && forward {
+        set_status(s, "Search hit BOTTOM, continuing at TOP");
+    } else if wrapped {
+        set_status(s, "Search hit TOP, continuing at BOTTOM");
+    }
+}
+
+/// Funtion to update status bar content and preview widget content on selection change.
+/// First the status bar is updated to show relevant permission details and size of file.
+/// Then preview is updated to reflect details about the selected entry. `child` is the index of
+/// the entry focused in `entry` when the tab left it, if `entry` is such a directory.
+fn update_info(
+    siv: &mut Cursive,
+    entry: &PathBuf,
+    child: Option<usize>,
+    options: &ConfigOptions,
+) {
+    preview::show(siv, entry, child, options);
+    let mut status = Entry::from(PathBuf::from(entry)).permission_string().unwrap();
+    if let Ok(metadata) = entry.metadata() {
+        if metadata.is_file() {
+            status = format!("{} {}", status, info::format_size(metadata.len(), options.size));
+        }
+    }
+    siv.call_on_id("status", |view: &mut TextView| view.set_content(status));
+}
+
+/// Formats the path shown in the top bar. If `shorten_title` is non-zero, only the
+/// last `shorten_title` components of the path are shown.
+fn format_title(path: &Path, shorten_title: usize) -> String {
+    let components: Vec<_> = path.components().collect();
+    if shorten_title == 0 || components.len() <= shorten_title {
+        return path.to_string_lossy().into_owned();
+    }
+    let tail: PathBuf = components[components.len() - shorten_title..].iter().collect();
+    format!(".../{}", tail.to_string_lossy())
 }
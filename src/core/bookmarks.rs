@@ -0,0 +1,101 @@
+//! View listing the bookmarks, to jump to them or delete them.
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use cursive::traits::{Boxable, Identifiable, Scrollable};
+use cursive::views::{Dialog, OnEventView, SelectView, TextView};
+use cursive::Cursive;
+
+use crate::utils::bookmarks::{Bookmarks, LAST};
+
+/// Shows every bookmark as `key  directory`.
+///
+/// `Enter` runs `on_jump` with the directory of the selected bookmark, `d` deletes it.
+/// Bookmarks predefined in `config.toml` are tagged and cannot be deleted from here.
+pub fn show_bookmarks<F>(s: &mut Cursive, bookmarks: &Rc<RefCell<Bookmarks>>, on_jump: F)
+where
+    F: Fn(&mut Cursive, PathBuf) + 'static,
+{
+    let on_jump = Rc::new(on_jump);
+    let mut list = SelectView::<char>::new();
+    fill_list(&mut list, &bookmarks.borrow());
+    let j_bookmarks = bookmarks.clone();
+    let j_jump = on_jump.clone();
+    list.set_on_submit(move |s, key: &char| jump(s, &j_bookmarks, *key, &*j_jump));
+    let (b_jump, b_delete, b_d) = (bookmarks.clone(), bookmarks.clone(), bookmarks.clone());
+    let dialog = Dialog::around(list.with_id("bookmarks").scrollable().min_width(50))
+        .title("Bookmarks")
+        .button("Jump", move |s| {
+            if let Some(key) = selected_key(s) {
+                jump(s, &b_jump, key, &*on_jump);
+            }
+        }).button("Delete", move |s| delete(s, &b_delete))
+        .dismiss_button("Close");
+    let view = OnEventView::new(dialog).on_event('d', move |s| delete(s, &b_d));
+    s.add_layer(view);
+}
+
+/// Returns the bookmarks as one `key  directory` line each, for prompts listing them.
+pub fn describe(bookmarks: &Bookmarks) -> String {
+    let lines: Vec<String> = bookmarks.list().iter().map(label).collect();
+    if lines.is_empty() {
+        String::from("No bookmarks")
+    } else {
+        lines.join("\n")
+    }
+}
+
+fn label((key, dir, predefined): &(char, PathBuf, bool)) -> String {
+    let tag = match (*key, *predefined) {
+        (LAST, _) => "  (last position)",
+        (_, true) => "  (config)",
+        _ => "",
+    };
+    format!("{}  {}{}", key, dir.display(), tag)
+}
+
+fn fill_list(view: &mut SelectView<char>, bookmarks: &Bookmarks) {
+    let selected = view.selected_id().unwrap_or(0);
+    view.clear();
+    for bookmark in bookmarks.list() {
+        view.add_item(label(&bookmark), bookmark.0);
+    }
+    view.set_selection(selected);
+}
+
+fn selected_key(s: &mut Cursive) -> Option<char> {
+    s.call_on_id("bookmarks", |view: &mut SelectView<char>| view.selection())
+        .and_then(|key| key)
+        .map(|key| *key)
+}
+
+fn set_status(s: &mut Cursive, message: String) {
+    s.call_on_id("status", |view: &mut TextView| view.set_content(message));
+}
+
+fn jump(
+    s: &mut Cursive,
+    bookmarks: &Rc<RefCell<Bookmarks>>,
+    key: char,
+    on_jump: &dyn Fn(&mut Cursive, PathBuf),
+) {
+    let dir = bookmarks.borrow().get(key).map(|dir| dir.to_path_buf());
+    if let Some(dir) = dir {
+        s.pop_layer();
+        on_jump(s, dir);
+    }
+}
+
+fn delete(s: &mut Cursive, bookmarks: &Rc<RefCell<Bookmarks>>) {
+    let key = match selected_key(s) {
+        Some(key) => key,
+        None => return,
+    };
+    match bookmarks.borrow_mut().remove(key) {
+        Ok(()) => set_status(s, format!("Deleted bookmark {}", key)),
+        Err(e) => set_status(s, e.to_string()),
+    }
+    let bookmarks = bookmarks.borrow();
+    s.call_on_id("bookmarks", |view: &mut SelectView<char>| fill_list(view, &bookmarks));
+}
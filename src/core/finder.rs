@@ -0,0 +1,185 @@
+//! Fuzzy finder overlay, listing the entries under a directory as a background walk finds
+//! them, best matches first.
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use cursive::event::Key;
+use cursive::traits::{Boxable, Identifiable, Scrollable};
+use cursive::views::{Dialog, EditView, LinearLayout, OnEventView, TextView};
+use cursive::Cursive;
+use walkdir::WalkDir;
+
+use crate::ui::MultiSelectView;
+use crate::utils::filter;
+use crate::utils::fuzzy;
+use crate::utils::ignore::IgnoreRules;
+
+/// The walk stops after finding this many entries.
+const MAX_ENTRIES: usize = 100_000;
+/// Maximum number of matches listed at once.
+const MAX_SHOWN: usize = 200;
+/// Minimum delay in ms between two refreshes of the list while walking.
+const REFRESH_DELAY: u64 = 100;
+
+/// Counts the finders opened, so that the walk of a closed finder stops.
+static SESSION: AtomicUsize = AtomicUsize::new(0);
+
+/// Entries found by a walk, relative to its root.
+#[derive(Default)]
+struct Walk {
+    entries: Mutex<Vec<PathBuf>>,
+    done: AtomicBool,
+    cancelled: AtomicBool,
+}
+
+/// Shows the finder for the entries under `root`. Hidden entries are only listed if
+/// `show_hidden` is set, and entries matched by `.gitignore` or `.ignore` files are skipped.
+///
+/// `Up` and `Down` move in the list, `Enter` closes the finder and runs `on_pick` with the
+/// selected entry.
+pub fn show_finder<F>(s: &mut Cursive, root: PathBuf, show_hidden: bool, on_pick: F)
+where
+    F: Fn(&mut Cursive, PathBuf) + 'static,
+{
+    let session = SESSION.fetch_add(1, Ordering::SeqCst) + 1;
+    let walk = Arc::new(Walk::default());
+    let on_pick = Rc::new(on_pick);
+
+    let l_pick = on_pick.clone();
+    let list = MultiSelectView::<PathBuf>::new().on_submit(move |s, path: &PathBuf| {
+        s.pop_layer();
+        l_pick(s, path.clone());
+    });
+    let (e_walk, e_root) = (walk.clone(), root.clone());
+    let query = EditView::new()
+        .on_edit(move |s, _, _| refresh(s, &e_walk, &e_root, false))
+        .on_submit(move |s, _| {
+            let path = s
+                .call_on_id("finder/list", |view: &mut MultiSelectView<PathBuf>| {
+                    view.selection()
+                }).and_then(|path| path);
+            if let Some(path) = path {
+                s.pop_layer();
+                on_pick(s, (*path).clone());
+            }
+        }).with_id("finder/query");
+    let query = OnEventView::new(query)
+        .on_pre_event(Key::Down, |s| move_selection(s, true))
+        .on_pre_event(Key::Up, |s| move_selection(s, false));
+    let layout = LinearLayout::vertical()
+        .child(query)
+        .child(TextView::new("").with_id("finder/count"))
+        .child(list.with_id("finder/list").scrollable().max_height(20));
+    s.add_layer(
+        Dialog::around(layout.min_width(70))
+            .title(format!("Find in {}", root.display()))
+            .dismiss_button("Close"),
+    );
+
+    let sink = s.cb_sink().clone();
+    thread::spawn(move || {
+        let mut ignore = IgnoreRules::new(&root);
+        let mut last_refresh = Instant::now();
+        let entries = WalkDir::new(&root).min_depth(1).into_iter().filter_entry(|e| {
+            filter::is_visible(e, show_hidden)
+                && e.file_name() != ".git"
+                && !ignore.is_ignored(e.path(), e.file_type().is_dir())
+        });
+        for entry in entries {
+            if walk.cancelled.load(Ordering::SeqCst) {
+                return;
+            }
+            let path = match entry {
+                Ok(entry) => entry.path().to_path_buf(),
+                Err(_) => continue,
+            };
+            if let Ok(relative) = path.strip_prefix(&root) {
+                let mut found = walk.entries.lock().unwrap();
+                found.push(relative.to_path_buf());
+                if found.len() >= MAX_ENTRIES {
+                    break;
+                }
+            }
+            if last_refresh.elapsed() >= Duration::from_millis(REFRESH_DELAY) {
+                last_refresh = Instant::now();
+                let (walk, root) = (walk.clone(), root.clone());
+                sink.send(Box::new(move |s: &mut Cursive| update(s, session, &walk, &root)));
+            }
+        }
+        walk.done.store(true, Ordering::SeqCst);
+        sink.send(Box::new(move |s: &mut Cursive| update(s, session, &walk, &root)));
+    });
+}
+
+/// Refreshes the list with the entries found so far, or stops the walk if its
+/// finder was closed.
+fn update(s: &mut Cursive, session: usize, walk: &Walk, root: &Path) {
+    let open = s
+        .call_on_id("finder/list", |_: &mut MultiSelectView<PathBuf>| ())
+        .is_some();
+    if !open || SESSION.load(Ordering::SeqCst) != session {
+        walk.cancelled.store(true, Ordering::SeqCst);
+        return;
+    }
+    refresh(s, walk, root, true);
+}
+
+/// Lists the entries matching the query, best matches first, shorter paths first
+/// among equal matches. The first match is selected unless `keep_selection` is set.
+fn refresh(s: &mut Cursive, walk: &Walk, root: &Path, keep_selection: bool) {
+    let pattern = s
+        .call_on_id("finder/query", |view: &mut EditView| view.get_content())
+        .map(|content| (*content).clone())
+        .unwrap_or_default();
+    let (mut matches, total) = {
+        let entries = walk.entries.lock().unwrap();
+        let matches: Vec<(i64, String)> = entries
+            .iter()
+            .filter_map(|path| {
+                let label = path.to_string_lossy().into_owned();
+                fuzzy::fuzzy_match(&pattern, &label).map(|(score, _)| (score, label))
+            }).collect();
+        (matches, entries.len())
+    };
+    matches.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.len().cmp(&b.1.len())));
+    let walking = if walk.done.load(Ordering::SeqCst) { "" } else { " ..." };
+    let count = format!("{}/{}{}", matches.len(), total, walking);
+    s.call_on_id("finder/count", |view: &mut TextView| view.set_content(count));
+    s.call_on_id("finder/list", |view: &mut MultiSelectView<PathBuf>| {
+        let selected = if keep_selection {
+            view.selected_id().unwrap_or(0)
+        } else {
+            0
+        };
+        view.clear();
+        for (_, label) in matches.iter().take(MAX_SHOWN) {
+            view.add_item(label.as_str(), root.join(label));
+        }
+        view.set_selection(selected);
+        view.set_highlight(move |label| {
+            fuzzy::fuzzy_match(&pattern, label)
+                .map(|(_, positions)| {
+                    positions
+                        .into_iter()
+                        .map(|start| {
+                            let len = label[start..].chars().next().map_or(1, char::len_utf8);
+                            (start, start + len)
+                        }).collect()
+                }).unwrap_or_default()
+        });
+    });
+}
+
+fn move_selection(s: &mut Cursive, down: bool) {
+    s.call_on_id("finder/list", |view: &mut MultiSelectView<PathBuf>| {
+        if down {
+            view.select_down(1);
+        } else {
+            view.select_up(1);
+        }
+    });
+}
@@ -0,0 +1,117 @@
+//! Results of `:grep`, listed over the `parent` and `current` columns while the `preview`
+//! pane shows the lines around the selected hit.
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::rc::Rc;
+
+use cursive::theme::Effect;
+use cursive::traits::{Boxable, Identifiable, Scrollable};
+use cursive::view::Position;
+use cursive::views::{Dialog, LayerPosition, SelectView};
+use cursive::Cursive;
+
+use crate::fs::grep::Hit;
+use crate::ui::preview::Span;
+use crate::ui::PreviewView;
+use crate::utils::search::Matcher;
+use crate::utils::text;
+
+/// Number of lines shown above and below the line of a hit.
+const CONTEXT: usize = 8;
+/// Width of the list of hits, about that of the `parent` and `current` columns.
+const LIST_WIDTH: usize = 72;
+
+/// Shows `hits` as `path:line: text`, with paths relative to `root`. Selecting a hit previews
+/// its surroundings, with tab stops every `tab_width` columns, `Enter` closes the list and
+/// runs `on_pick` with the hit.
+pub fn show_hits<F>(
+    s: &mut Cursive,
+    root: &Path,
+    hits: Vec<Hit>,
+    matcher: Matcher,
+    tab_width: usize,
+    on_pick: F,
+) where
+    F: Fn(&mut Cursive, &Hit) + 'static,
+{
+    let hits = Rc::new(hits);
+    let mut list = SelectView::<usize>::new();
+    for (index, hit) in hits.iter().enumerate() {
+        let path = hit.path.strip_prefix(root).unwrap_or(&hit.path);
+        list.add_item(
+            format!("{}:{}: {}", path.display(), hit.line, hit.text.trim_start()),
+            index,
+        );
+    }
+    let (p_hits, p_matcher) = (hits.clone(), matcher.clone());
+    list.set_on_select(move |s, index: &usize| {
+        preview(s, &p_hits[*index], &p_matcher, tab_width)
+    });
+    let s_hits = hits.clone();
+    list.set_on_submit(move |s, index: &usize| {
+        s.pop_layer();
+        on_pick(s, &s_hits[*index]);
+    });
+
+    let height = s.screen_size().y.saturating_sub(4).max(5);
+    s.add_layer(
+        Dialog::around(list.with_id("grep").scrollable())
+            .title(format!("Grep in {}", root.display()))
+            .dismiss_button("Close")
+            .fixed_width(LIST_WIDTH)
+            .max_height(height),
+    );
+    s.screen_mut()
+        .reposition_layer(LayerPosition::FromFront(0), Position::absolute((0, 1)));
+    if let Some(hit) = hits.first() {
+        preview(s, hit, &matcher, tab_width);
+    }
+}
+
+/// Shows the lines around `hit` in the `preview` pane, with line numbers and the
+/// matches of the hit line emphasized.
+fn preview(s: &mut Cursive, hit: &Hit, matcher: &Matcher, tab_width: usize) {
+    let first = hit.line.saturating_sub(CONTEXT).max(1);
+    let content = match read_lines(&hit.path, first, hit.line + CONTEXT) {
+        Ok(content) => content,
+        Err(e) => {
+            s.call_on_id("preview", |view: &mut PreviewView| view.set_message(e.to_string()));
+            return;
+        }
+    };
+    let mut lines = vec![vec![Span::plain(hit.path.display().to_string())], Vec::new()];
+    for (index, line) in content.iter().enumerate() {
+        let (number, line) = (first + index, text::expand_tabs(line, tab_width));
+        if number != hit.line {
+            lines.push(vec![Span::plain(format!("{:>5}  {}", number, line))]);
+            continue;
+        }
+        let mut spans = vec![Span::styled(format!("{:>5}> ", number), None, Some(Effect::Bold))];
+        let mut last = 0;
+        for (start, end) in matcher.ranges(&line) {
+            spans.push(Span::plain(&line[last..start]));
+            spans.push(Span::styled(&line[start..end], None, Some(Effect::Reverse)));
+            last = end;
+        }
+        spans.push(Span::plain(&line[last..]));
+        lines.push(spans);
+    }
+    s.call_on_id("preview", |view: &mut PreviewView| view.set_lines(lines, false));
+}
+
+/// Reads the lines numbered `first` to `last` of the file at `path`, stopping there rather
+/// than reading the whole file.
+fn read_lines(path: &Path, first: usize, last: usize) -> io::Result<Vec<String>> {
+    let mut lines = Vec::new();
+    for (index, line) in BufReader::new(File::open(path)?).split(b'\n').enumerate().take(last) {
+        let mut line = line?;
+        if index + 1 >= first {
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            lines.push(String::from_utf8_lossy(&line).into_owned());
+        }
+    }
+    Ok(lines)
+}
@@ -0,0 +1,367 @@
+//! Background jobs running long file operations (copy, move, delete, trash, archive) on worker
+//! threads, so that the UI stays responsive.
+//!
+//! Workers report their progress through `Cursive::cb_sink`, which updates the progress shown
+//! in the status bar and in the jobs view. Jobs can be paused and cancelled from the jobs view.
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+use cursive::event::Event;
+use cursive::traits::{Boxable, Identifiable, Scrollable};
+use cursive::views::{Dialog, OnEventView, SelectView, TextView};
+use cursive::{CbFunc, Cursive};
+
+use crate::error::*;
+use crate::fs::ops::Monitor;
+
+/// Minimum delay between two progress reports of a job.
+const REPORT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Delay between two checks of a paused job.
+const PAUSE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Kind of operation run by a job.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JobKind {
+    Copy,
+    Move,
+    Delete,
+    Trash,
+    Archive,
+    Undo,
+    Redo,
+    Find,
+    Grep,
+}
+
+impl JobKind {
+    fn name(self) -> &'static str {
+        match self {
+            JobKind::Copy => "copy",
+            JobKind::Move => "move",
+            JobKind::Delete => "delete",
+            JobKind::Trash => "trash",
+            JobKind::Archive => "archive",
+            JobKind::Undo => "undo",
+            JobKind::Redo => "redo",
+            JobKind::Find => "find",
+            JobKind::Grep => "grep",
+        }
+    }
+}
+
+/// Status of a job.
+#[derive(Clone, Debug, PartialEq)]
+pub enum JobStatus {
+    Running,
+    Done,
+    Failed(String),
+    Cancelled,
+}
+
+/// Files and bytes processed by a job, out of the totals measured when it started.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Progress {
+    pub files_done: u64,
+    pub files_total: u64,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+impl Progress {
+    /// Returns the completion of the job in percent, based on bytes or on files if no byte
+    /// has to be processed.
+    pub fn percent(&self) -> u64 {
+        (self.bytes_done * 100)
+            .checked_div(self.bytes_total)
+            .or_else(|| (self.files_done * 100).checked_div(self.files_total))
+            .map_or(0, |percent| percent.min(100))
+    }
+}
+
+/// A job, shared between its worker thread and the UI.
+pub struct Job {
+    pub id: usize,
+    pub kind: JobKind,
+    pub description: String,
+    cancelled: AtomicBool,
+    paused: AtomicBool,
+    progress: Mutex<Progress>,
+    status: Mutex<JobStatus>,
+}
+
+impl Job {
+    pub fn progress(&self) -> Progress {
+        *self.progress.lock().unwrap()
+    }
+
+    pub fn status(&self) -> JobStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.status() == JobStatus::Running
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Asks the worker to stop at the next checkpoint.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Pauses a running job, or resumes a paused one.
+    pub fn toggle_pause(&self) {
+        self.paused.fetch_xor(true, Ordering::SeqCst);
+    }
+
+    /// Returns a single line describing the job, as shown in the jobs view.
+    pub fn label(&self) -> String {
+        let progress = self.progress();
+        let status = match self.status() {
+            JobStatus::Running if self.is_paused() => String::from("paused"),
+            JobStatus::Running => format!("{}%", progress.percent()),
+            JobStatus::Done => String::from("done"),
+            JobStatus::Failed(reason) => format!("failed: {}", reason),
+            JobStatus::Cancelled => String::from("cancelled"),
+        };
+        format!(
+            "#{} {:<8} {} ({}/{} files) {}",
+            self.id,
+            self.kind.name(),
+            self.description,
+            progress.files_done,
+            progress.files_total,
+            status
+        )
+    }
+}
+
+/// Keeps track of every job and spawns their worker threads.
+#[derive(Clone)]
+pub struct JobManager {
+    jobs: Arc<Mutex<Vec<Arc<Job>>>>,
+    next_id: Arc<AtomicUsize>,
+    sink: Sender<Box<dyn CbFunc>>,
+}
+
+impl JobManager {
+    /// Creates a manager reporting progress to the cursive instance owning `sink`.
+    pub fn new(sink: Sender<Box<dyn CbFunc>>) -> Self {
+        Self {
+            jobs: Arc::new(Mutex::new(Vec::new())),
+            next_id: Arc::new(AtomicUsize::new(1)),
+            sink,
+        }
+    }
+
+    /// Runs `work` on a new worker thread and returns the id of the job.
+    ///
+    /// `work` is handed a `Worker`, which is the `Monitor` to pass to the operations of
+    /// `fs::ops`. Once `work` returns, the status bar shows the outcome and `finished_event` is
+    /// sent to the cursive instance.
+    pub fn spawn<F>(&self, kind: JobKind, description: String, work: F) -> usize
+    where
+        F: FnOnce(&Worker) -> Result<()> + Send + 'static,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let job = Arc::new(Job {
+            id,
+            kind,
+            description,
+            cancelled: AtomicBool::new(false),
+            paused: AtomicBool::new(false),
+            progress: Mutex::new(Progress::default()),
+            status: Mutex::new(JobStatus::Running),
+        });
+        self.jobs.lock().unwrap().push(job.clone());
+        let worker = Worker {
+            job,
+            manager: self.clone(),
+            last_report: Cell::new(Instant::now()),
+        };
+        thread::spawn(move || {
+            let status = match work(&worker) {
+                Ok(()) => JobStatus::Done,
+                Err(ref e) if worker.job.cancelled.load(Ordering::SeqCst) => {
+                    debug!("Job #{} stopped: {}", worker.job.id, e);
+                    JobStatus::Cancelled
+                }
+                Err(e) => JobStatus::Failed(e.to_string()),
+            };
+            *worker.job.status.lock().unwrap() = status;
+            let manager = worker.manager.clone();
+            let label = worker.job.label();
+            worker.manager.sink.send(Box::new(move |s: &mut Cursive| {
+                update_views(s, &manager);
+                s.call_on_id("status", |view: &mut TextView| view.set_content(label));
+                s.on_event(finished_event());
+            }));
+        });
+        self.report();
+        id
+    }
+
+    /// Returns every job, running or finished, oldest first.
+    pub fn jobs(&self) -> Vec<Arc<Job>> {
+        self.jobs.lock().unwrap().clone()
+    }
+
+    /// Returns the job with the given id.
+    pub fn get(&self, id: usize) -> Option<Arc<Job>> {
+        self.jobs().into_iter().find(|job| job.id == id)
+    }
+
+    /// Returns the number of running jobs.
+    pub fn running(&self) -> usize {
+        self.jobs().iter().filter(|job| job.is_running()).count()
+    }
+
+    /// Forgets every job which is not running anymore.
+    pub fn clear_finished(&self) {
+        self.jobs.lock().unwrap().retain(|job| job.is_running());
+    }
+
+    /// Returns the progress of the running jobs as shown in the status bar, or an empty string
+    /// if no job is running.
+    pub fn summary(&self) -> String {
+        let running: Vec<Progress> = self
+            .jobs()
+            .iter()
+            .filter(|job| job.is_running())
+            .map(|job| job.progress())
+            .collect();
+        if running.is_empty() {
+            return String::new();
+        }
+        let total = running.iter().fold(Progress::default(), |acc, p| Progress {
+            files_done: acc.files_done + p.files_done,
+            files_total: acc.files_total + p.files_total,
+            bytes_done: acc.bytes_done + p.bytes_done,
+            bytes_total: acc.bytes_total + p.bytes_total,
+        });
+        format!(" [{} job(s): {}%] ", running.len(), total.percent())
+    }
+
+    /// Refreshes the progress shown by the UI.
+    fn report(&self) {
+        let manager = self.clone();
+        self.sink
+            .send(Box::new(move |s: &mut Cursive| update_views(s, &manager)));
+    }
+}
+
+/// Handle given to the work of a job, through which it reports progress.
+pub struct Worker {
+    job: Arc<Job>,
+    manager: JobManager,
+    last_report: Cell<Instant>,
+}
+
+impl Worker {
+    /// Sets the number of files and bytes the job has to process.
+    pub fn set_totals(&self, files: u64, bytes: u64) {
+        {
+            let mut progress = self.job.progress.lock().unwrap();
+            progress.files_total = files;
+            progress.bytes_total = bytes;
+        }
+        self.manager.report();
+    }
+}
+
+impl Monitor for Worker {
+    fn advance(&self, files: u64, bytes: u64) -> Result<()> {
+        {
+            let mut progress = self.job.progress.lock().unwrap();
+            progress.files_done += files;
+            progress.bytes_done += bytes;
+        }
+        if self.job.is_paused() {
+            self.manager.report();
+            while self.job.is_paused() && !self.job.cancelled.load(Ordering::SeqCst) {
+                thread::sleep(PAUSE_INTERVAL);
+            }
+        }
+        if self.job.cancelled.load(Ordering::SeqCst) {
+            return Err(ErrorKind::Cancelled.into());
+        }
+        if self.last_report.get().elapsed() >= REPORT_INTERVAL {
+            self.last_report.set(Instant::now());
+            self.manager.report();
+        }
+        Ok(())
+    }
+}
+
+/// Event sent to the cursive instance whenever a job finishes, so that listings can be
+/// refreshed.
+pub fn finished_event() -> Event {
+    Event::Unknown(b"marcos:job-finished".to_vec())
+}
+
+/// Shows the list of running and finished jobs.
+///
+/// `p` pauses or resumes the selected job, `c` cancels it.
+pub fn show_jobs(s: &mut Cursive, manager: &JobManager) {
+    let mut list = SelectView::<usize>::new();
+    fill_list(&mut list, manager);
+    let (m_pause, m_cancel, m_clear) = (manager.clone(), manager.clone(), manager.clone());
+    let (m_p, m_c) = (manager.clone(), manager.clone());
+    let dialog = Dialog::around(list.with_id("jobs").scrollable().min_width(60))
+        .title("Jobs")
+        .button("Pause/Resume", move |s| toggle_pause(s, &m_pause))
+        .button("Cancel job", move |s| cancel(s, &m_cancel))
+        .button("Clear finished", move |s| {
+            m_clear.clear_finished();
+            update_views(s, &m_clear);
+        }).dismiss_button("Close");
+    let view = OnEventView::new(dialog)
+        .on_event('p', move |s| toggle_pause(s, &m_p))
+        .on_event('c', move |s| cancel(s, &m_c));
+    s.add_layer(view);
+}
+
+/// Updates the job progress in the status bar and the jobs view, if shown.
+fn update_views(s: &mut Cursive, manager: &JobManager) {
+    let summary = manager.summary();
+    s.call_on_id("status/jobs", |view: &mut TextView| view.set_content(summary));
+    s.call_on_id("jobs", |view: &mut SelectView<usize>| fill_list(view, manager));
+}
+
+fn fill_list(view: &mut SelectView<usize>, manager: &JobManager) {
+    let selected = view.selected_id().unwrap_or(0);
+    view.clear();
+    for job in manager.jobs() {
+        view.add_item(job.label(), job.id);
+    }
+    view.set_selection(selected);
+}
+
+fn selected_job(s: &mut Cursive, manager: &JobManager) -> Option<Arc<Job>> {
+    s.call_on_id("jobs", |view: &mut SelectView<usize>| view.selection())
+        .and_then(|id| id)
+        .and_then(|id| manager.get(*id))
+}
+
+fn toggle_pause(s: &mut Cursive, manager: &JobManager) {
+    if let Some(job) = selected_job(s, manager) {
+        if job.is_running() {
+            job.toggle_pause();
+        }
+    }
+    update_views(s, manager);
+}
+
+fn cancel(s: &mut Cursive, manager: &JobManager) {
+    if let Some(job) = selected_job(s, manager) {
+        job.cancel();
+    }
+    update_views(s, manager);
+}
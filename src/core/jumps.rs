@@ -0,0 +1,27 @@
+//! View listing the directories visited in a tab, to go back to one of them.
+use std::path::PathBuf;
+
+use cursive::traits::{Boxable, Scrollable};
+use cursive::views::{Dialog, SelectView};
+use cursive::Cursive;
+
+/// Shows `dirs`, the directories visited in a tab along with their index in its jump list,
+/// most recent first. `Enter` runs `on_pick` with the index of the selected directory.
+pub fn show_history<F>(s: &mut Cursive, dirs: Vec<(usize, PathBuf)>, current: usize, on_pick: F)
+where
+    F: Fn(&mut Cursive, usize) + 'static,
+{
+    let mut list = SelectView::<usize>::new();
+    for (index, dir) in dirs {
+        let tag = if index == current { "  (current)" } else { "" };
+        list.add_item(format!("{}{}", dir.display(), tag), index);
+    }
+    list.set_on_submit(move |s, index: &usize| {
+        s.pop_layer();
+        on_pick(s, *index);
+    });
+    let dialog = Dialog::around(list.scrollable().min_width(50))
+        .title("History")
+        .dismiss_button("Close");
+    s.add_layer(dialog);
+}
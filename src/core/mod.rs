@@ -1,2 +1,10 @@
 //! Core module of the app
 pub mod app;
+pub mod bookmarks;
+pub mod finder;
+pub mod grep;
+pub mod jobs;
+pub mod jumps;
+pub mod preview;
+pub mod rename;
+pub mod trash;
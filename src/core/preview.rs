@@ -0,0 +1,378 @@
+//! Contents of the `preview` pane for the entry selected in the `current` column.
+//!
+//! Directories are listed as in the `current` column. Source files are shown plain at first,
+//! then highlighted by a background thread. Highlighted files are cached by path,
+//! modification time and the options changing their lines, so that coming back to them is
+//! instant. Pictures are drawn with half blocks if `show_images` is set, and other binary
+//! files are dumped in hexadecimal.
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::env;
+use std::fs as stdfs;
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::SystemTime;
+
+use crossbeam_channel::Sender;
+use cursive::theme::{Color, ColorStyle, Effect, PaletteColor};
+use cursive::vec::Vec2;
+use cursive::{CbFunc, Cursive};
+use mime_guess::guess_mime_type;
+
+use crate::config::options::ConfigOptions;
+use crate::fs::grep;
+use crate::ui::preview::{Line, Span};
+use crate::ui::tab;
+use crate::ui::PreviewView;
+use crate::utils::highlight::{self, Class, Syntax};
+use crate::utils::picture::{self, Cell, Rgb};
+use crate::utils::{hex, info, sort, text};
+
+/// Number of entries listed at most in the preview of a directory.
+const MAX_LISTED: usize = 1000;
+/// Number of bytes of binary files dumped at most.
+const MAX_DUMPED: usize = 4096;
+/// Number of highlighted files kept in the cache.
+const MAX_CACHED: usize = 32;
+
+thread_local! {
+    /// Highlighted lines of the files previewed lately, most recent last. Only used from the
+    /// UI thread.
+    static HIGHLIGHTED: RefCell<VecDeque<(Key, Vec<Line>)>> = RefCell::new(VecDeque::new());
+    /// Queue of the thread highlighting files, once started.
+    static HIGHLIGHTER: RefCell<Option<mpsc::Sender<Request>>> = RefCell::new(None);
+}
+
+/// What the highlighted lines of a file depend on.
+#[derive(Clone, Debug, PartialEq)]
+struct Key {
+    path: PathBuf,
+    mtime: SystemTime,
+    tab_width: usize,
+    max_size: usize,
+}
+
+/// Lines of a file to highlight, then show if the preview numbered `generation` is still
+/// shown.
+struct Request {
+    key: Key,
+    syntax: &'static Syntax,
+    lines: Vec<String>,
+    generation: usize,
+    sink: Sender<Box<dyn CbFunc>>,
+}
+
+/// Shows `path` in the `preview` pane: the entries of directories, with the entry at index
+/// `child` highlighted, pictures, the start of text files, at most `preview_max_size` bytes,
+/// and a dump of the start of binary files. Pipes, sockets and devices are only described.
+pub fn show(s: &mut Cursive, path: &Path, child: Option<usize>, options: &ConfigOptions) {
+    if path.is_dir() {
+        return show_dir(s, path, child, options);
+    }
+    if options.show_images && picture::is_picture(path) {
+        return show_picture(s, path);
+    }
+    let metadata = match path.metadata() {
+        Ok(metadata) => metadata,
+        Err(e) => return set_message(s, &format!("Cannot read {}: {}", path.display(), e)),
+    };
+    // Reading a pipe or a device could block the UI, or never end.
+    if !metadata.is_file() {
+        return set_message(s, &format!("{}: {}", path.display(), describe(&metadata)));
+    }
+    let (bytes, truncated) = match text::read_head(path, options.preview_max_size) {
+        Ok(head) => head,
+        Err(e) => return set_message(s, &format!("Cannot read {}: {}", path.display(), e)),
+    };
+    if grep::is_binary(&bytes) {
+        return show_dump(s, path, &bytes, options);
+    }
+    let lines: Vec<String> = String::from_utf8_lossy(&bytes)
+        .lines()
+        .map(|line| text::expand_tabs(line, options.tab_width))
+        .collect();
+    let footer = if truncated {
+        vec![vec![Span::styled(
+            format!("-- first {} bytes shown --", options.preview_max_size),
+            Some(ColorStyle::secondary()),
+            None,
+        )]]
+    } else {
+        Vec::new()
+    };
+    let (numbered, wrap) = (options.line_numbers, options.preview_wrap);
+    let syntax = highlight::detect(path, lines.first().map_or("", String::as_str));
+    let key = metadata.modified().ok().map(|mtime| Key {
+        path: path.to_path_buf(),
+        mtime,
+        tab_width: options.tab_width,
+        max_size: options.preview_max_size,
+    });
+
+    let cached = key.as_ref().and_then(cached);
+    let highlighted = cached.is_some();
+    let shown = cached.unwrap_or_else(|| {
+        lines
+            .iter()
+            .map(|line| vec![Span::plain(line.as_str())])
+            .collect()
+    });
+    let generation = s.call_on_id("preview", |view: &mut PreviewView| {
+        view.set_wrap(wrap);
+        view.set_lines(shown, numbered);
+        view.set_footer(footer);
+        view.generation()
+    });
+    if highlighted {
+        return;
+    }
+    if let (Some(syntax), Some(key), Some(generation)) = (syntax, key, generation) {
+        highlight_later(Request {
+            key,
+            syntax,
+            lines,
+            generation,
+            sink: s.cb_sink().clone(),
+        });
+    }
+}
+
+/// Hands `request` to the thread highlighting files, starting it if needed.
+fn highlight_later(request: Request) {
+    HIGHLIGHTER.with(|highlighter| {
+        let mut highlighter = highlighter.borrow_mut();
+        let request = match *highlighter {
+            Some(ref sender) => match sender.send(request) {
+                Ok(()) => return,
+                Err(mpsc::SendError(request)) => request,
+            },
+            None => request,
+        };
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || highlight_all(&receiver));
+        if sender.send(request).is_ok() {
+            *highlighter = Some(sender);
+        }
+    });
+}
+
+/// Highlights the files requested, skipping the requests followed by others meanwhile, as
+/// their preview is no longer shown.
+fn highlight_all(requests: &mpsc::Receiver<Request>) {
+    while let Ok(mut request) = requests.recv() {
+        while let Ok(later) = requests.try_recv() {
+            request = later;
+        }
+        let Request {
+            key,
+            syntax,
+            lines,
+            generation,
+            sink,
+        } = request;
+        let styled: Vec<Line> = highlight::highlight(syntax, &lines)
+            .into_iter()
+            .map(|runs| runs.into_iter().map(|(class, text)| span(class, text)).collect())
+            .collect();
+        sink.send(Box::new(move |s: &mut Cursive| {
+            remember(key, styled.clone());
+            s.call_on_id("preview", |view: &mut PreviewView| {
+                if view.generation() == generation {
+                    view.restyle(styled);
+                }
+            });
+        }));
+    }
+}
+
+/// Describes an entry which is neither a directory nor a regular file.
+fn describe(metadata: &stdfs::Metadata) -> &'static str {
+    let kind = metadata.file_type();
+    if kind.is_fifo() {
+        "named pipe (FIFO)"
+    } else if kind.is_socket() {
+        "socket"
+    } else if kind.is_block_device() {
+        "block device"
+    } else if kind.is_char_device() {
+        "character device"
+    } else {
+        "special file"
+    }
+}
+
+/// Lists the entries of `dir`, sorted and filtered as in the `current` column, below
+/// the number of directories and files.
+fn show_dir(s: &mut Cursive, dir: &Path, child: Option<usize>, options: &ConfigOptions) {
+    if let Err(e) = stdfs::read_dir(dir) {
+        return set_message(s, &format!("Cannot read {}: {}", dir.display(), e));
+    }
+    let entries = sort::listing(dir, options);
+    let dirs = entries.iter().filter(|entry| entry.path().is_dir()).count();
+    let count = format!("{} directories, {} files", dirs, entries.len() - dirs);
+    let mut lines = vec![vec![Span::styled(count, Some(ColorStyle::secondary()), None)]];
+    for (index, entry) in entries.iter().enumerate().take(MAX_LISTED) {
+        let label = tab::label(entry.path(), &entry.file_name().to_string_lossy());
+        let span = if child == Some(index) {
+            Span::styled(label, Some(ColorStyle::highlight()), None)
+        } else if index < dirs {
+            Span::styled(label, None, Some(Effect::Bold))
+        } else {
+            Span::plain(label)
+        };
+        lines.push(vec![span]);
+    }
+    if entries.len() > MAX_LISTED {
+        let more = format!("... {} more", entries.len() - MAX_LISTED);
+        lines.push(vec![Span::styled(more, Some(ColorStyle::secondary()), None)]);
+    }
+    s.call_on_id("preview", |view: &mut PreviewView| {
+        view.set_wrap(false);
+        view.set_lines(lines, false);
+        if let Some(child) = child {
+            // The first line holds the counts.
+            view.reveal(child + 1);
+        }
+    });
+}
+
+/// Draws a picture scaled down to the size of the pane. It is decoded by a background
+/// thread, and drawn unless another entry was previewed meanwhile.
+fn show_picture(s: &mut Cursive, path: &Path) {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let shown = s.call_on_id("preview", |view: &mut PreviewView| {
+        view.set_wrap(false);
+        view.set_message(format!("Loading {}...", name));
+        (view.size(), view.generation())
+    });
+    let (size, generation) = match shown {
+        // Before the first layout, guess the size of the pane.
+        Some((size, generation)) if size.x == 0 => (Vec2::new(40, 20), generation),
+        Some(shown) => shown,
+        None => return,
+    };
+    let truecolor = env::var("COLORTERM").map_or(false, |c| c == "truecolor" || c == "24bit");
+    let (sink, path) = (s.cb_sink().clone(), path.to_path_buf());
+    thread::spawn(move || {
+        let lines = match picture::render(&path, size.x, size.y) {
+            Ok(rows) => Ok(rows
+                .into_iter()
+                .map(|row| row.into_iter().map(|cell| cell_span(cell, truecolor)).collect())
+                .collect::<Vec<Line>>()),
+            Err(e) => Err(e.to_string()),
+        };
+        sink.send(Box::new(move |s: &mut Cursive| {
+            s.call_on_id("preview", |view: &mut PreviewView| {
+                if view.generation() != generation {
+                    return;
+                }
+                match lines {
+                    Ok(lines) => view.set_lines(lines, false),
+                    Err(e) => view.set_message(e),
+                }
+            });
+        }));
+    });
+}
+
+/// Draws a cell of a picture, in 24-bit colours if `truecolor` is set.
+fn cell_span((upper, lower): Cell, truecolor: bool) -> Span {
+    let color = |(r, g, b): Rgb| {
+        if truecolor {
+            Color::Rgb(r, g, b)
+        } else {
+            let (r, g, b) = picture::low_res((r, g, b));
+            Color::RgbLowRes(r, g, b)
+        }
+    };
+    let style = match lower {
+        Some(lower) => ColorStyle::new(color(upper), color(lower)),
+        None => ColorStyle::new(color(upper), PaletteColor::View),
+    };
+    Span::styled(picture::UPPER_HALF, Some(style), None)
+}
+
+/// Shows the first bytes of a binary file as a hexadecimal dump, below its MIME type.
+fn show_dump(s: &mut Cursive, path: &Path, bytes: &[u8], options: &ConfigOptions) {
+    let mime = guess_mime_type(path);
+    let size = path.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+    let length = info::format_size(size, options.size);
+    let header = format!("{}/{}, {}", mime.type_(), mime.subtype(), length);
+    let mut lines = vec![vec![Span::styled(header, Some(ColorStyle::secondary()), None)]];
+    let shown = &bytes[..bytes.len().min(MAX_DUMPED)];
+    for (index, chunk) in shown.chunks(hex::ROW_SIZE).enumerate() {
+        lines.push(vec![Span::plain(hex::row((index * hex::ROW_SIZE) as u64, chunk))]);
+    }
+    if (shown.len() as u64) < size {
+        let more = format!("-- first {} bytes shown --", shown.len());
+        lines.push(vec![Span::styled(more, Some(ColorStyle::secondary()), None)]);
+    }
+    s.call_on_id("preview", |view: &mut PreviewView| {
+        view.set_wrap(false);
+        view.set_lines(lines, false);
+    });
+}
+
+/// Returns the highlighted lines of a file if they are cached for `key`.
+fn cached(key: &Key) -> Option<Vec<Line>> {
+    HIGHLIGHTED.with(|cache| {
+        cache
+            .borrow()
+            .iter()
+            .find(|(cached, _)| cached == key)
+            .map(|(_, lines)| lines.clone())
+    })
+}
+
+/// Caches the highlighted lines of a file, forgetting the oldest files if needed.
+fn remember(key: Key, lines: Vec<Line>) {
+    HIGHLIGHTED.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        cache.retain(|(cached, _)| cached.path != key.path);
+        if cache.len() >= MAX_CACHED {
+            cache.pop_front();
+        }
+        cache.push_back((key, lines));
+    });
+}
+
+/// Styles a run of highlighted text with the colours of the theme.
+fn span(class: Class, text: String) -> Span {
+    let palette = |color: PaletteColor| Some(ColorStyle::new(color, PaletteColor::View));
+    match class {
+        Class::Plain => Span::plain(text),
+        Class::Keyword => {
+            Span::styled(text, palette(PaletteColor::TitlePrimary), Some(Effect::Bold))
+        }
+        Class::Type => Span::styled(text, palette(PaletteColor::TitleSecondary), None),
+        Class::String => Span::styled(text, palette(PaletteColor::Tertiary), None),
+        Class::Number => Span::styled(text, palette(PaletteColor::Highlight), None),
+        Class::Comment => {
+            Span::styled(text, Some(ColorStyle::secondary()), Some(Effect::Italic))
+        }
+    }
+}
+
+/// Shows a message instead of the contents of an entry.
+fn set_message(s: &mut Cursive, message: &str) {
+    s.call_on_id("preview", |view: &mut PreviewView| view.set_message(message));
+}
+
+/// Scrolls the `preview` pane by `count` rows, or half pages if `pages` is set.
+pub fn scroll(s: &mut Cursive, count: usize, down: bool, pages: bool) {
+    s.call_on_id("preview", |view: &mut PreviewView| {
+        let rows = if pages { count * view.half_page() } else { count };
+        if down {
+            view.scroll_down(rows);
+        } else {
+            view.scroll_up(rows);
+        }
+    });
+}
+
+/// Wraps, or cuts, the lines of the `preview` pane.
+pub fn set_wrap(s: &mut Cursive, wrap: bool) {
+    s.call_on_id("preview", |view: &mut PreviewView| view.set_wrap(wrap));
+}
@@ -0,0 +1,118 @@
+//! Dialog renaming many entries at once with a `Pattern`, previewing the new names as they are
+//! typed.
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use cursive::theme::{BaseColor, Color, ColorStyle, PaletteColor};
+use cursive::traits::{Boxable, Identifiable, Scrollable};
+use cursive::utils::markup::StyledString;
+use cursive::views::{Dialog, DummyView, EditView, LinearLayout, ListView, SelectView, TextView};
+use cursive::Cursive;
+
+use crate::fs::rename::{self, Case, Pattern, Plan};
+
+/// Maximum number of entries shown in the preview.
+const MAX_PREVIEW: usize = 500;
+
+/// Shows the rename dialog for `paths`. `on_apply` is called with the plan once the user
+/// confirms, which is only possible when every entry can be renamed.
+pub fn show_rename<F>(s: &mut Cursive, paths: Vec<PathBuf>, on_apply: F)
+where
+    F: Fn(&mut Cursive, &Plan) + 'static,
+{
+    let paths = Rc::new(paths);
+    let plan: Rc<RefCell<Option<Plan>>> = Rc::new(RefCell::new(None));
+
+    let field = |id: &'static str| {
+        let (f_paths, f_plan) = (paths.clone(), plan.clone());
+        EditView::new()
+            .on_edit(move |s, _, _| update(s, &f_paths, &f_plan))
+            .with_id(id)
+    };
+    let (c_paths, c_plan) = (paths.clone(), plan.clone());
+    let case = SelectView::<Case>::new()
+        .popup()
+        .item("Keep", Case::Keep)
+        .item("lower", Case::Lower)
+        .item("UPPER", Case::Upper)
+        .item("Title", Case::Title)
+        .on_submit(move |s, _: &Case| update(s, &c_paths, &c_plan))
+        .with_id("rename/case");
+    let form = ListView::new()
+        .child("Find (regex)", field("rename/find"))
+        .child("Replace", field("rename/replace"))
+        .child("Case", case)
+        .child("Extension", field("rename/extension"));
+    let layout = LinearLayout::vertical()
+        .child(form)
+        .child(DummyView)
+        .child(
+            TextView::new("")
+                .with_id("rename/preview")
+                .scrollable()
+                .max_height(15),
+        );
+
+    let a_plan = plan.clone();
+    let dialog = Dialog::around(layout.min_width(70))
+        .title(format!("Rename {} entries", paths.len()))
+        .button("Rename", move |s| {
+            let plan = a_plan.borrow().clone();
+            if let Some(plan) = plan {
+                s.pop_layer();
+                on_apply(s, &plan);
+            }
+        }).dismiss_button("Cancel");
+    s.add_layer(dialog);
+    update(s, &paths, &plan);
+}
+
+/// Rebuilds the pattern from the fields and previews it.
+fn update(s: &mut Cursive, paths: &[PathBuf], plan: &Rc<RefCell<Option<Plan>>>) {
+    let text = |s: &mut Cursive, id: &str| {
+        s.call_on_id(id, |view: &mut EditView| view.get_content())
+            .map(|content| (*content).clone())
+            .unwrap_or_default()
+    };
+    let (find, replace, extension) = (
+        text(s, "rename/find"),
+        text(s, "rename/replace"),
+        text(s, "rename/extension"),
+    );
+    let case = s
+        .call_on_id("rename/case", |view: &mut SelectView<Case>| view.selection())
+        .and_then(|case| case)
+        .map_or(Case::Keep, |case| *case);
+
+    let mut content = StyledString::new();
+    let error = ColorStyle::new(Color::Dark(BaseColor::Red), PaletteColor::View);
+    *plan.borrow_mut() = match Pattern::new(&find, &replace, case, &extension) {
+        Ok(pattern) => {
+            let (rows, new_plan) = rename::preview(paths, &pattern);
+            let width = paths.iter().map(|path| name(path).len()).max().unwrap_or(0);
+            for (path, (new, problem)) in paths.iter().zip(rows).take(MAX_PREVIEW) {
+                content.append_plain(format!("{:w$} -> {}", name(path), new, w = width));
+                if let Some(problem) = problem {
+                    content.append_styled(format!("  ({})", problem), error);
+                }
+                content.append_plain("\n");
+            }
+            if paths.len() > MAX_PREVIEW {
+                content.append_plain(format!("({} more)\n", paths.len() - MAX_PREVIEW));
+            }
+            new_plan
+        }
+        Err(e) => {
+            content.append_styled(e.to_string(), error);
+            None
+        }
+    };
+    s.call_on_id("rename/preview", |view: &mut TextView| view.set_content(content));
+}
+
+fn name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
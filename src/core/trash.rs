@@ -0,0 +1,128 @@
+//! View listing the entries of the trash, to restore them or delete them permanently.
+use cursive::traits::{Boxable, Identifiable, Scrollable};
+use cursive::views::{Dialog, OnEventView, SelectView, TextView};
+use cursive::Cursive;
+
+use crate::core::jobs::{JobKind, JobManager};
+use crate::fs::ops;
+use crate::fs::trash::{Trash, TrashEntry};
+use crate::ui::prompt;
+
+/// Shows the entries of the home trash.
+///
+/// `r` restores the selected entry, `D` deletes it permanently. Both run as background jobs,
+/// and permanent deletions ask for confirmation if `confirm` is set.
+pub fn show_trash(s: &mut Cursive, jobs: &JobManager, confirm: bool, popup: bool) {
+    let trash = match Trash::home() {
+        Ok(trash) => trash,
+        Err(e) => {
+            s.call_on_id("status", |view: &mut TextView| {
+                view.set_content(format!("Cannot open trash: {}", e))
+            });
+            return;
+        }
+    };
+    let mut list = SelectView::<TrashEntry>::new();
+    fill_list(&mut list, &trash);
+    let (t_restore, t_erase, t_empty) = (trash.clone(), trash.clone(), trash.clone());
+    let (t_r, t_d) = (trash.clone(), trash.clone());
+    let (j_restore, j_erase, j_empty) = (jobs.clone(), jobs.clone(), jobs.clone());
+    let (j_r, j_d) = (jobs.clone(), jobs.clone());
+    let dialog = Dialog::around(list.with_id("trash").scrollable().min_width(60))
+        .title("Trash")
+        .button("Restore", move |s| restore(s, &t_restore, &j_restore))
+        .button("Delete", move |s| erase(s, &t_erase, &j_erase, confirm, popup))
+        .button("Empty", move |s| empty(s, &t_empty, &j_empty, confirm, popup))
+        .dismiss_button("Close");
+    let view = OnEventView::new(dialog)
+        .on_event('r', move |s| restore(s, &t_r, &j_r))
+        .on_event('D', move |s| erase(s, &t_d, &j_d, confirm, popup));
+    s.add_layer(view);
+}
+
+fn fill_list(view: &mut SelectView<TrashEntry>, trash: &Trash) {
+    let selected = view.selected_id().unwrap_or(0);
+    view.clear();
+    match trash.list() {
+        Ok(entries) => for entry in entries {
+            let label = format!("{}  ({})", entry.original.display(), entry.deleted);
+            view.add_item(label, entry);
+        },
+        Err(e) => error!("Cannot list trash: {}", e),
+    }
+    view.set_selection(selected);
+}
+
+fn selected_entry(s: &mut Cursive) -> Option<TrashEntry> {
+    s.call_on_id("trash", |view: &mut SelectView<TrashEntry>| view.selection())
+        .and_then(|entry| entry)
+        .map(|entry| (*entry).clone())
+}
+
+fn remove_selected(s: &mut Cursive) {
+    s.call_on_id("trash", |view: &mut SelectView<TrashEntry>| {
+        if let Some(id) = view.selected_id() {
+            view.remove_item(id);
+        }
+    });
+}
+
+fn set_status(s: &mut Cursive, message: String) {
+    s.call_on_id("status", |view: &mut TextView| view.set_content(message));
+}
+
+fn restore(s: &mut Cursive, trash: &Trash, jobs: &JobManager) {
+    let entry = match selected_entry(s) {
+        Some(entry) => entry,
+        None => return,
+    };
+    set_status(s, format!("Restoring {}", entry.original.display()));
+    let trash = trash.clone();
+    let description = format!("{} from trash", entry.name);
+    jobs.spawn(JobKind::Trash, description, move |worker| {
+        let (files, bytes) = ops::measure(&[trash.file_path(&entry)]);
+        worker.set_totals(files, bytes);
+        trash.restore(&entry, worker).map(|_| ())
+    });
+    // The entry disappears from the list once the job is over.
+    remove_selected(s);
+}
+
+fn erase(s: &mut Cursive, trash: &Trash, jobs: &JobManager, confirm: bool, popup: bool) {
+    let entry = match selected_entry(s) {
+        Some(entry) => entry,
+        None => return,
+    };
+    let (trash, jobs) = (trash.clone(), jobs.clone());
+    let message = format!("Permanently delete {}?", entry.original.display());
+    let action = move |s: &mut Cursive| {
+        let (trash, entry) = (trash.clone(), entry.clone());
+        let description = format!("{} from trash", entry.name);
+        jobs.spawn(JobKind::Delete, description, move |worker| {
+            trash.erase(&entry, worker)
+        });
+        // The entry disappears from the list once the job is over.
+        remove_selected(s);
+    };
+    if confirm {
+        prompt::confirm(s, &message, popup, action);
+    } else {
+        action(s);
+    }
+}
+
+fn empty(s: &mut Cursive, trash: &Trash, jobs: &JobManager, confirm: bool, popup: bool) {
+    let (trash, jobs) = (trash.clone(), jobs.clone());
+    let action = move |s: &mut Cursive| {
+        let trash = trash.clone();
+        jobs.spawn(JobKind::Delete, String::from("empty trash"), move |worker| {
+            trash.empty(worker)
+        });
+        s.call_on_id("trash", |view: &mut SelectView<TrashEntry>| view.clear());
+    };
+    if confirm {
+        prompt::confirm(s, "Permanently delete every entry of the trash?", popup, action);
+    } else {
+        action(s);
+    }
+}
@@ -1,6 +1,7 @@
 //! Error handling for marcos file manager
 
 use std::io;
+use std::path::Path;
 use std::{fmt, result};
 
 use log::SetLoggerError;
@@ -17,7 +18,7 @@ pub struct Error {
 }
 
 impl Fail for Error {
-    fn cause(&self) -> Option<&Fail> {
+    fn cause(&self) -> Option<&dyn Fail> {
         self.inner.cause()
     }
 
@@ -26,6 +27,13 @@ impl Fail for Error {
     }
 }
 
+impl Error {
+    /// Returns the kind of the error.
+    pub fn kind(&self) -> &ErrorKind {
+        self.inner.get_context()
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         fmt::Display::fmt(&self.inner, f)
@@ -35,17 +43,47 @@ impl fmt::Display for Error {
 /// Kinds of error which need to be handled
 #[derive(Debug, Fail)]
 pub enum ErrorKind {
-    #[fail(display = "IO Error")]
+    #[fail(display = "IO Error: {}", _0)]
     Io(#[cause] io::Error),
 
+    #[fail(display = "IO Error on {}: {}", path, cause)]
+    IoOnPath {
+        path: String,
+        #[cause]
+        cause: io::Error,
+    },
+
     #[fail(display = "Directory not found: {}", dirname)]
     DirNotFound { dirname: String },
 
     #[fail(display = "Error while initializing logger!")]
     LogInitError(#[cause] SetLoggerError),
     // TODO handle generic error
-    #[fail(display = "Toml deserialization error")]
-    TomlDeError(#[cause] de::Error),
+    #[fail(
+        display = "Toml deserialization error in {} at line {}, column {}",
+        file,
+        line,
+        column
+    )]
+    TomlDeError {
+        file: String,
+        line: usize,
+        column: usize,
+        #[cause]
+        cause: de::Error,
+    },
+
+    #[fail(display = "Invalid key binding: {}", binding)]
+    InvalidKeyBinding { binding: String },
+
+    #[fail(display = "Invalid operation: {}", reason)]
+    InvalidOperation { reason: String },
+
+    #[fail(display = "{}", reason)]
+    InvalidCommand { reason: String },
+
+    #[fail(display = "Operation cancelled")]
+    Cancelled,
 
     #[fail(display = "Generic Error")]
     GenericError,
@@ -73,18 +111,27 @@ impl From<io::Error> for Error {
     }
 }
 
-impl From<SetLoggerError> for Error {
-    fn from(kind: SetLoggerError) -> Error {
-        Error {
-            inner: Context::new(ErrorKind::LogInitError(kind)),
-        }
+/// Extension of `io::Result` to report the path an operation failed on.
+pub trait PathContext<T> {
+    /// Turns an I/O error into `ErrorKind::IoOnPath` for `path`.
+    fn on_path(self, path: &Path) -> Result<T>;
+}
+
+impl<T> PathContext<T> for io::Result<T> {
+    fn on_path(self, path: &Path) -> Result<T> {
+        self.map_err(|cause| {
+            ErrorKind::IoOnPath {
+                path: path.display().to_string(),
+                cause,
+            }.into()
+        })
     }
 }
 
-impl From<de::Error> for Error {
-    fn from(kind: de::Error) -> Error {
+impl From<SetLoggerError> for Error {
+    fn from(kind: SetLoggerError) -> Error {
         Error {
-            inner: Context::new(ErrorKind::TomlDeError(kind)),
+            inner: Context::new(ErrorKind::LogInitError(kind)),
         }
     }
 }
@@ -94,8 +141,12 @@ pub fn failure_to_string(err: &failure::Error) -> String {
     let mut pretty = err.to_string();
     let mut prev = err.as_fail();
     while let Some(next) = prev.cause() {
-        pretty.push_str(": ");
-        pretty.push_str(&next.to_string());
+        // Some errors already include their cause in their message.
+        let cause = next.to_string();
+        if !pretty.ends_with(&cause) {
+            pretty.push_str(": ");
+            pretty.push_str(&cause);
+        }
         prev = next;
     }
     pretty
@@ -0,0 +1,46 @@
+//! Recursive search of the entries matching criteria, as run by `:find`.
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+use crate::error::*;
+use crate::fs::ops::Monitor;
+use crate::utils::filter::{self, Criterion};
+
+/// The search stops after finding this many entries.
+pub const MAX_RESULTS: usize = 10_000;
+
+/// Returns the entries under `root` matching every criterion, in walk order, along with
+/// whether the search stopped at `MAX_RESULTS`. Hidden entries, and the entries of hidden
+/// directories, are only searched if `show_hidden` is set.
+///
+/// `monitor` is advanced by one file per entry visited.
+pub fn find(
+    root: &Path,
+    criteria: &[Criterion],
+    show_hidden: bool,
+    monitor: &dyn Monitor,
+) -> Result<(Vec<PathBuf>, bool)> {
+    let mut found = Vec::new();
+    let entries = WalkDir::new(root)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|e| filter::is_visible(e, show_hidden));
+    for entry in entries {
+        monitor.advance(1, 0)?;
+        let path = match entry {
+            Ok(entry) => entry.path().to_path_buf(),
+            Err(e) => {
+                debug!("Skipping unreadable entry: {}", e);
+                continue;
+            }
+        };
+        if criteria.iter().all(|criterion| criterion.matches(&path)) {
+            found.push(path);
+            if found.len() >= MAX_RESULTS {
+                return Ok((found, true));
+            }
+        }
+    }
+    Ok((found, false))
+}
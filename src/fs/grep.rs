@@ -0,0 +1,146 @@
+//! Search of the lines matching a pattern in the files under a directory, as run by `:grep`.
+//!
+//! Files are read by `WORKERS` threads, while the calling thread gathers their hits.
+use std::fs as stdfs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use walkdir::WalkDir;
+
+use crate::error::*;
+use crate::fs::ops::Monitor;
+use crate::utils::filter;
+use crate::utils::search::Matcher;
+
+/// Number of threads reading files.
+const WORKERS: usize = 4;
+/// The search stops after this many hits.
+pub const MAX_HITS: usize = 10_000;
+/// Lines of hits are cut to this many characters.
+const MAX_TEXT: usize = 200;
+/// Number of leading bytes looked at to tell binary files apart.
+const SNIFF_SIZE: usize = 8000;
+
+/// A line matching the pattern.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hit {
+    pub path: PathBuf,
+    /// Number of the line, starting at 1.
+    pub line: usize,
+    pub text: String,
+}
+
+/// Returns `true` if `bytes` look like the start of a binary file, i.e. hold a NUL byte.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes.iter().take(SNIFF_SIZE).any(|&b| b == 0)
+}
+
+/// Returns the files under `root` to search, along with their size. Files larger than
+/// `max_size` bytes are skipped, as are hidden entries unless `show_hidden` is set.
+///
+/// `monitor` is advanced by no file per entry walked, so that the walk can be paused or
+/// cancelled.
+pub fn walk(
+    root: &Path,
+    show_hidden: bool,
+    max_size: u64,
+    monitor: &dyn Monitor,
+) -> Result<Vec<(PathBuf, u64)>> {
+    let mut files = Vec::new();
+    let entries = WalkDir::new(root)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|e| filter::is_visible(e, show_hidden) && e.file_name() != ".git")
+        .filter_map(|entry| entry.ok());
+    for entry in entries {
+        monitor.advance(0, 0)?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        match entry.metadata() {
+            Ok(meta) if meta.len() <= max_size => {
+                files.push((entry.path().to_path_buf(), meta.len()))
+            }
+            _ => {}
+        }
+    }
+    Ok(files)
+}
+
+/// Returns the lines matching `matcher` in `files`, as listed by `walk`, sorted by path and
+/// line, along with whether the search stopped at `MAX_HITS`. Binary files are skipped.
+///
+/// `monitor` is advanced by one file, and its size, per file searched.
+pub fn grep(
+    files: Vec<(PathBuf, u64)>,
+    matcher: &Matcher,
+    monitor: &dyn Monitor,
+) -> Result<(Vec<Hit>, bool)> {
+    let queue = Arc::new(Mutex::new(files));
+    let stop = Arc::new(AtomicBool::new(false));
+    let matcher = Arc::new(matcher.clone());
+    let (sender, receiver) = mpsc::channel();
+    for _ in 0..WORKERS {
+        let (queue, stop, matcher, sender) =
+            (queue.clone(), stop.clone(), matcher.clone(), sender.clone());
+        thread::spawn(move || {
+            while !stop.load(Ordering::SeqCst) {
+                let next = queue.lock().unwrap().pop();
+                let (path, size) = match next {
+                    Some(file) => file,
+                    None => break,
+                };
+                let hits = search_file(&path, &matcher);
+                if sender.send((size, hits)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    drop(sender);
+
+    let mut found = Vec::new();
+    let mut truncated = false;
+    for (size, hits) in receiver.iter() {
+        if let Err(e) = monitor.advance(1, size) {
+            stop.store(true, Ordering::SeqCst);
+            return Err(e);
+        }
+        found.extend(hits);
+        if found.len() >= MAX_HITS {
+            stop.store(true, Ordering::SeqCst);
+            truncated = true;
+            break;
+        }
+    }
+    found.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+    found.truncate(MAX_HITS);
+    Ok((found, truncated))
+}
+
+/// Returns the matching lines of the file at `path`, or none if it cannot be read
+/// or is binary.
+fn search_file(path: &Path, matcher: &Matcher) -> Vec<Hit> {
+    let mut bytes = Vec::new();
+    let read = stdfs::File::open(path).and_then(|mut file| file.read_to_end(&mut bytes));
+    if let Err(e) = read {
+        debug!("Skipping {:?}: {}", path, e);
+        return Vec::new();
+    }
+    if is_binary(&bytes) {
+        return Vec::new();
+    }
+    String::from_utf8_lossy(&bytes)
+        .lines()
+        .enumerate()
+        .filter(|(_, text)| matcher.is_match(text))
+        .map(|(index, text)| Hit {
+            path: path.to_path_buf(),
+            line: index + 1,
+            text: text.trim_end().chars().take(MAX_TEXT).collect(),
+        }).collect()
+}
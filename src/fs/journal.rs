@@ -0,0 +1,599 @@
+//! Journal of the file operations, so that they can be undone and redone.
+//!
+//! Every operation is recorded with enough information to revert it: copies and archives are
+//! removed, moves and renames are moved back, trashed entries are restored and created entries
+//! are removed if they are still empty. Entries overwritten by a paste are restored from the
+//! trash. The journal is saved to `$XDG_DATA_HOME/marcos/journal.toml` after every change,
+//! so that an undo survives a restart.
+//!
+//! An operation may only be partly reverted or replayed, e.g. when a file is missing. The part
+//! done moves to the other list while the rest stays, so that it can be retried.
+use std::fs as stdfs;
+use std::path::{Path, PathBuf};
+
+use dirs;
+use serde::de::{Deserialize, DeserializeOwned, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use toml;
+
+use crate::error::*;
+use crate::fs::ops;
+use crate::fs::ops::{Mode, Monitor, Pasted};
+use crate::fs::trash::{self, Trash, TrashEntry};
+
+/// Maximum number of operations kept in each direction.
+const MAX_OPERATIONS: usize = 100;
+
+/// An entry moved to the trash, as recorded in the journal.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Trashed {
+    /// Name of the entry in the trash.
+    pub name: String,
+    /// Path the entry had before being trashed.
+    #[serde(with = "raw")]
+    pub original: PathBuf,
+}
+
+/// A file operation recorded in the journal.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Operation {
+    /// Entries copied, as `(source, copy)` pairs, and the entries they replaced.
+    Copy {
+        #[serde(with = "raw")]
+        pairs: Vec<(PathBuf, PathBuf)>,
+        #[serde(default)]
+        replaced: Vec<Trashed>,
+    },
+    /// Entries moved or renamed, as `(source, destination)` pairs, and the entries they
+    /// replaced.
+    Move {
+        #[serde(with = "raw")]
+        pairs: Vec<(PathBuf, PathBuf)>,
+        #[serde(default)]
+        replaced: Vec<Trashed>,
+    },
+    /// Entries moved to the trash.
+    Trash { entries: Vec<Trashed> },
+    /// Archive created from entries.
+    Archive {
+        #[serde(with = "raw")]
+        paths: Vec<PathBuf>,
+        #[serde(with = "raw")]
+        archive: PathBuf,
+    },
+    /// Empty file or directory created.
+    Create {
+        #[serde(with = "raw")]
+        path: PathBuf,
+        dir: bool,
+    },
+}
+
+/// What a revert or a replay did, as it may stop half way.
+#[derive(Debug, Default)]
+pub struct Outcome {
+    /// The part of the operation reverted (or replayed), to replay (or revert) in order to
+    /// redo (or undo) it.
+    pub done: Option<Operation>,
+    /// The part of the operation left untouched because of an error, to try again.
+    pub left: Option<Operation>,
+}
+
+impl Operation {
+    /// Returns the record of entries which were moved to the trash.
+    pub fn trashed(entries: &[TrashEntry]) -> Self {
+        Operation::Trash {
+            entries: record_trashed(entries),
+        }
+    }
+
+    /// Returns the record of entries pasted from the clipboard.
+    pub fn pasted(mode: Mode, pasted: Pasted) -> Self {
+        let (pairs, replaced) = (pasted.pairs, record_trashed(&pasted.replaced));
+        match mode {
+            Mode::Copy => Operation::Copy { pairs, replaced },
+            Mode::Move => Operation::Move { pairs, replaced },
+        }
+    }
+
+    /// Returns a short description of the operation, as shown in the status bar.
+    pub fn describe(&self) -> String {
+        let (verb, count, first) = match self {
+            Operation::Copy { pairs, .. } => ("copy", pairs.len(), pairs.first().map(|p| &p.1)),
+            Operation::Move { pairs, .. } => ("move", pairs.len(), pairs.first().map(|p| &p.1)),
+            Operation::Trash { entries } => {
+                ("trash", entries.len(), entries.first().map(|e| &e.original))
+            }
+            Operation::Archive { archive, .. } => ("archive", 1, Some(archive)),
+            Operation::Create { path, .. } => ("create", 1, Some(path)),
+        };
+        match (count, first) {
+            (1, Some(path)) => format!("{} of {}", verb, path.display()),
+            _ => format!("{} of {} entries", verb, count),
+        }
+    }
+
+    /// Returns `true` if the operation has nothing left to revert or replay.
+    fn is_empty(&self) -> bool {
+        match self {
+            Operation::Copy { pairs, replaced } | Operation::Move { pairs, replaced } => {
+                pairs.is_empty() && replaced.is_empty()
+            }
+            Operation::Trash { entries } => entries.is_empty(),
+            Operation::Archive { .. } | Operation::Create { .. } => false,
+        }
+    }
+
+    /// Reverts the operation. The outcome holds the part reverted, and the part left if an
+    /// error stopped the revert.
+    pub fn revert(self, monitor: &dyn Monitor) -> (Outcome, Result<()>) {
+        match self {
+            Operation::Copy { pairs, replaced } => {
+                let (removed, left, result) = each(pairs, |(_, copy)| {
+                    if copy.symlink_metadata().is_ok() {
+                        ops::remove_path(copy, monitor)?;
+                    }
+                    Ok(())
+                });
+                if result.is_err() {
+                    let done = Operation::Copy { pairs: removed, replaced: Vec::new() };
+                    return outcome(done, Operation::Copy { pairs: left, replaced }, result);
+                }
+                let (restored, left, result) = restore(replaced, monitor);
+                let done = Operation::Copy { pairs: removed, replaced: restored };
+                outcome(done, Operation::Copy { pairs: Vec::new(), replaced: left }, result)
+            }
+            Operation::Move { pairs, replaced } => {
+                // The last entries are moved back first.
+                let pairs = pairs.into_iter().rev().collect();
+                let (mut moved, mut left, result) = each(pairs, |(src, dest)| {
+                    ensure_free(src)?;
+                    ops::move_path(dest, src, monitor)
+                });
+                moved.reverse();
+                left.reverse();
+                if result.is_err() {
+                    let done = Operation::Move { pairs: moved, replaced: Vec::new() };
+                    return outcome(done, Operation::Move { pairs: left, replaced }, result);
+                }
+                let (restored, left, result) = restore(replaced, monitor);
+                let done = Operation::Move { pairs: moved, replaced: restored };
+                outcome(done, Operation::Move { pairs: Vec::new(), replaced: left }, result)
+            }
+            Operation::Trash { entries } => {
+                let (restored, left, result) = restore(entries, monitor);
+                let done = Operation::Trash { entries: restored };
+                outcome(done, Operation::Trash { entries: left }, result)
+            }
+            Operation::Archive { paths, archive } => {
+                let result = if archive.symlink_metadata().is_ok() {
+                    ops::remove_path(&archive, monitor)
+                } else {
+                    Ok(())
+                };
+                whole(Operation::Archive { paths, archive }, result)
+            }
+            Operation::Create { path, dir } => {
+                let result = if dir {
+                    stdfs::remove_dir(&path).on_path(&path)
+                } else {
+                    match path.symlink_metadata().on_path(&path) {
+                        Ok(ref metadata) if metadata.len() == 0 => {
+                            stdfs::remove_file(&path).on_path(&path)
+                        }
+                        Ok(_) => Err(ErrorKind::InvalidOperation {
+                            reason: format!("{:?} was modified since its creation", path),
+                        }.into()),
+                        Err(e) => Err(e),
+                    }
+                };
+                whole(Operation::Create { path, dir }, result)
+            }
+        }
+    }
+
+    /// Replays a reverted operation. The outcome holds the part replayed, and the part left if
+    /// an error stopped the replay.
+    pub fn replay(self, monitor: &dyn Monitor) -> (Outcome, Result<()>) {
+        match self {
+            Operation::Copy { pairs, replaced } => {
+                let (trashed, left, result) = trash(replaced, monitor);
+                if result.is_err() {
+                    let done = Operation::Copy { pairs: Vec::new(), replaced: trashed };
+                    return outcome(done, Operation::Copy { pairs, replaced: left }, result);
+                }
+                let (copied, left, result) = each(pairs, |(src, copy)| {
+                    ensure_free(copy)?;
+                    if let Err(e) = ops::copy_path(src, copy, monitor) {
+                        // Do not leave a partial copy behind, which would prevent a retry.
+                        let _ = ops::remove_path(copy, &());
+                        return Err(e);
+                    }
+                    Ok(())
+                });
+                let done = Operation::Copy { pairs: copied, replaced: trashed };
+                outcome(done, Operation::Copy { pairs: left, replaced: Vec::new() }, result)
+            }
+            Operation::Move { pairs, replaced } => {
+                let (trashed, left, result) = trash(replaced, monitor);
+                if result.is_err() {
+                    let done = Operation::Move { pairs: Vec::new(), replaced: trashed };
+                    return outcome(done, Operation::Move { pairs, replaced: left }, result);
+                }
+                let (moved, left, result) = each(pairs, |(src, dest)| {
+                    ensure_free(dest)?;
+                    ops::move_path(src, dest, monitor)
+                });
+                let done = Operation::Move { pairs: moved, replaced: trashed };
+                outcome(done, Operation::Move { pairs: left, replaced: Vec::new() }, result)
+            }
+            Operation::Trash { entries } => {
+                let (trashed, left, result) = trash(entries, monitor);
+                let done = Operation::Trash { entries: trashed };
+                outcome(done, Operation::Trash { entries: left }, result)
+            }
+            Operation::Archive { paths, archive } => {
+                let result =
+                    ensure_free(&archive).and_then(|_| ops::archive(&paths, &archive, monitor));
+                whole(Operation::Archive { paths, archive }, result)
+            }
+            Operation::Create { path, dir } => {
+                let result = ensure_free(&path).and_then(|_| {
+                    if dir {
+                        stdfs::create_dir(&path).on_path(&path)
+                    } else {
+                        stdfs::File::create(&path).map(|_| ()).on_path(&path)
+                    }
+                });
+                whole(Operation::Create { path, dir }, result)
+            }
+        }
+    }
+}
+
+/// Returns the outcome of an operation split into the part `done` and the part `left`.
+fn outcome(done: Operation, left: Operation, result: Result<()>) -> (Outcome, Result<()>) {
+    let outcome = Outcome {
+        done: Some(done).filter(|operation| !operation.is_empty()),
+        left: Some(left).filter(|operation| !operation.is_empty()),
+    };
+    (outcome, result)
+}
+
+/// Returns the outcome of an operation which is either entirely done, or not at all.
+fn whole(operation: Operation, result: Result<()>) -> (Outcome, Result<()>) {
+    let outcome = match result {
+        Ok(()) => Outcome {
+            done: Some(operation),
+            left: None,
+        },
+        Err(_) => Outcome {
+            done: None,
+            left: Some(operation),
+        },
+    };
+    (outcome, result)
+}
+
+/// Runs `f` on the items in order, until it fails. Returns the items done, the items left
+/// starting with the one which failed, and the error.
+fn each<T, F>(items: Vec<T>, mut f: F) -> (Vec<T>, Vec<T>, Result<()>)
+where
+    F: FnMut(&T) -> Result<()>,
+{
+    for (index, item) in items.iter().enumerate() {
+        if let Err(e) = f(item) {
+            let mut done = items;
+            let left = done.split_off(index);
+            return (done, left, Err(e));
+        }
+    }
+    (items, Vec::new(), Ok(()))
+}
+
+/// Moves the recorded entries back from the trash. Returns the paths they were restored to,
+/// which may be new names, the entries left in the trash because of an error, and the error.
+fn restore(
+    entries: Vec<Trashed>,
+    monitor: &dyn Monitor,
+) -> (Vec<Trashed>, Vec<Trashed>, Result<()>) {
+    if entries.is_empty() {
+        return (Vec::new(), entries, Ok(()));
+    }
+    let trash = match Trash::home() {
+        Ok(trash) => trash,
+        Err(e) => return (Vec::new(), entries, Err(e)),
+    };
+    let mut restored = Vec::with_capacity(entries.len());
+    let (_, left, result) = each(entries, |entry| {
+        let entry = TrashEntry {
+            name: entry.name.clone(),
+            original: entry.original.clone(),
+            deleted: String::new(),
+        };
+        let path = trash.restore(&entry, monitor)?;
+        restored.push(Trashed {
+            name: String::new(),
+            original: path,
+        });
+        Ok(())
+    });
+    (restored, left, result)
+}
+
+/// Moves the recorded entries to the trash again. Returns their new record, the entries
+/// left in place because of an error, and the error.
+fn trash(
+    entries: Vec<Trashed>,
+    monitor: &dyn Monitor,
+) -> (Vec<Trashed>, Vec<Trashed>, Result<()>) {
+    if entries.is_empty() {
+        return (Vec::new(), entries, Ok(()));
+    }
+    let trash = match Trash::home() {
+        Ok(trash) => trash,
+        Err(e) => return (Vec::new(), entries, Err(e)),
+    };
+    let mut trashed = Vec::with_capacity(entries.len());
+    let (_, left, result) = each(entries, |entry| {
+        trashed.push(trash.put(&entry.original, monitor)?);
+        Ok(())
+    });
+    (record_trashed(&trashed), left, result)
+}
+
+fn record_trashed(entries: &[TrashEntry]) -> Vec<Trashed> {
+    entries
+        .iter()
+        .map(|entry| Trashed {
+            name: entry.name.clone(),
+            original: entry.original.clone(),
+        }).collect()
+}
+
+/// Refuses overwriting an entry when reverting or replaying an operation.
+fn ensure_free(path: &Path) -> Result<()> {
+    if path.symlink_metadata().is_ok() {
+        return Err(ErrorKind::InvalidOperation {
+            reason: format!("{:?} already exists", path),
+        }.into());
+    }
+    Ok(())
+}
+
+/// Operations which can be undone and redone, most recent last.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Journal {
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    #[serde(default)]
+    undo: Vec<Operation>,
+    #[serde(default)]
+    redo: Vec<Operation>,
+}
+
+impl Journal {
+    /// Loads the journal from the data directory, or starts an empty one if there is none.
+    pub fn load() -> Result<Self> {
+        let data_path: PathBuf = dirs::data_dir().ok_or(ErrorKind::DirNotFound {
+            dirname: String::from("DATA_DIR"),
+        })?;
+        let path = data_path.join("marcos").join("journal.toml");
+        let mut journal = if path.exists() {
+            let content = stdfs::read_to_string(&path)?;
+            toml::from_str(&content).unwrap_or_else(|e| {
+                warn!("Discarding unreadable journal {:?}: {}", path, e);
+                Journal::default()
+            })
+        } else {
+            Journal::default()
+        };
+        journal.path = Some(path);
+        Ok(journal)
+    }
+
+    /// Saves the journal to the file it was loaded from.
+    pub fn save(&self) -> Result<()> {
+        if let Some(ref path) = self.path {
+            if let Some(parent) = path.parent() {
+                stdfs::create_dir_all(parent)?;
+            }
+            // Going through `toml::Value` emits plain values before tables, as TOML requires.
+            let content = toml::Value::try_from(self)
+                .and_then(|value| toml::to_string(&value))
+                .map_err(|e| ErrorKind::InvalidOperation {
+                    reason: format!("Cannot save journal: {}", e),
+                })?;
+            stdfs::write(path, content)?;
+        }
+        Ok(())
+    }
+
+    /// Records a new operation. Operations previously undone cannot be redone anymore.
+    pub fn record(&mut self, operation: Operation) -> Result<()> {
+        self.redo.clear();
+        self.push_undo(operation)
+    }
+
+    /// Takes the most recent operation to undo.
+    pub fn pop_undo(&mut self) -> Option<Operation> {
+        self.undo.pop()
+    }
+
+    /// Takes the most recently undone operation, to redo it.
+    pub fn pop_redo(&mut self) -> Option<Operation> {
+        self.redo.pop()
+    }
+
+    /// Pushes an operation which can be undone, without touching the redo list.
+    pub fn push_undo(&mut self, operation: Operation) -> Result<()> {
+        push_bounded(&mut self.undo, operation);
+        self.save()
+    }
+
+    /// Pushes an operation which can be redone.
+    pub fn push_redo(&mut self, operation: Operation) -> Result<()> {
+        push_bounded(&mut self.redo, operation);
+        self.save()
+    }
+}
+
+fn push_bounded(operations: &mut Vec<Operation>, operation: Operation) {
+    operations.push(operation);
+    if operations.len() > MAX_OPERATIONS {
+        let excess = operations.len() - MAX_OPERATIONS;
+        operations.drain(..excess);
+    }
+}
+
+/// Paths are saved as strings, which TOML requires to be valid UTF-8. Other paths are saved
+/// percent-encoded after a leading `%`, which no absolute path starts with. Paths starting
+/// with `%` are encoded as well, so that they are read back as they were.
+mod raw {
+    use super::*;
+
+    /// A value holding paths, saved through its form made of strings.
+    pub trait Raw: Sized {
+        type Form: Serialize + DeserializeOwned;
+
+        fn to_raw(&self) -> Self::Form;
+
+        fn from_raw(form: Self::Form) -> Self;
+    }
+
+    impl Raw for PathBuf {
+        type Form = String;
+
+        fn to_raw(&self) -> String {
+            match self.to_str() {
+                Some(path) if !path.starts_with('%') => path.to_string(),
+                _ => format!("%{}", trash::encode(self)),
+            }
+        }
+
+        fn from_raw(form: String) -> Self {
+            match form.strip_prefix('%') {
+                Some(encoded) => trash::decode(encoded),
+                None => PathBuf::from(form),
+            }
+        }
+    }
+
+    impl<T: Raw> Raw for Vec<T> {
+        type Form = Vec<T::Form>;
+
+        fn to_raw(&self) -> Self::Form {
+            self.iter().map(Raw::to_raw).collect()
+        }
+
+        fn from_raw(form: Self::Form) -> Self {
+            form.into_iter().map(T::from_raw).collect()
+        }
+    }
+
+    impl<A: Raw, B: Raw> Raw for (A, B) {
+        type Form = (A::Form, B::Form);
+
+        fn to_raw(&self) -> Self::Form {
+            (self.0.to_raw(), self.1.to_raw())
+        }
+
+        fn from_raw(form: Self::Form) -> Self {
+            (A::from_raw(form.0), B::from_raw(form.1))
+        }
+    }
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        T: Raw,
+        S: Serializer,
+    {
+        value.to_raw().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> ::std::result::Result<T, D::Error>
+    where
+        T: Raw,
+        D: Deserializer<'de>,
+    {
+        T::Form::deserialize(deserializer).map(T::from_raw)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsString;
+    use std::os::unix::ffi::OsStringExt;
+
+    #[test]
+    fn saves_paths_which_are_not_utf8() {
+        let odd = PathBuf::from(OsString::from_vec(b"/tmp/latin-\xe9".to_vec()));
+        let journal = Journal {
+            path: None,
+            undo: vec![
+                Operation::Move {
+                    pairs: vec![(PathBuf::from("/tmp/a"), odd.clone())],
+                    replaced: Vec::new(),
+                },
+                Operation::Create {
+                    path: PathBuf::from("%not-a-prefix"),
+                    dir: false,
+                },
+            ],
+            redo: Vec::new(),
+        };
+        let content = toml::to_string(&toml::Value::try_from(&journal).unwrap()).unwrap();
+        assert!(content.contains("\"/tmp/a\""), "{}", content);
+        let loaded: Journal = toml::from_str(&content).unwrap();
+        match &loaded.undo[..] {
+            [Operation::Move { pairs, .. }, Operation::Create { path, .. }] => {
+                assert_eq!(pairs, &[(PathBuf::from("/tmp/a"), odd)]);
+                assert_eq!(path, Path::new("%not-a-prefix"));
+            }
+            operations => panic!("unexpected operations {:?}", operations),
+        }
+    }
+
+    #[test]
+    fn keeps_what_is_left_of_a_failed_revert() {
+        let dir = ::std::env::temp_dir().join(format!("marcos-journal-{}", ::std::process::id()));
+        let _ = stdfs::remove_dir_all(&dir);
+        stdfs::create_dir_all(&dir).unwrap();
+        let (a, b, c, d) = (dir.join("a"), dir.join("b"), dir.join("c"), dir.join("d"));
+        stdfs::write(&b, "b").unwrap();
+        stdfs::write(&d, "d").unwrap();
+        // `a` was moved to `b` then `c` to `d`, but something took the place of `a` since.
+        stdfs::write(&a, "new a").unwrap();
+        let operation = Operation::Move {
+            pairs: vec![(a.clone(), b.clone()), (c.clone(), d.clone())],
+            replaced: Vec::new(),
+        };
+        let (outcome, result) = operation.revert(&());
+        assert!(result.is_err());
+        assert_eq!(stdfs::read_to_string(&c).unwrap(), "d");
+        match (outcome.done, outcome.left) {
+            (
+                Some(Operation::Move { pairs: done, .. }),
+                Some(Operation::Move { pairs: left, .. }),
+            ) => {
+                assert_eq!(done, vec![(c, d)]);
+                assert_eq!(left, vec![(a.clone(), b.clone())]);
+            }
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+
+        // Once the way is clear, what was left is reverted.
+        stdfs::remove_file(&a).unwrap();
+        let left = Operation::Move {
+            pairs: vec![(a.clone(), b)],
+            replaced: Vec::new(),
+        };
+        let (outcome, result) = left.revert(&());
+        assert!(result.is_ok());
+        assert!(outcome.left.is_none());
+        assert_eq!(stdfs::read_to_string(&a).unwrap(), "b");
+        stdfs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,3 +1,9 @@
 //! Contains structs and functions related to file IO.
 pub use self::metadata::Entry;
+pub mod find;
+pub mod grep;
+pub mod journal;
 pub mod metadata;
+pub mod ops;
+pub mod rename;
+pub mod trash;
@@ -0,0 +1,321 @@
+//! File operations such as copying and moving files and directory trees.
+//!
+//! Copies preserve permissions and timestamps, and symbolic links are copied as links.
+//! Moves fall back to copy and delete when the destination is on another device. Entries
+//! overwritten by a paste are moved to the trash.
+//!
+//! Every operation reports its progress to a `Monitor`, which may also interrupt it. Pass `&()`
+//! when progress is not needed.
+use std::fs as stdfs;
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use filetime::{set_symlink_file_times, FileTime};
+use walkdir::WalkDir;
+
+use crate::error::*;
+use crate::fs::trash::{Trash, TrashEntry};
+
+/// `errno` returned by `rename(2)` when source and destination are on different devices.
+const EXDEV: i32 = 18;
+
+/// Size of the buffer used to copy files.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Observer of a long running operation, which may also interrupt it.
+pub trait Monitor {
+    /// Called with the number of files and bytes processed since the last call.
+    /// Returning an error aborts the operation.
+    fn advance(&self, files: u64, bytes: u64) -> Result<()>;
+}
+
+/// Monitor which ignores progress and never interrupts.
+impl Monitor for () {
+    fn advance(&self, _files: u64, _bytes: u64) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// What to do when the destination of an operation already exists.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Conflict {
+    /// Leave the existing entry alone and do nothing.
+    Skip,
+    /// Replace the existing entry.
+    Overwrite,
+    /// Pick a free name such as `name (1).ext` for the new entry.
+    Rename,
+}
+
+/// Kind of operation pending in the `Clipboard`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    Copy,
+    Move,
+}
+
+/// Entries yanked or cut, waiting to be pasted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Clipboard {
+    pub mode: Mode,
+    pub paths: Vec<PathBuf>,
+}
+
+impl Clipboard {
+    /// Returns the paths which already exist in `dest_dir`.
+    pub fn conflicts(&self, dest_dir: &Path) -> Vec<PathBuf> {
+        self.paths
+            .iter()
+            .filter_map(|path| path.file_name())
+            .map(|name| dest_dir.join(name))
+            .filter(|dest| dest.symlink_metadata().is_ok())
+            .collect()
+    }
+
+    /// Copies or moves every entry into `dest_dir`. Entries skipped because of a conflict
+    /// are not part of the result, and entries overwritten are moved to the trash.
+    ///
+    /// The entries pasted before a failure, or before the paste is cancelled, are returned
+    /// along with the error, so that they can still be undone.
+    pub fn paste(
+        &self,
+        dest_dir: &Path,
+        conflict: Conflict,
+        monitor: &dyn Monitor,
+    ) -> (Pasted, Result<()>) {
+        let mut pasted = Pasted::default();
+        let mut trash = None;
+        let result = self.paths.iter().try_for_each(|path| {
+            let dest = match destination(path, dest_dir, conflict)? {
+                Some(dest) => dest,
+                None => return Ok(()),
+            };
+            if dest.symlink_metadata().is_ok() {
+                if trash.is_none() {
+                    trash = Some(Trash::home()?);
+                }
+                if let Some(trash) = &trash {
+                    pasted.replaced.push(trash.put(&dest, &())?);
+                }
+            }
+            match self.mode {
+                Mode::Copy => copy_path(path, &dest, monitor)?,
+                Mode::Move => move_path(path, &dest, monitor)?,
+            }
+            pasted.pairs.push((path.clone(), dest));
+            Ok(())
+        });
+        (pasted, result)
+    }
+}
+
+/// Entries pasted from the `Clipboard`.
+#[derive(Clone, Debug, Default)]
+pub struct Pasted {
+    /// Source and destination of every pasted entry.
+    pub pairs: Vec<(PathBuf, PathBuf)>,
+    /// Existing entries moved to the trash to make room for the pasted ones.
+    pub replaced: Vec<TrashEntry>,
+}
+
+/// Returns the number of files and the number of bytes under `paths`, directories excluded.
+pub fn measure(paths: &[PathBuf]) -> (u64, u64) {
+    let mut files = 0;
+    let mut bytes = 0;
+    for path in paths {
+        for entry in WalkDir::new(path).into_iter().filter_map(|e| e.ok()) {
+            if entry.file_type().is_dir() {
+                continue;
+            }
+            files += 1;
+            bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+        }
+    }
+    (files, bytes)
+}
+
+/// Moves `src` to `dest`, copying and deleting if they are on different devices.
+///
+/// If the copy fails or is cancelled, what was copied so far is removed and `src` is left
+/// untouched.
+pub fn move_path(src: &Path, dest: &Path, monitor: &dyn Monitor) -> Result<()> {
+    monitor.advance(0, 0)?;
+    let (files, bytes) = measure(&[src.to_path_buf()]);
+    match stdfs::rename(src, dest) {
+        Ok(()) => monitor.advance(files, bytes),
+        Err(ref e) if e.raw_os_error() == Some(EXDEV) => {
+            debug!("Cross-device move of {:?}, falling back to copy", src);
+            let existed = dest.symlink_metadata().is_ok();
+            if let Err(e) = copy_path(src, dest, monitor) {
+                if !existed {
+                    if let Err(e) = remove_path(dest, &()) {
+                        warn!("Cannot remove the partial copy {:?}: {}", dest, e);
+                    }
+                }
+                return Err(e);
+            }
+            remove_path(src, &())
+        }
+        Err(e) => Err(e).on_path(src),
+    }
+}
+
+/// Recursively copies `src` to `dest`, preserving permissions and timestamps.
+pub fn copy_path(src: &Path, dest: &Path, monitor: &dyn Monitor) -> Result<()> {
+    if dest.starts_with(src) {
+        return Err(ErrorKind::InvalidOperation {
+            reason: format!("Cannot copy {:?} into itself", src),
+        }.into());
+    }
+    monitor.advance(0, 0)?;
+    let meta = src.symlink_metadata().on_path(src)?;
+    let file_type = meta.file_type();
+    if file_type.is_symlink() {
+        symlink(stdfs::read_link(src).on_path(src)?, dest).on_path(dest)?;
+        monitor.advance(1, 0)?;
+    } else if file_type.is_dir() {
+        stdfs::create_dir(dest).on_path(dest)?;
+        for entry in stdfs::read_dir(src).on_path(src)? {
+            let entry = entry.on_path(src)?;
+            copy_path(&entry.path(), &dest.join(entry.file_name()), monitor)?;
+        }
+        stdfs::set_permissions(dest, meta.permissions()).on_path(dest)?;
+    } else {
+        if let Err(e) = copy_file(src, dest, monitor) {
+            // Do not leave a truncated copy behind.
+            let _ = stdfs::remove_file(dest);
+            return Err(e);
+        }
+        stdfs::set_permissions(dest, meta.permissions()).on_path(dest)?;
+        monitor.advance(1, 0)?;
+    }
+    let atime = FileTime::from_last_access_time(&meta);
+    let mtime = FileTime::from_last_modification_time(&meta);
+    set_symlink_file_times(dest, atime, mtime).on_path(dest)?;
+    Ok(())
+}
+
+/// Copies the content of a regular file, reporting every chunk to `monitor`.
+fn copy_file(src: &Path, dest: &Path, monitor: &dyn Monitor) -> Result<()> {
+    let mut reader = stdfs::File::open(src).on_path(src)?;
+    let mut writer = stdfs::File::create(dest).on_path(dest)?;
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e).on_path(src),
+        };
+        writer.write_all(&buffer[..read]).on_path(dest)?;
+        monitor.advance(0, read as u64)?;
+    }
+    Ok(())
+}
+
+/// Removes `path`, recursively if it is a directory. Symbolic links are never followed.
+pub fn remove_path(path: &Path, monitor: &dyn Monitor) -> Result<()> {
+    monitor.advance(0, 0)?;
+    let meta = path.symlink_metadata().on_path(path)?;
+    if meta.is_dir() {
+        for entry in stdfs::read_dir(path).on_path(path)? {
+            remove_path(&entry.on_path(path)?.path(), monitor)?;
+        }
+        stdfs::remove_dir(path).on_path(path)?;
+    } else {
+        stdfs::remove_file(path).on_path(path)?;
+        monitor.advance(1, meta.len())?;
+    }
+    Ok(())
+}
+
+/// Creates the archive `dest` containing `paths` with `tar`, which picks the compression
+/// according to the extension of `dest` (e.g. `.tar.gz`, `.tar.xz`).
+///
+/// Entries are stored relative to the parent of the first path.
+pub fn archive(paths: &[PathBuf], dest: &Path, monitor: &dyn Monitor) -> Result<()> {
+    let parent = paths
+        .first()
+        .and_then(|path| path.parent())
+        .ok_or_else(|| ErrorKind::InvalidOperation {
+            reason: String::from("Nothing to archive"),
+        })?;
+    let mut child = Command::new("tar")
+        .arg("--create")
+        .arg("--auto-compress")
+        .arg("--verbose")
+        .arg("--file")
+        .arg(dest)
+        .arg("--directory")
+        .arg(parent)
+        .arg("--")
+        .args(paths.iter().filter_map(|path| path.file_name()))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(stdout) = child.stdout.take() {
+        // `tar --verbose` prints one line for every archived entry.
+        for line in BufReader::new(stdout).lines() {
+            line?;
+            if let Err(e) = monitor.advance(1, 0) {
+                let _ = child.kill();
+                let _ = child.wait();
+                let _ = stdfs::remove_file(dest);
+                return Err(e);
+            }
+        }
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(ErrorKind::InvalidOperation {
+            reason: format!("tar exited with {}", status),
+        }.into());
+    }
+    Ok(())
+}
+
+/// Returns a path which does not exist yet, by appending ` (1)`, ` (2)`, ... to the file
+/// stem of `path`.
+pub fn unique_name(path: &Path) -> PathBuf {
+    let parent = path.parent().unwrap_or_else(|| Path::new(""));
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|i| parent.join(format!("{} ({}){}", stem, i, extension)))
+        .find(|candidate| candidate.symlink_metadata().is_err())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// Computes where `src` ends up in `dest_dir`, according to `conflict`. The destination
+/// returned may exist if it is to be overwritten.
+fn destination(src: &Path, dest_dir: &Path, conflict: Conflict) -> Result<Option<PathBuf>> {
+    let name = src.file_name().ok_or_else(|| ErrorKind::InvalidOperation {
+        reason: format!("{:?} has no file name", src),
+    })?;
+    let dest = dest_dir.join(name);
+    match dest.symlink_metadata() {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Some(dest)),
+        Err(e) => Err(e.into()),
+        Ok(_) => match conflict {
+            Conflict::Skip => Ok(None),
+            Conflict::Rename => Ok(Some(unique_name(&dest))),
+            // Pasting an entry onto itself leaves it untouched.
+            Conflict::Overwrite if dest == src => Ok(None),
+            // Replacing a directory would also remove the entry pasted from it.
+            Conflict::Overwrite if src.starts_with(&dest) => Err(ErrorKind::InvalidOperation {
+                reason: format!("Cannot replace {:?}, which contains {:?}", dest, src),
+            }.into()),
+            Conflict::Overwrite => Ok(Some(dest)),
+        },
+    }
+}
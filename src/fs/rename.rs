@@ -0,0 +1,549 @@
+//! Bulk renaming of entries of a directory, either by editing their names one per line, or by
+//! applying a `Pattern` to each of them.
+//!
+//! The new names are checked as a whole before anything is renamed: new names must be valid,
+//! unique, and must not replace entries which are not renamed themselves. Renames which depend
+//! on each other are ordered, and cycles such as `a -> b, b -> a` go through a temporary name.
+use std::collections::{HashMap, HashSet};
+use std::fs as stdfs;
+use std::path::{Path, PathBuf};
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+use regex::{Captures, Regex};
+
+use crate::error::*;
+
+/// Renames checked against each other, ready to be applied.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Plan {
+    /// The requested renames, as `(from, to)` pairs, in the order of the entries.
+    pub renames: Vec<(PathBuf, PathBuf)>,
+    /// The renames to run in order, including those through temporary names.
+    pub steps: Vec<(PathBuf, PathBuf)>,
+    /// Number of cycles broken with a temporary name.
+    pub cycles: usize,
+}
+
+/// Returns the text to edit for `paths`: their file names, one per line.
+///
+/// Names containing a line break cannot be edited this way, so they are rejected.
+pub fn names(paths: &[PathBuf]) -> Result<String> {
+    let mut text = String::new();
+    for path in paths {
+        let name = file_name(path)?;
+        if name.contains('\n') {
+            return Err(invalid(format!("Cannot rename {:?}: it contains a line break", name)));
+        }
+        text.push_str(&name);
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+/// Builds the plan renaming `paths` to the lines of `edited`, line `n` giving the new name of
+/// the `n`th path. Unchanged lines are left alone.
+pub fn plan(paths: &[PathBuf], edited: &str) -> Result<Plan> {
+    let lines: Vec<String> = edited.lines().map(|line| line.to_string()).collect();
+    if lines.len() != paths.len() {
+        return Err(invalid(format!(
+            "Expected {} lines, found {}: lines must not be added or removed",
+            paths.len(),
+            lines.len()
+        )));
+    }
+    let problems = check(paths, &lines);
+    if let Some((n, problem)) = problems
+        .into_iter()
+        .enumerate()
+        .find_map(|(n, problem)| problem.map(|p| (n, p)))
+    {
+        return Err(invalid(format!("Line {}: {}", n + 1, problem)));
+    }
+    Ok(build(paths, &lines))
+}
+
+/// Returns, for each of `names`, why the path at the same index cannot be renamed to it, if
+/// it cannot.
+pub fn check(paths: &[PathBuf], names: &[String]) -> Vec<Option<String>> {
+    let dests: Vec<PathBuf> = paths
+        .iter()
+        .zip(names)
+        .map(|(path, name)| path.with_file_name(name))
+        .collect();
+    let sources: HashSet<&PathBuf> = paths
+        .iter()
+        .zip(&dests)
+        .filter(|(path, dest)| path != dest)
+        .map(|(path, _)| path)
+        .collect();
+    let mut seen: HashMap<&PathBuf, usize> = HashMap::new();
+    names
+        .iter()
+        .zip(paths.iter().zip(&dests))
+        .enumerate()
+        .map(|(n, (name, (path, dest)))| {
+            if name.trim().is_empty() || name.contains('/') || name == "." || name == ".." {
+                return Some(format!("{:?} is not a valid name", name));
+            }
+            if let Some(first) = seen.insert(dest, n) {
+                let other = paths[first].file_name().unwrap_or_default();
+                return Some(format!("Duplicate name {:?}, also given to {:?}", name, other));
+            }
+            if dest != path && !sources.contains(dest) && dest.symlink_metadata().is_ok() {
+                return Some(format!("{} already exists", dest.display()));
+            }
+            None
+        }).collect()
+}
+
+/// Builds the plan renaming `paths` to `names`, which must have been checked.
+pub fn build(paths: &[PathBuf], names: &[String]) -> Plan {
+    let renames: Vec<(PathBuf, PathBuf)> = paths
+        .iter()
+        .zip(names)
+        .map(|(path, name)| (path.clone(), path.with_file_name(name)))
+        .filter(|(path, dest)| path != dest)
+        .collect();
+    let (steps, cycles) = order(&renames);
+    Plan {
+        renames,
+        steps,
+        cycles,
+    }
+}
+
+impl Plan {
+    /// Runs the steps of the plan. If one fails, those already run are undone, so that either
+    /// every entry is renamed or none is.
+    pub fn apply(&self) -> Result<()> {
+        for (done, (src, dest)) in self.steps.iter().enumerate() {
+            let result = if dest.symlink_metadata().is_ok() {
+                Err(format!("{} already exists", dest.display()))
+            } else {
+                stdfs::rename(src, dest).map_err(|e| e.to_string())
+            };
+            if let Err(e) = result {
+                let mut reason = format!("Cannot rename {}: {}", src.display(), e);
+                for (src, dest) in self.steps[..done].iter().rev() {
+                    if let Err(e) = stdfs::rename(dest, src) {
+                        let restore = format!(" (and cannot restore {}: {})", src.display(), e);
+                        reason.push_str(&restore);
+                        break;
+                    }
+                }
+                return Err(invalid(reason));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Case conversion applied by a `Pattern`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Case {
+    Keep,
+    Lower,
+    Upper,
+    /// Capitalizes the first letter of every word.
+    Title,
+}
+
+/// Part of the replacement of a `Pattern`.
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    /// Text in which `$1` or `${name}` are replaced by the captures.
+    Text(String),
+    /// Position of the entry, starting at 1, padded with zeros to the width.
+    Counter(usize),
+    Stem,
+    Extension,
+    Name,
+    /// Modification time, in a `strftime` format.
+    Date(String),
+}
+
+/// A rename rule for many entries at once.
+///
+/// The first match of `find` in the file name (the whole name if `find` is empty) is replaced
+/// by the replacement, in which:
+///
+/// * `$1`, `${1}` or `${name}` are the groups captured by `find`,
+/// * `{n}` is the position of the entry, starting at 1, and `{n:03}` pads it with zeros,
+/// * `{name}`, `{stem}` and `{ext}` are the file name, the name without its extension and the
+///   extension,
+/// * `{date}` is the modification date, and `{date:%Y%m%d_%H%M}` formats it with `strftime`,
+/// * `{{` and `}}` are literal braces.
+///
+/// An empty replacement leaves the name as is. Then the case is converted and, if given, the
+/// extension is replaced.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    find: Regex,
+    replace: Option<Vec<Token>>,
+    case: Case,
+    extension: Option<String>,
+}
+
+impl Pattern {
+    /// Parses a pattern. `extension` may start with a dot, and is kept as is if empty.
+    pub fn new(find: &str, replace: &str, case: Case, extension: &str) -> Result<Self> {
+        let find = if find.is_empty() { "^.*$" } else { find };
+        let find = Regex::new(find).map_err(|e| invalid(format!("Invalid pattern: {}", e)))?;
+        let replace = if replace.is_empty() {
+            None
+        } else {
+            Some(tokens(replace)?)
+        };
+        let extension = extension.trim_start_matches('.');
+        if extension.contains('/') {
+            return Err(invalid(format!("Invalid extension: {:?}", extension)));
+        }
+        Ok(Pattern {
+            find,
+            replace,
+            case,
+            extension: if extension.is_empty() {
+                None
+            } else {
+                Some(extension.to_string())
+            },
+        })
+    }
+
+    /// Returns the new name of the entry at `path`, the `index`th (from 0) of the renamed ones.
+    pub fn apply(&self, path: &Path, index: usize) -> Result<String> {
+        let name = file_name(path)?;
+        let mut new = match (&self.replace, self.find.captures(&name)) {
+            (Some(tokens), Some(captures)) => {
+                let whole = captures.get(0).map_or(0..0, |m| m.start()..m.end());
+                let replacement = self.expand(tokens, &captures, path, &name, index);
+                format!("{}{}{}", &name[..whole.start], replacement, &name[whole.end..])
+            }
+            _ => name.clone(),
+        };
+        new = match self.case {
+            Case::Keep => new,
+            Case::Lower => new.to_lowercase(),
+            Case::Upper => new.to_uppercase(),
+            Case::Title => title_case(&new),
+        };
+        if let Some(ref extension) = self.extension {
+            let stem = Path::new(&new)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            new = format!("{}.{}", stem, extension);
+        }
+        Ok(new)
+    }
+
+    fn expand(
+        &self,
+        tokens: &[Token],
+        captures: &Captures,
+        path: &Path,
+        name: &str,
+        index: usize,
+    ) -> String {
+        let mut result = String::new();
+        for token in tokens {
+            match token {
+                Token::Text(text) => captures.expand(text, &mut result),
+                Token::Counter(width) => {
+                    result.push_str(&format!("{:0width$}", index + 1, width = width))
+                }
+                Token::Name => result.push_str(name),
+                Token::Stem => result.push_str(&stem(name)),
+                Token::Extension => result.push_str(&extension(name)),
+                Token::Date(format) => {
+                    let modified = path.symlink_metadata().and_then(|meta| meta.modified());
+                    if let Ok(modified) = modified {
+                        let date: DateTime<Local> = modified.into();
+                        result.push_str(&date.format(format).to_string());
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Builds the plan renaming `paths` with `pattern`, along with the new name of each path and
+/// why it cannot be renamed, if it cannot. The plan is `None` if any path cannot be renamed.
+pub fn preview(
+    paths: &[PathBuf],
+    pattern: &Pattern,
+) -> (Vec<(String, Option<String>)>, Option<Plan>) {
+    let names: Vec<Result<String>> = paths
+        .iter()
+        .enumerate()
+        .map(|(index, path)| pattern.apply(path, index))
+        .collect();
+    let valid: Vec<String> = names
+        .iter()
+        .map(|name| name.as_ref().ok().cloned().unwrap_or_default())
+        .collect();
+    let problems = check(paths, &valid);
+    let rows: Vec<(String, Option<String>)> = names
+        .into_iter()
+        .zip(problems)
+        .map(|(name, problem)| match name {
+            Ok(name) => (name, problem),
+            Err(e) => (String::new(), Some(e.to_string())),
+        }).collect();
+    let plan = if rows.iter().all(|(_, problem)| problem.is_none()) {
+        Some(build(paths, &valid))
+    } else {
+        None
+    };
+    (rows, plan)
+}
+
+/// Splits a replacement into tokens.
+fn tokens(replace: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut chars = replace.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut spec = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => spec.push(c),
+                        None => return Err(invalid(format!("Unterminated {{ in {}", replace))),
+                    }
+                }
+                if !text.is_empty() {
+                    tokens.push(Token::Text(text.split_off(0)));
+                }
+                tokens.push(token(&spec)?);
+            }
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+    Ok(tokens)
+}
+
+/// Parses the content of a `{...}` token.
+fn token(spec: &str) -> Result<Token> {
+    let (name, format) = match spec.find(':') {
+        Some(i) => (&spec[..i], Some(&spec[i + 1..])),
+        None => (spec, None),
+    };
+    match (name, format) {
+        ("n", None) => Ok(Token::Counter(0)),
+        ("n", Some(width)) => width
+            .parse()
+            .map(Token::Counter)
+            .map_err(|_| invalid(format!("Invalid counter width: {{{}}}", spec))),
+        ("name", None) => Ok(Token::Name),
+        ("stem", None) => Ok(Token::Stem),
+        ("ext", None) => Ok(Token::Extension),
+        ("date", format) => {
+            let format = format.unwrap_or("%Y-%m-%d");
+            if StrftimeItems::new(format).any(|item| item == Item::Error) {
+                return Err(invalid(format!("Invalid date format: {}", format)));
+            }
+            Ok(Token::Date(format.to_string()))
+        }
+        _ => Err(invalid(format!("Unknown token: {{{}}}", spec))),
+    }
+}
+
+fn title_case(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut start = true;
+    for c in name.chars() {
+        if start {
+            result.extend(c.to_uppercase());
+        } else {
+            result.extend(c.to_lowercase());
+        }
+        start = !c.is_alphanumeric();
+    }
+    result
+}
+
+/// Returns the name without its extension. A leading dot does not start an extension.
+fn stem(name: &str) -> String {
+    match name.rfind('.') {
+        Some(i) if i > 0 => name[..i].to_string(),
+        _ => name.to_string(),
+    }
+}
+
+fn extension(name: &str) -> String {
+    match name.rfind('.') {
+        Some(i) if i > 0 => name[i + 1..].to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Orders `renames` so that no entry is replaced before it is renamed itself.
+/// Returns the steps and the number of cycles broken with a temporary name.
+fn order(renames: &[(PathBuf, PathBuf)]) -> (Vec<(PathBuf, PathBuf)>, usize) {
+    let mut pending: Vec<(PathBuf, PathBuf)> = renames.to_vec();
+    let mut steps = Vec::with_capacity(renames.len());
+    let mut cycles = 0;
+    while !pending.is_empty() {
+        // A rename can run once its destination is not waiting to be renamed.
+        let free = pending
+            .iter()
+            .position(|(_, dest)| !pending.iter().any(|(src, _)| src == dest));
+        match free {
+            Some(i) => steps.push(pending.remove(i)),
+            None => {
+                // Every pending rename is part of a cycle: move one entry out of the way.
+                let temporary = temporary_name(&pending[0].0, renames);
+                steps.push((pending[0].0.clone(), temporary.clone()));
+                pending[0].0 = temporary;
+                cycles += 1;
+            }
+        }
+    }
+    (steps, cycles)
+}
+
+/// Finds a free name next to `path`, used by none of the `renames`.
+fn temporary_name(path: &Path, renames: &[(PathBuf, PathBuf)]) -> PathBuf {
+    (0..)
+        .map(|i| path.with_file_name(format!(".marcos-rename-{}", i)))
+        .find(|candidate| {
+            candidate.symlink_metadata().is_err()
+                && !renames
+                    .iter()
+                    .any(|(src, dest)| src == candidate || dest == candidate)
+        }).unwrap_or_else(|| path.to_path_buf())
+}
+
+/// Returns the file name of `path`, which must be valid UTF-8 to be edited.
+fn file_name(path: &Path) -> Result<String> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.to_string())
+        .ok_or_else(|| invalid(format!("Cannot rename {:?}: its name is not UTF-8", path)))
+}
+
+fn invalid(reason: String) -> Error {
+    ErrorKind::InvalidOperation { reason }.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a fresh directory for a test, holding one file per name, each containing its
+    /// own name.
+    fn directory(test: &str, names: &[&str]) -> PathBuf {
+        let dir = ::std::env::temp_dir().join(format!(
+            "marcos-rename-{}-{}",
+            ::std::process::id(),
+            test
+        ));
+        let _ = stdfs::remove_dir_all(&dir);
+        stdfs::create_dir_all(&dir).unwrap();
+        for name in names {
+            stdfs::write(dir.join(name), name).unwrap();
+        }
+        dir
+    }
+
+    fn paths(dir: &Path, names: &[&str]) -> Vec<PathBuf> {
+        names.iter().map(|name| dir.join(name)).collect()
+    }
+
+    fn strings(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn content(dir: &Path, name: &str) -> String {
+        stdfs::read_to_string(dir.join(name)).unwrap()
+    }
+
+    #[test]
+    fn swaps_two_entries() {
+        let dir = directory("swap", &["a", "b"]);
+        let paths = paths(&dir, &["a", "b"]);
+        let names = strings(&["b", "a"]);
+        assert_eq!(check(&paths, &names), vec![None, None]);
+        let plan = build(&paths, &names);
+        assert_eq!(plan.cycles, 1);
+        assert_eq!(plan.steps.len(), 3);
+        plan.apply().unwrap();
+        assert_eq!(content(&dir, "a"), "b");
+        assert_eq!(content(&dir, "b"), "a");
+        assert_eq!(stdfs::read_dir(&dir).unwrap().count(), 2);
+        stdfs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rotates_three_entries() {
+        let dir = directory("cycle", &["a", "b", "c"]);
+        let paths = paths(&dir, &["a", "b", "c"]);
+        let plan = plan(&paths, "b\nc\na\n").unwrap();
+        assert_eq!(plan.cycles, 1);
+        assert_eq!(plan.steps.len(), 4);
+        plan.apply().unwrap();
+        assert_eq!(content(&dir, "b"), "a");
+        assert_eq!(content(&dir, "c"), "b");
+        assert_eq!(content(&dir, "a"), "c");
+        assert_eq!(stdfs::read_dir(&dir).unwrap().count(), 3);
+        stdfs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn orders_chains_without_temporary_names() {
+        let renames = vec![
+            (PathBuf::from("/d/a"), PathBuf::from("/d/b")),
+            (PathBuf::from("/d/b"), PathBuf::from("/d/c")),
+        ];
+        let (steps, cycles) = order(&renames);
+        assert_eq!(cycles, 0);
+        assert_eq!(steps, vec![renames[1].clone(), renames[0].clone()]);
+    }
+
+    #[test]
+    fn rejects_collisions() {
+        let dir = directory("collision", &["a", "b", "c"]);
+        let paths = paths(&dir, &["a", "b"]);
+        // `c` is not renamed, so it cannot be replaced.
+        let problems = check(&paths, &strings(&["c", "b"]));
+        assert!(problems[0].as_ref().unwrap().ends_with("already exists"));
+        assert_eq!(problems[1], None);
+        // `b` is renamed, so `a` can take its name.
+        assert_eq!(check(&paths, &strings(&["b", "d"])), vec![None, None]);
+        let problems = check(&paths, &strings(&["d", "d"]));
+        assert!(problems[1].as_ref().unwrap().starts_with("Duplicate name"));
+        let error = plan(&paths, "c\nb\n").unwrap_err().to_string();
+        assert!(error.contains("Line 1"), "{}", error);
+        stdfs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rolls_back_after_a_failed_step() {
+        let dir = directory("rollback", &["a", "b"]);
+        let plan = plan(&paths(&dir, &["a", "b"]), "x\ny\n").unwrap();
+        // An entry created after the check makes the second step fail.
+        stdfs::write(dir.join("y"), "y").unwrap();
+        let error = plan.apply().unwrap_err().to_string();
+        assert!(error.contains("already exists"), "{}", error);
+        assert_eq!(content(&dir, "a"), "a");
+        assert_eq!(content(&dir, "b"), "b");
+        assert!(dir.join("x").symlink_metadata().is_err());
+        stdfs::remove_dir_all(&dir).unwrap();
+    }
+}
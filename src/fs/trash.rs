@@ -0,0 +1,289 @@
+//! Trash can following the [freedesktop.org trash specification](https://specifications.freedesktop.org/trash-spec/trashspec-1.0.html).
+//!
+//! Trashed entries are moved to `$XDG_DATA_HOME/Trash/files`, and a `.trashinfo` file with
+//! the original path and the deletion date is written to `$XDG_DATA_HOME/Trash/info`.
+use std::fs as stdfs;
+use std::io::Write;
+use std::ffi::OsString;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use dirs;
+
+use crate::error::*;
+use crate::fs::ops;
+use crate::fs::ops::Monitor;
+
+const INFO_EXTENSION: &str = "trashinfo";
+
+/// An entry of the trash can.
+#[derive(Clone, Debug)]
+pub struct TrashEntry {
+    /// Name of the entry in the `files` directory of the trash.
+    pub name: String,
+    /// Path the entry had before being trashed.
+    pub original: PathBuf,
+    /// Deletion date, as written in the `.trashinfo` file.
+    pub deleted: String,
+}
+
+/// The home trash of the user.
+#[derive(Clone, Debug)]
+pub struct Trash {
+    root: PathBuf,
+}
+
+impl Trash {
+    /// Opens the home trash, `$XDG_DATA_HOME/Trash`, creating it if needed.
+    pub fn home() -> Result<Self> {
+        let data_path: PathBuf = dirs::data_dir().ok_or(ErrorKind::DirNotFound {
+            dirname: String::from("DATA_DIR"),
+        })?;
+        let trash = Trash {
+            root: data_path.join("Trash"),
+        };
+        stdfs::create_dir_all(trash.files_dir()).on_path(&trash.files_dir())?;
+        stdfs::create_dir_all(trash.info_dir()).on_path(&trash.info_dir())?;
+        Ok(trash)
+    }
+
+    fn files_dir(&self) -> PathBuf {
+        self.root.join("files")
+    }
+
+    fn info_dir(&self) -> PathBuf {
+        self.root.join("info")
+    }
+
+    fn info_path(&self, name: &str) -> PathBuf {
+        self.info_dir().join(format!("{}.{}", name, INFO_EXTENSION))
+    }
+
+    /// Returns the path of the trashed entry in the `files` directory.
+    pub fn file_path(&self, entry: &TrashEntry) -> PathBuf {
+        self.files_dir().join(&entry.name)
+    }
+
+    /// Moves `path` to the trash.
+    pub fn put(&self, path: &Path, monitor: &dyn Monitor) -> Result<TrashEntry> {
+        let original = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            ::std::env::current_dir()?.join(path)
+        };
+        let file_name = original.file_name().ok_or_else(|| ErrorKind::InvalidOperation {
+            reason: format!("Cannot trash {:?}", original),
+        })?;
+        let deleted = Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+        // The `.trashinfo` file is created first, atomically, to reserve the name.
+        let mut name = file_name.to_string_lossy().into_owned();
+        let mut info = loop {
+            match stdfs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(self.info_path(&name))
+            {
+                Ok(file) => break file,
+                Err(ref e) if e.kind() == ::std::io::ErrorKind::AlreadyExists => {
+                    let unique = ops::unique_name(&self.files_dir().join(&name));
+                    name = unique
+                        .file_name()
+                        .map(|n| n.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| format!("{}.1", name));
+                }
+                Err(e) => return Err(e).on_path(&self.info_path(&name)),
+            }
+        };
+        write!(
+            info,
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            encode(&original),
+            deleted
+        )?;
+        let entry = TrashEntry {
+            name,
+            original,
+            deleted,
+        };
+        if let Err(e) = ops::move_path(path, &self.file_path(&entry), monitor) {
+            let _ = stdfs::remove_file(self.info_path(&entry.name));
+            return Err(e);
+        }
+        Ok(entry)
+    }
+
+    /// Lists the trashed entries, most recently deleted first.
+    pub fn list(&self) -> Result<Vec<TrashEntry>> {
+        let mut entries = Vec::new();
+        for info in stdfs::read_dir(self.info_dir())? {
+            let info = info?.path();
+            if info.extension().and_then(|e| e.to_str()) != Some(INFO_EXTENSION) {
+                continue;
+            }
+            let name = match info.file_stem() {
+                Some(name) => name.to_string_lossy().into_owned(),
+                None => continue,
+            };
+            let content = stdfs::read_to_string(&info).on_path(&info)?;
+            let mut original = None;
+            let mut deleted = String::new();
+            for line in content.lines() {
+                if let Some(path) = line.strip_prefix("Path=") {
+                    original = Some(decode(path));
+                } else if let Some(date) = line.strip_prefix("DeletionDate=") {
+                    deleted = date.to_string();
+                }
+            }
+            if let Some(original) = original {
+                entries.push(TrashEntry {
+                    name,
+                    original,
+                    deleted,
+                });
+            }
+        }
+        entries.sort_by(|a, b| b.deleted.cmp(&a.deleted));
+        Ok(entries)
+    }
+
+    /// Moves `entry` back to its original location, or next to it under a new name if the
+    /// location is taken. Returns the restored path.
+    pub fn restore(&self, entry: &TrashEntry, monitor: &dyn Monitor) -> Result<PathBuf> {
+        let mut dest = entry.original.clone();
+        if let Some(parent) = dest.parent() {
+            stdfs::create_dir_all(parent).on_path(parent)?;
+        }
+        if dest.symlink_metadata().is_ok() {
+            dest = ops::unique_name(&dest);
+        }
+        ops::move_path(&self.file_path(entry), &dest, monitor)?;
+        let info = self.info_path(&entry.name);
+        stdfs::remove_file(&info).on_path(&info)?;
+        Ok(dest)
+    }
+
+    /// Permanently deletes `entry` from the trash.
+    pub fn erase(&self, entry: &TrashEntry, monitor: &dyn Monitor) -> Result<()> {
+        let file = self.file_path(entry);
+        if file.symlink_metadata().is_ok() {
+            ops::remove_path(&file, monitor)?;
+        }
+        let info = self.info_path(&entry.name);
+        stdfs::remove_file(&info).on_path(&info)?;
+        Ok(())
+    }
+
+    /// Permanently deletes every entry of the trash.
+    pub fn empty(&self, monitor: &dyn Monitor) -> Result<()> {
+        for entry in self.list()? {
+            self.erase(&entry, monitor)?;
+        }
+        Ok(())
+    }
+}
+
+/// Percent-encodes a path as required by the `Path` key of `.trashinfo` files.
+pub fn encode(path: &Path) -> String {
+    let mut encoded = String::new();
+    for byte in path.as_os_str().as_bytes() {
+        match *byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(*byte as char)
+            }
+            b => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+/// Decodes a percent-encoded path.
+pub fn decode(encoded: &str) -> PathBuf {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = if bytes[i] == b'%' && i + 2 < bytes.len() {
+            ::std::str::from_utf8(&bytes[i + 1..i + 3])
+                .ok()
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+        } else {
+            None
+        };
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    PathBuf::from(OsString::from_vec(decoded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_reserved_bytes() {
+        let path = Path::new("/home/me/My Notes/100%_done.txt");
+        assert_eq!(encode(path), "/home/me/My%20Notes/100%25_done.txt");
+        assert_eq!(encode(Path::new("/tmp/a~b-c")), "/tmp/a~b-c");
+    }
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let names: Vec<OsString> = vec![
+            OsString::from("/tmp/plain"),
+            OsString::from("/tmp/with space and %"),
+            OsString::from("/tmp/été/日本"),
+            OsString::from_vec(b"/tmp/not-utf8-\xff\xfe".to_vec()),
+        ];
+        for name in names {
+            let path = PathBuf::from(name);
+            assert_eq!(decode(&encode(&path)), path);
+        }
+    }
+
+    #[test]
+    fn decodes_lowercase_and_keeps_malformed_escapes() {
+        assert_eq!(decode("/tmp/a%2fb%20c"), PathBuf::from("/tmp/a/b c"));
+        assert_eq!(decode("/tmp/100%"), PathBuf::from("/tmp/100%"));
+        assert_eq!(decode("/tmp/%zz%4"), PathBuf::from("/tmp/%zz%4"));
+    }
+
+    #[test]
+    fn puts_lists_and_restores() {
+        let dir = ::std::env::temp_dir().join(format!("marcos-trash-{}", ::std::process::id()));
+        let _ = stdfs::remove_dir_all(&dir);
+        let trash = Trash {
+            root: dir.join("Trash"),
+        };
+        stdfs::create_dir_all(trash.files_dir()).unwrap();
+        stdfs::create_dir_all(trash.info_dir()).unwrap();
+        let file = dir.join("a file");
+        stdfs::write(&file, "first").unwrap();
+        let first = trash.put(&file, &()).unwrap();
+        stdfs::write(&file, "second").unwrap();
+        let second = trash.put(&file, &()).unwrap();
+        assert!(file.symlink_metadata().is_err());
+        assert_ne!(first.name, second.name);
+
+        let entries = trash.list().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|entry| entry.original == file));
+
+        assert_eq!(trash.restore(&first, &()).unwrap(), file);
+        assert_eq!(stdfs::read_to_string(&file).unwrap(), "first");
+        // The original location is taken, so the entry gets a new name next to it.
+        let restored = trash.restore(&second, &()).unwrap();
+        assert_eq!(restored, dir.join("a file (1)"));
+        assert_eq!(stdfs::read_to_string(&restored).unwrap(), "second");
+        assert!(trash.list().unwrap().is_empty());
+        stdfs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -32,38 +32,99 @@
 //! | k        | Select item up                                                                        |
 //! | h        | Go previous (left)                                                                    |
 //! | l        | Go next(right)                                                                        |
+//! | C-o, H   | Go back to the previously visited directory of the tab ([count] directories)          |
+//! | C-i, L   | Go forward again in the visited directories ([count] directories)                     |
 //! | :        | Activate command mode                                                                 |
 //! | gg       | Go to the first selection                                                             |
 //! | G        | Go to the last selection                                                              |
 //! | [count]G | Go to the [count] item                                                                |
 //! | za       | Toggle visibility of hidden items                                                     |
-//! | y        | Yank(Copy) the selected file/folder(Similar to Ctrl-c)                                |
-//! | x        | Cut the selected file/folder(similar to Ctrl-x)                                       |
+//! | y        | Yank(Copy) the marked entries, or the selected file/folder(Similar to Ctrl-c)         |
+//! | x        | Cut the marked entries, or the selected file/folder(similar to Ctrl-x)                |
 //! | p        | Paste the Copied/Cut file/folder(Similar to Ctrl-v)                                   |
 //! | r        | Rename selected file/folder                                                           |
-//! | dd       | Delete selected file/folder(with confirmation)                                        |
+//! | R        | Rename the marked entries, or all of them, in `$EDITOR` (with a preview)              |
+//! | gr       | Rename the marked entries, or all of them, with a pattern (`{n:03}`, `$1`, `{date}`)  |
+//! | dd       | Move the marked entries, or the selected one, to the trash (with confirmation)        |
+//! | D        | Permanently delete the marked entries, or the selected one (with confirmation)        |
+//! | T        | Show the trash (`r` restores, `D` deletes permanently the selected entry)             |
+//! | u        | Undo the last file operation (copy, move, rename, trash, create)                      |
+//! | U        | Redo the last undone file operation                                                   |
 //! | o        | Create new file(`touch filename`)                                                     |
 //! | O        | Create new directory (`mkdir dirname`)                                                |
 //! | P        | Paste the Copied/Cut file/folder replacing existing with same name(with Confirmation) |
-//! | mX       | Create a bookmark with name X                                                         |
-//! | `X       | Jump to bookmark with name X                                                          |
-//! | n        | Move to next match                                                                    |
-//! | N        | Move to previous match                                                                |
-//! | /        | Search                                                                                |
-//! | v        | Starts visual mode, selects all files until you press ESC                             |
-//! | V        | Visual mode, select all                                                               |
+//! | mX       | Bookmark the current directory as X (saved in `bookmarks.toml`)                       |
+//! | `X       | Jump to bookmark X; `` ` `` jumps back to the position before the last jump           |
+//! | n        | Move to next match, wrapping around                                                   |
+//! | N        | Move to previous match, wrapping around                                               |
+//! | /        | Search forward as you type (smart-case, regex if `search_regex` is set)               |
+//! | ?        | Search backward as you type                                                           |
+//! | C-p      | Fuzzy-find entries under the current directory and go to the chosen one               |
+//! | J / K    | Scroll the preview down / up (takes a count)                                          |
+//! | C-d      | Scroll the preview down by half a page (C-u scrolls up)                               |
+//! | zw       | Toggle wrapping of long lines in the preview                                          |
+//! | gx       | View the selected file in hexadecimal (`o` jumps to an offset, `/` searches bytes)    |
+//! | Space    | Mark or unmark the selected entry and move down ([count] entries)                     |
+//! | *        | Invert the marks                                                                      |
+//! | -        | Unmark all entries                                                                    |
+//! | v        | Start (or end) visual mode, marking the entries from here to the cursor until ESC     |
+//! | V        | Mark all entries                                                                      |
 //! | Ctrl+r   | Refresh(listings, data, cache, etc)                                                   |
+//! | gn       | Open a new tab in the current directory                                               |
+//! | gc       | Close the current tab                                                                 |
+//! | gt       | Go to the next tab, or to the tab [count] if a count is given                         |
+//! | gT       | Go to the previous tab                                                                |
+//! | Alt+N    | Go to the tab N (1-9)                                                                 |
+//! | w        | Show running and finished jobs (`p` pauses/resumes, `c` cancels the selected job)     |
 //! | ESC      | Get me out!                                                                           |
 //! |          |                                                                                       |
+//!
+//! Bare digits are count prefixes (`5j`, `3G`), so tabs are switched with Alt+1 to Alt+9 or
+//! `[count]gt` rather than with the keys 1 to 9.
+//!
+//! ## Commands
+//!
+//! | Command                      | Action                                                                  |
+//! |------------------------------|-------------------------------------------------------------------------|
+//! | `:cd [dir]`                  | Go to `dir`, or to the home directory                                   |
+//! | `:mkdir <dir>...`            | Create directories                                                      |
+//! | `:touch <file>...`           | Create empty files                                                      |
+//! | `:rename <name>`             | Rename the selected file/folder                                         |
+//! | `:bulkrename`                | Rename the marked entries, or all of them, in `$EDITOR`                 |
+//! | `:batchrename`               | Rename the marked entries, or all of them, with a pattern               |
+//! | `:delete[!]`                 | Move the marked or selected entries to the trash (`!` deletes them)     |
+//! | `:archive <file>`            | Archive the marked or selected entries, e.g. into `name.tar.gz`         |
+//! | `:mark <pattern>...`         | Mark entries matching a glob, `re:`, `type:`, `larger:`, `newer:`, ...  |
+//! | `:unmark <pattern>...`       | Unmark entries matching any of the patterns                             |
+//! | `:mark-newer <age>`          | Mark entries modified less than `age` ago, e.g. `2d` (or `-older`)      |
+//! | `:mark-larger <size>`        | Mark files larger than `size`, e.g. `10M` (or `-smaller`)               |
+//! | `:bookmarks`                 | List the bookmarks (`Enter` jumps, `d` deletes)                         |
+//! | `:bookmark <key> [dir]`      | Bookmark `dir`, or the current directory, as `key`                      |
+//! | `:delbookmark <key>...`      | Delete bookmarks                                                        |
+//! | `:history`                   | List the directories visited in the tab, to go back to one of them      |
+//! | `:z [fragment]...`           | Go to the most frecent visited directory matching, or pick one          |
+//! | `:find <criterion>...`       | List entries below the current directory matching all (`h` leaves)      |
+//! | `:grep <pattern>`            | List the lines matching in the files below the current directory        |
+//! | `:set <opt>[=<value>]...`    | Set options of `[Options]`; `opt` and `noopt` set booleans              |
+//! | `:sort[!] <key> [reverse]`   | Sort by name, size, modified or extension (`!` reverses)                |
+//! | `:tabnew [dir]`              | Open a new tab in `dir`, or in the current directory                    |
+//! | `:q[uit][!]`                 | Exit marcos (`!` does not ask about running jobs)                       |
+//!
+//! Arguments may be quoted with `'...'` or `"..."`, and errors are shown in the status bar.
 
 #[macro_use]
 extern crate log;
 extern crate alphanumeric_sort;
+extern crate chrono;
+extern crate crossbeam_channel;
 extern crate cursive;
 extern crate dirs;
 extern crate failure;
 extern crate fern;
+extern crate filetime;
+extern crate image;
 extern crate mime_guess;
+extern crate regex;
 extern crate systemstat;
 extern crate uname;
 extern crate users;
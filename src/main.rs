@@ -52,7 +52,7 @@ fn try_main() -> Result<(), failure::Error> {
         },
         matches.value_of("log"),
         matches.value_of("log_level"),
-    ).unwrap();
+    )?;
     app.run();
     // if let Some(c) = matches.value_of("path") {
     //     let mut app = core::app::init(c).unwrap();
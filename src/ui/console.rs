@@ -0,0 +1,216 @@
+//! Console shown at the bottom of the screen by `:`, `/` and `?`.
+//!
+//! Up and Down recall the lines previously entered in the same prompt, and in the command
+//! prompt Tab (Shift-Tab backwards) completes command names, option names and paths. When
+//! several candidates match, they are listed above the console and Tab cycles through them.
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use cursive::event::{Event, Key};
+use cursive::theme::{ColorStyle, ColorType, Effect, PaletteColor};
+use cursive::traits::{Boxable, Identifiable};
+use cursive::utils::markup::StyledString;
+use cursive::views::{EditView, HideableView, IdView, LinearLayout, OnEventView, TextView};
+use cursive::Cursive;
+
+use crate::config::commands;
+use crate::utils::history::{History, Recall};
+
+/// Maximum number of candidates listed above the console.
+const MAX_CANDIDATES: usize = 50;
+
+/// Candidates of the completion in progress.
+struct Completion {
+    /// Offset of the completed word in the line.
+    start: usize,
+    candidates: Vec<String>,
+    /// Candidate currently inserted, if cycling.
+    index: Option<usize>,
+    /// Content of the console after the last completion, to detect edits.
+    line: String,
+}
+
+/// Returns the name under which the history of the prompt `prefix` is saved.
+pub fn prompt_name(prefix: &str) -> &'static str {
+    match prefix {
+        ":" => "command",
+        "/" => "search",
+        "?" => "search_reverse",
+        _ => "other",
+    }
+}
+
+/// Creates the console for the prompt `prefix`. Paths are completed relative to `cwd`.
+///
+/// `on_edit` is called whenever the user edits the line. Submitted lines are added to
+/// `history` before `on_submit` is called, once the console is closed.
+pub fn create_console<E, F>(
+    prefix: &str,
+    cwd: PathBuf,
+    history: Rc<RefCell<History>>,
+    on_edit: E,
+    on_submit: F,
+) -> OnEventView<LinearLayout>
+where
+    E: Fn(&mut Cursive, &str) + 'static,
+    F: Fn(&mut Cursive, &str) + 'static,
+{
+    let name = prompt_name(prefix);
+    let recall = Rc::new(RefCell::new(Recall::default()));
+    let completion: Rc<RefCell<Option<Completion>>> = Rc::new(RefCell::new(None));
+
+    let (e_recall, e_completion) = (recall.clone(), completion.clone());
+    let s_history = history.clone();
+    let edit_view = EditView::new()
+        .filler(" ")
+        .on_edit(move |s, content, _| {
+            e_recall.borrow_mut().reset();
+            if e_completion.borrow_mut().take().is_some() {
+                hide_candidates(s);
+            }
+            on_edit(s, content);
+        }).on_submit(move |s, content| {
+            if let Err(e) = s_history.borrow_mut().push(name, content) {
+                warn!("Cannot save history: {}", e);
+            }
+            s.pop_layer();
+            on_submit(s, content);
+        }).style(ColorStyle::new(
+            ColorType::from(PaletteColor::Background),
+            ColorType::from(PaletteColor::Primary),
+        )).with_id("console");
+
+    let mut popup = HideableView::new(TextView::new("").with_id("console/candidates"));
+    popup.hide();
+    let layout = LinearLayout::vertical()
+        .child(popup.with_id("console/popup"))
+        .child(
+            LinearLayout::horizontal()
+                .child(TextView::new(prefix))
+                .child(edit_view.full_width()),
+        );
+
+    let (u_history, u_recall) = (history.clone(), recall.clone());
+    let (d_history, d_recall) = (history, recall);
+    let mut view = OnEventView::new(layout)
+        .on_pre_event(Key::Up, move |s| {
+            let lines = u_history.borrow().lines(name).to_vec();
+            let current = content(s);
+            if let Some(line) = u_recall.borrow_mut().previous(&lines, &current) {
+                set_line(s, &line);
+            }
+        }).on_pre_event(Key::Down, move |s| {
+            let lines = d_history.borrow().lines(name).to_vec();
+            if let Some(line) = d_recall.borrow_mut().next(&lines) {
+                set_line(s, &line);
+            }
+        });
+    if prefix == ":" {
+        let (f_completion, f_cwd) = (completion.clone(), cwd.clone());
+        view.set_on_pre_event(Key::Tab, move |s| complete(s, &f_completion, &f_cwd, true));
+        view.set_on_pre_event(Event::Shift(Key::Tab), move |s| {
+            complete(s, &completion, &cwd, false)
+        });
+    } else {
+        // Tab is also `C-i`, which must not reach the key bindings while typing.
+        view.set_on_pre_event(Key::Tab, |_| {});
+        view.set_on_pre_event(Event::Shift(Key::Tab), |_| {});
+    }
+    view
+}
+
+fn content(s: &mut Cursive) -> String {
+    s.call_on_id("console", |view: &mut EditView| view.get_content())
+        .map(|content| (*content).clone())
+        .unwrap_or_default()
+}
+
+fn set_line(s: &mut Cursive, line: &str) {
+    s.call_on_id("console", |view: &mut EditView| {
+        view.set_content(line);
+        view.set_cursor(line.len());
+    });
+}
+
+/// Completes the last word of the command line, or inserts the next (or
+/// previous) candidate if the line was just completed.
+fn complete(
+    s: &mut Cursive,
+    completion: &Rc<RefCell<Option<Completion>>>,
+    cwd: &Path,
+    forward: bool,
+) {
+    let line = content(s);
+    let mut completion = completion.borrow_mut();
+    let cycling = completion
+        .as_ref()
+        .map_or(false, |c| c.line == line && c.candidates.len() > 1);
+    if cycling {
+        if let Some(ref mut c) = *completion {
+            let len = c.candidates.len();
+            let index = match (c.index, forward) {
+                (None, true) => 0,
+                (None, false) => len - 1,
+                (Some(i), true) => (i + 1) % len,
+                (Some(i), false) => (i + len - 1) % len,
+            };
+            c.index = Some(index);
+            c.line = format!("{}{}", &line[..c.start], c.candidates[index]);
+            set_line(s, &c.line);
+            show_candidates(s, &c.candidates, c.index);
+        }
+        return;
+    }
+    let (start, candidates) = commands::complete(&line, cwd);
+    match candidates.len() {
+        0 => {
+            *completion = None;
+            hide_candidates(s);
+        }
+        1 => {
+            *completion = None;
+            set_line(s, &format!("{}{}", &line[..start], candidates[0]));
+            hide_candidates(s);
+        }
+        _ => {
+            let line = format!("{}{}", &line[..start], commands::common_prefix(&candidates));
+            set_line(s, &line);
+            show_candidates(s, &candidates, None);
+            *completion = Some(Completion {
+                start,
+                candidates,
+                index: None,
+                line,
+            });
+        }
+    }
+}
+
+/// Lists the candidates above the console, highlighting the inserted one.
+fn show_candidates(s: &mut Cursive, candidates: &[String], index: Option<usize>) {
+    let mut text = StyledString::new();
+    for (i, candidate) in candidates.iter().enumerate().take(MAX_CANDIDATES) {
+        if i > 0 {
+            text.append_plain("  ");
+        }
+        if Some(i) == index {
+            text.append_styled(candidate.as_str(), Effect::Reverse);
+        } else {
+            text.append_plain(candidate.as_str());
+        }
+    }
+    if candidates.len() > MAX_CANDIDATES {
+        text.append_plain(format!("  ({} more)", candidates.len() - MAX_CANDIDATES));
+    }
+    s.call_on_id("console/candidates", |view: &mut TextView| view.set_content(text));
+    s.call_on_id("console/popup", |view: &mut HideableView<IdView<TextView>>| {
+        view.unhide()
+    });
+}
+
+fn hide_candidates(s: &mut Cursive) {
+    s.call_on_id("console/popup", |view: &mut HideableView<IdView<TextView>>| {
+        view.hide()
+    });
+}
@@ -0,0 +1,80 @@
+//! Running an external editor in the terminal, with the cursive UI suspended meanwhile.
+use std::env;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use cursive::Cursive;
+
+use crate::error::*;
+
+/// Leaves the alternate screen, shows the cursor and stops mouse reporting.
+const SUSPEND: &str = "\x1b[?1006l\x1b[?1015l\x1b[?1002l\x1b[?1000l\x1b[?25h\x1b[?1049l";
+/// Undoes `SUSPEND`.
+const RESUME: &str = "\x1b[?1049h\x1b[?25l\x1b[?1000h\x1b[?1002h\x1b[?1015h\x1b[?1006h";
+
+/// Returns the editor command: `$VISUAL`, `$EDITOR` or `vi`.
+pub fn editor() -> String {
+    env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| String::from("vi"))
+}
+
+/// Opens `path` in the editor and waits for it to exit. The terminal is given back to the
+/// editor in its usual mode meanwhile, and the UI is redrawn afterwards.
+pub fn edit(s: &mut Cursive, path: &Path) -> Result<()> {
+    let editor = editor();
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let args: Vec<&str> = words.collect();
+
+    let saved = stty(&["-g"]);
+    suspend();
+    if saved.is_some() {
+        stty(&["sane"]);
+    }
+    let status = Command::new(program).args(&args).arg(path).status();
+    if let Some(ref saved) = saved {
+        stty(&[saved.trim()]);
+    }
+    resume();
+    s.clear();
+
+    let status = status.map_err(|e| ErrorKind::InvalidOperation {
+        reason: format!("Cannot run {}: {}", program, e),
+    })?;
+    if !status.success() {
+        return Err(ErrorKind::InvalidOperation {
+            reason: format!("{} exited with {}", program, status),
+        }.into());
+    }
+    Ok(())
+}
+
+/// Runs `stty` on the terminal, returning its output if it succeeded.
+fn stty(args: &[&str]) -> Option<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()
+        .ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        None
+    }
+}
+
+fn suspend() {
+    let mut stdout = io::stdout();
+    let _ = stdout.write_all(SUSPEND.as_bytes());
+    let _ = stdout.flush();
+}
+
+fn resume() {
+    let mut stdout = io::stdout();
+    let _ = stdout.write_all(RESUME.as_bytes());
+    let _ = stdout.flush();
+}
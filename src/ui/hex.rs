@@ -0,0 +1,293 @@
+//! Full-screen hexadecimal viewer of a file, reading only the rows shown, so that files of any
+//! size can be paged through, jumped in and searched for byte patterns.
+use std::cmp::min;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use cursive::direction::Direction;
+use cursive::event::{Event, EventResult, Key};
+use cursive::theme::{ColorStyle, Effect};
+use cursive::traits::{Boxable, Identifiable};
+use cursive::vec::Vec2;
+use cursive::view::View;
+use cursive::views::{LinearLayout, TextView};
+use cursive::{Cursive, Printer};
+
+use crate::error::*;
+use crate::ui::prompt;
+use crate::utils::hex::{self, ROW_SIZE};
+
+/// Keys of the viewer, shown in its status line.
+const HELP: &str = "j/k: rows, Space/b: pages, g/G: start/end, o: offset, /: search, n: next";
+
+pub struct HexView {
+    path: PathBuf,
+    file: File,
+    len: u64,
+    /// Offset of the first row shown, a multiple of `ROW_SIZE`.
+    top: u64,
+    /// Number of rows shown.
+    rows: usize,
+    /// Bytes of the rows shown.
+    data: Vec<u8>,
+    /// Pattern searched last, and the offset of the match shown.
+    pattern: Vec<u8>,
+    found: Option<u64>,
+    /// Message for the status line, until the next move.
+    message: Option<String>,
+    popup: bool,
+}
+
+impl HexView {
+    /// Opens `path`, asking for offsets and patterns in popups if `popup` is set.
+    pub fn open(path: &Path, popup: bool) -> Result<Self> {
+        let cannot_open = |e: io::Error| ErrorKind::InvalidOperation {
+            reason: format!("Cannot open {}: {}", path.display(), e),
+        };
+        let file = File::open(path).map_err(cannot_open)?;
+        let metadata = file.metadata().map_err(cannot_open)?;
+        if !metadata.is_file() {
+            return Err(ErrorKind::InvalidOperation {
+                reason: format!("Not a file: {}", path.display()),
+            }.into());
+        }
+        Ok(HexView {
+            path: path.to_path_buf(),
+            file,
+            len: metadata.len(),
+            top: 0,
+            rows: 0,
+            data: Vec::new(),
+            pattern: Vec::new(),
+            found: None,
+            message: None,
+            popup,
+        })
+    }
+
+    /// Scrolls `count` rows down.
+    pub fn scroll_down(&mut self, count: usize) {
+        let top = self.top.saturating_add((count * ROW_SIZE) as u64);
+        self.top = min(top, self.max_top());
+    }
+
+    /// Scrolls `count` rows up.
+    pub fn scroll_up(&mut self, count: usize) {
+        self.top = self.top.saturating_sub((count * ROW_SIZE) as u64);
+    }
+
+    /// Shows the row holding `offset` first, or the last rows if it is near the end.
+    pub fn go_to(&mut self, offset: u64) {
+        if offset > self.len {
+            self.message = Some(format!("Offset beyond the end: 0x{:x}", offset));
+        }
+        let row = min(offset, self.len) / ROW_SIZE as u64 * ROW_SIZE as u64;
+        self.top = min(row, self.max_top());
+    }
+
+    /// Searches `pattern` from the start of the rows shown.
+    pub fn search(&mut self, pattern: Vec<u8>) {
+        self.pattern = pattern;
+        let top = self.top;
+        self.search_from(top);
+    }
+
+    /// Searches the last pattern again after the match shown.
+    pub fn search_next(&mut self) {
+        if self.pattern.is_empty() {
+            self.message = Some(String::from("No pattern searched yet"));
+            return;
+        }
+        let from = self.found.map_or(self.top, |found| found + 1);
+        self.search_from(from);
+    }
+
+    /// Searches the last pattern from `from`, wrapping around to the start of the
+    /// file, and shows the match.
+    fn search_from(&mut self, from: u64) {
+        let mut found = hex::search(&mut self.file, &self.pattern, from);
+        if let Ok(None) = found {
+            if from > 0 {
+                found = hex::search(&mut self.file, &self.pattern, 0);
+                self.message = Some(String::from("Search wrapped around"));
+            }
+        }
+        match found {
+            Ok(Some(offset)) => {
+                self.found = Some(offset);
+                self.go_to(offset);
+            }
+            Ok(None) => {
+                self.found = None;
+                self.message = Some(String::from("Pattern not found"));
+            }
+            Err(e) => self.message = Some(format!("Cannot read {}: {}", self.path.display(), e)),
+        }
+    }
+
+    /// Returns the text of the status line, then forgets the last message.
+    pub fn status(&mut self) -> String {
+        let shown = min(self.top + (self.rows * ROW_SIZE) as u64, self.len);
+        let percent = (shown * 100).checked_div(self.len).unwrap_or(100);
+        let position = format!(
+            "{}  0x{:08x} / 0x{:08x}  {}%",
+            self.path.display(),
+            self.top,
+            self.len,
+            percent
+        );
+        match self.message.take() {
+            Some(message) => format!("{}  {}", position, message),
+            None => format!("{}  {}", position, HELP),
+        }
+    }
+
+    fn page(&self) -> usize {
+        self.rows.max(1)
+    }
+
+    fn max_top(&self) -> u64 {
+        let rows = (self.len + ROW_SIZE as u64 - 1) / ROW_SIZE as u64;
+        rows.saturating_sub(self.rows as u64) * ROW_SIZE as u64
+    }
+
+    /// Reads the bytes of the rows shown.
+    fn load(&mut self) {
+        self.data.clear();
+        let size = (self.rows * ROW_SIZE) as u64;
+        let (file, data) = (&mut self.file, &mut self.data);
+        let result = file.seek(SeekFrom::Start(self.top));
+        let result = result.and_then(|_| file.take(size).read_to_end(data));
+        if let Err(e) = result {
+            self.message = Some(format!("Cannot read {}: {}", self.path.display(), e));
+        }
+    }
+
+    /// Asks a value in a prompt, and runs `f` on the viewer with it.
+    fn ask<F>(&self, message: &'static str, initial: &'static str, f: F) -> EventResult
+    where
+        F: Fn(&mut HexView, &str) + 'static,
+    {
+        let popup = self.popup;
+        let f = Rc::new(f);
+        EventResult::with_cb(move |s| {
+            let f = f.clone();
+            prompt::input(s, message, initial, initial.len(), popup, move |s, line| {
+                s.call_on_id("hex", |view: &mut HexView| f(view, line));
+                show_status(s);
+            });
+        })
+    }
+}
+
+impl View for HexView {
+    fn draw(&self, printer: &Printer) {
+        let matched = |offset: u64| match self.found {
+            Some(found) => offset >= found && offset < found + self.pattern.len() as u64,
+            None => false,
+        };
+        for (y, chunk) in self.data.chunks(ROW_SIZE).enumerate().take(printer.size.y) {
+            let offset = self.top + (y * ROW_SIZE) as u64;
+            let row = hex::row(offset, chunk);
+            printer.print((0, y), &row);
+            printer.with_color(ColorStyle::secondary(), |printer| {
+                printer.print((0, y), &row[..8])
+            });
+            for (i, byte) in chunk.iter().enumerate() {
+                if !matched(offset + i as u64) {
+                    continue;
+                }
+                let (hex_x, ascii_x) = hex::columns(i);
+                printer.with_effect(Effect::Reverse, |printer| {
+                    printer.print((hex_x, y), &format!("{:02x}", byte));
+                    printer.print((ascii_x, y), &hex::printable(*byte).to_string());
+                });
+            }
+        }
+    }
+
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        constraint
+    }
+
+    /// Reads the rows shown, which may have moved since the last layout.
+    fn layout(&mut self, size: Vec2) {
+        self.rows = size.y;
+        self.top = min(self.top, self.max_top());
+        self.load();
+    }
+
+    fn take_focus(&mut self, _: Direction) -> bool {
+        true
+    }
+
+    fn on_event(&mut self, event: Event) -> EventResult {
+        match event {
+            Event::Char('j') | Event::Key(Key::Down) => self.scroll_down(1),
+            Event::Char('k') | Event::Key(Key::Up) => self.scroll_up(1),
+            Event::Char(' ') | Event::CtrlChar('f') | Event::Key(Key::PageDown) => {
+                let page = self.page();
+                self.scroll_down(page);
+            }
+            Event::Char('b') | Event::CtrlChar('b') | Event::Key(Key::PageUp) => {
+                let page = self.page();
+                self.scroll_up(page);
+            }
+            Event::CtrlChar('d') => {
+                let half = (self.page() / 2).max(1);
+                self.scroll_down(half);
+            }
+            Event::CtrlChar('u') => {
+                let half = (self.page() / 2).max(1);
+                self.scroll_up(half);
+            }
+            Event::Char('g') | Event::Key(Key::Home) => self.go_to(0),
+            Event::Char('G') | Event::Key(Key::End) => {
+                let len = self.len;
+                self.go_to(len);
+            }
+            Event::Char('o') => {
+                return self.ask("Offset:", "0x", |view, line| match hex::parse_offset(line) {
+                    Ok(offset) => view.go_to(offset),
+                    Err(e) => view.message = Some(e.to_string()),
+                });
+            }
+            Event::Char('/') => {
+                return self.ask("Bytes:", "", |view, line| match hex::parse_pattern(line) {
+                    Ok(pattern) => view.search(pattern),
+                    Err(e) => view.message = Some(e.to_string()),
+                });
+            }
+            Event::Char('n') => self.search_next(),
+            Event::Char('q') | Event::Key(Key::Esc) => {
+                return EventResult::with_cb(|s| {
+                    s.pop_layer();
+                });
+            }
+            // Other keys must not reach the key bindings of the listing below.
+            Event::Char(_) | Event::CtrlChar(_) | Event::AltChar(_) => {}
+            _ => return EventResult::Ignored,
+        }
+        EventResult::with_cb(show_status)
+    }
+}
+
+/// Shows `path` in a full-screen hexadecimal viewer, above a status line.
+pub fn show(s: &mut Cursive, path: &Path, popup: bool) -> Result<()> {
+    let view = HexView::open(path, popup)?;
+    let layout = LinearLayout::vertical()
+        .child(view.with_id("hex").full_height())
+        .child(TextView::new("").with_id("hex/status"));
+    s.add_fullscreen_layer(layout);
+    show_status(s);
+    Ok(())
+}
+
+/// Shows the position in the file, or the last message, in the status line.
+fn show_status(s: &mut Cursive) {
+    if let Some(status) = s.call_on_id("hex", |view: &mut HexView| view.status()) {
+        s.call_on_id("hex/status", |view: &mut TextView| view.set_content(status));
+    }
+}
@@ -1,5 +1,12 @@
 //! Contains structs and function to manipulate view of the file manager.
+pub mod console;
+pub mod editor;
+pub mod hex;
 pub mod multi_select;
+pub mod picker;
+pub mod preview;
+pub mod prompt;
 pub mod tab;
 pub use self::multi_select::MultiSelectView;
-pub use crate::ui::tab::Tab;
+pub use self::preview::PreviewView;
+pub use crate::ui::tab::{Found, Tab};
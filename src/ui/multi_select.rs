@@ -3,7 +3,7 @@ use cursive::direction::Direction;
 use cursive::event::{Callback, Event, EventResult, Key, MouseButton, MouseEvent};
 use cursive::menu::MenuTree;
 use cursive::rect::Rect;
-use cursive::theme::ColorStyle;
+use cursive::theme::{ColorStyle, Effect};
 use cursive::vec::Vec2;
 use cursive::view::{Position, View};
 use cursive::views::MenuPopup;
@@ -12,13 +12,21 @@ use cursive::Printer;
 use cursive::With;
 use std::borrow::Borrow;
 use std::cell::Cell;
-use std::cmp::min;
+use std::cmp::{max, min};
 use std::rc::Rc;
 use unicode_width::UnicodeWidthStr;
 
-/// View to select an item among a list.
+/// Callback run with an item of the list.
+type ItemCallback<T> = Rc<dyn Fn(&mut Cursive, &T)>;
+
+/// Returns the byte ranges of a label to highlight.
+type Highlight = Rc<dyn Fn(&str) -> Vec<(usize, usize)>>;
+
+/// View to select items among a list.
 ///
-/// It contains a list of values of type T, with associated labels.
+/// It contains a list of values of type T, with associated labels. Besides the focused item,
+/// any number of items can be selected, either one by one or as a visual range which extends
+/// from an anchor to the focus as the focus moves.
 ///
 /// # Examples
 ///
@@ -55,9 +63,9 @@ pub struct MultiSelectView<T = String> {
     focus: Rc<Cell<usize>>,
     // This is a custom callback to include a &T.
     // It will be called whenever "Enter" is pressed.
-    on_submit: Option<Rc<Fn(&mut Cursive, &T)>>,
+    on_submit: Option<ItemCallback<T>>,
     // This callback is called when the selection is changed.
-    on_select: Option<Rc<Fn(&mut Cursive, &T)>>,
+    on_select: Option<ItemCallback<T>>,
     align: Align,
     // `true` if we show a one-line view, with popup on selection.
     popup: bool,
@@ -65,9 +73,10 @@ pub struct MultiSelectView<T = String> {
     // We "cache" it during the draw, so we need interior mutability.
     last_offset: Cell<Vec2>,
     last_size: Vec2,
-    input_buffer: Vec<Event>,
-    input_num_buffer: Vec<usize>,
-    input_count: usize,
+    // Returns the byte ranges of the labels to highlight, e.g. search matches.
+    highlight: Option<Highlight>,
+    // Anchor of the visual range, which spans from it to the focus.
+    visual: Option<usize>,
 }
 
 impl<T: 'static> Default for MultiSelectView<T> {
@@ -89,9 +98,8 @@ impl<T: 'static> MultiSelectView<T> {
             popup: false,
             last_offset: Cell::new(Vec2::zero()),
             last_size: Vec2::zero(),
-            input_buffer: Vec::new(),
-            input_num_buffer: Vec::new(),
-            input_count: 0,
+            highlight: None,
+            visual: None,
         }
     }
 
@@ -204,6 +212,43 @@ impl<T: 'static> MultiSelectView<T> {
         self
     }
 
+    /// Sets a function returning the byte ranges of a label to highlight.
+    pub fn set_highlight<F>(&mut self, highlight: F)
+    where
+        F: Fn(&str) -> Vec<(usize, usize)> + 'static,
+    {
+        self.highlight = Some(Rc::new(highlight));
+    }
+
+    /// Removes the highlighting set by `set_highlight`.
+    pub fn clear_highlight(&mut self) {
+        self.highlight = None;
+    }
+
+    /// Returns the index of the first item, starting at `start` and wrapping around, whose
+    /// label satisfies `predicate`, along with whether the search wrapped around.
+    ///
+    /// Items are visited downwards if `forward` is set, upwards otherwise.
+    pub fn find<F>(&self, start: usize, forward: bool, predicate: F) -> Option<(usize, bool)>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let len = self.items.len();
+        if len == 0 {
+            return None;
+        }
+        let start = min(start, len - 1);
+        (0..len)
+            .map(|step| {
+                if forward {
+                    (start + step) % len
+                } else {
+                    (start + len - step) % len
+                }
+            }).find(|&i| predicate(&self.items[i].label))
+            .map(|i| (i, if forward { i < start } else { i > start }))
+    }
+
     /// Returns the value of the currently selected item.
     ///
     /// Returns `None` if the list is empty.
@@ -220,6 +265,110 @@ impl<T: 'static> MultiSelectView<T> {
     pub fn clear(&mut self) {
         self.items.clear();
         self.focus.set(0);
+        self.visual = None;
+    }
+
+    /// Returns `true` if the item at `i` is selected, or in the visual range.
+    pub fn is_selected(&self, i: usize) -> bool {
+        self.items.get(i).map_or(false, |item| item.selected) || self.in_visual_range(i)
+    }
+
+    fn in_visual_range(&self, i: usize) -> bool {
+        self.visual.map_or(false, |anchor| {
+            let focus = self.focus();
+            min(anchor, focus) <= i && i <= max(anchor, focus)
+        })
+    }
+
+    /// Selects or unselects the item at `i`.
+    pub fn set_selected(&mut self, i: usize, selected: bool) {
+        if let Some(item) = self.items.get_mut(i) {
+            item.selected = selected;
+        }
+    }
+
+    /// Toggles the selection of the item at `i`.
+    pub fn toggle_selected(&mut self, i: usize) {
+        if let Some(item) = self.items.get_mut(i) {
+            item.selected = !item.selected;
+        }
+    }
+
+    /// Selects (or unselects, if `selected` is not set) the items whose value satisfies
+    /// `predicate`. Returns the number of items whose selection changed.
+    pub fn select_where<F>(&mut self, selected: bool, predicate: F) -> usize
+    where
+        F: Fn(&T) -> bool,
+    {
+        let mut changed = 0;
+        for item in &mut self.items {
+            if item.selected != selected && predicate(&item.value) {
+                item.selected = selected;
+                changed += 1;
+            }
+        }
+        changed
+    }
+
+    /// Selects every item.
+    pub fn select_all(&mut self) {
+        self.visual = None;
+        for item in &mut self.items {
+            item.selected = true;
+        }
+    }
+
+    /// Selects the unselected items and unselects the others.
+    pub fn invert_selection(&mut self) {
+        self.stop_visual();
+        for item in &mut self.items {
+            item.selected = !item.selected;
+        }
+    }
+
+    /// Unselects every item and leaves the visual mode.
+    pub fn clear_selection(&mut self) {
+        self.visual = None;
+        for item in &mut self.items {
+            item.selected = false;
+        }
+    }
+
+    /// Starts a visual range anchored at the focused item.
+    pub fn start_visual(&mut self) {
+        if !self.items.is_empty() {
+            self.visual = Some(self.focus());
+        }
+    }
+
+    /// Leaves the visual mode, keeping the items of the range selected.
+    pub fn stop_visual(&mut self) {
+        if self.visual.is_some() {
+            for i in 0..self.items.len() {
+                if self.in_visual_range(i) {
+                    self.items[i].selected = true;
+                }
+            }
+            self.visual = None;
+        }
+    }
+
+    /// Returns `true` if a visual range is in progress.
+    pub fn is_visual(&self) -> bool {
+        self.visual.is_some()
+    }
+
+    /// Returns the number of selected items.
+    pub fn selected_count(&self) -> usize {
+        (0..self.items.len()).filter(|&i| self.is_selected(i)).count()
+    }
+
+    /// Returns the values of the selected items, in the order of the list.
+    pub fn selected_items(&self) -> Vec<Rc<T>> {
+        (0..self.items.len())
+            .filter(|&i| self.is_selected(i))
+            .map(|i| Rc::clone(&self.items[i].value))
+            .collect()
     }
 
     /// Adds a item to the list, with given label and value.
@@ -271,6 +420,11 @@ impl<T: 'static> MultiSelectView<T> {
         if focus >= id && focus > 0 {
             self.focus.set(focus - 1);
         }
+        self.visual = match self.visual {
+            Some(_) if self.items.is_empty() => None,
+            Some(anchor) if anchor >= id && anchor > 0 => Some(anchor - 1),
+            anchor => anchor,
+        };
 
         self.make_select_cb().unwrap_or_else(Callback::dummy)
     }
@@ -315,7 +469,16 @@ impl<T: 'static> MultiSelectView<T> {
         let l = self.items[i].label.width();
         let x = self.align.h.get_offset(l, printer.size.x);
         printer.print_hline((0, 0), x, " ");
-        printer.print((x, 0), &self.items[i].label);
+        let label = &self.items[i].label;
+        printer.print((x, 0), label);
+        if let Some(ref highlight) = self.highlight {
+            for (start, end) in highlight(label) {
+                let offset = x + label[..start].width();
+                printer.with_effect(Effect::Underline, |printer| {
+                    printer.print((offset, 0), &label[start..end])
+                });
+            }
+        }
         if l < printer.size.x {
             assert!((l + x) <= printer.size.x);
             printer.print_hline((x + l, 0), printer.size.x - (l + x), " ");
@@ -428,52 +591,16 @@ impl<T: 'static> MultiSelectView<T> {
         )
     }
 
+    // Vim-like keys (`j`, `k`, `gg`, `[count]G`, ...) are not handled here, but by the
+    // `KeyDispatcher` according to the configured key maps.
     fn on_event_regular(&mut self, event: Event) -> EventResult {
-        if is_numeric(&event) {
-            let number: Option<u32> = match event {
-                Event::Char(c) => c.to_digit(10),
-                _ => None,
-            };
-            if let Some(c) = number {
-                self.input_num_buffer.push(c as usize)
-            };
-            return EventResult::Ignored;
-        }
-
-        if should_intercept(&event) {
-            self.input_buffer.push(event.clone())
-        }
-        match self.input_buffer.as_slice() {
-            [Event::Char('g'), Event::Char('g')] => {
-                self.focus.set(0);
-                self.input_buffer.clear();
-                return EventResult::Consumed(self.make_select_cb());
-            }
-            [Event::Char('G')] => {
-                let num = get_number(&self.input_num_buffer);
-                if num == 0 {
-                } else {
-                    if num - 1 < self.items.len() {
-                        self.focus.set(num - 1);
-                    } else {
-                        self.focus.set(self.items.len().saturating_sub(1));
-                    }
-                    self.input_buffer.clear();
-                    self.input_num_buffer.clear();
-                    return EventResult::Consumed(self.make_select_cb());
-                }
-            }
-            _ => {}
-        }
         match event {
-            Event::Key(Key::Esc) => self.input_num_buffer.clear(),
             Event::Key(Key::Up) if self.focus() > 0 => self.focus_up(1),
             Event::Key(Key::Down) if self.focus() + 1 < self.items.len() => self.focus_down(1),
             Event::Key(Key::PageUp) => self.focus_up(10),
             Event::Key(Key::PageDown) => self.focus_down(10),
             Event::Key(Key::Home) => self.focus.set(0),
             Event::Key(Key::End) => self.focus.set(self.items.len().saturating_sub(1)),
-            Event::Char('G') => self.focus.set(self.items.len().saturating_sub(1)),
             // Event::Char('/') => {
             //     debug!("You pressed search key!");
             // },
@@ -708,6 +835,14 @@ impl<T: 'static> View for MultiSelectView<T> {
                             printer.with_color(ColorStyle::secondary(), |printer| {
                                 self.draw_item(printer, i)
                             });
+                        } else if i != self.focus() && self.is_selected(i) {
+                            printer.with_color(ColorStyle::title_primary(), |printer| {
+                                printer.with_effect(Effect::Bold, |printer| {
+                                    self.draw_item(printer, i)
+                                })
+                            });
+                        } else if self.is_selected(i) {
+                            printer.with_effect(Effect::Bold, |printer| self.draw_item(printer, i));
                         } else {
                             self.draw_item(printer, i);
                         }
@@ -761,6 +896,7 @@ impl<T: 'static> View for MultiSelectView<T> {
 struct Item<T> {
     label: String,
     value: Rc<T>,
+    selected: bool,
 }
 
 impl<T> Item<T> {
@@ -768,45 +904,7 @@ impl<T> Item<T> {
         Item {
             label,
             value: Rc::new(value),
+            selected: false,
         }
     }
 }
-
-fn is_numeric(event: &Event) -> bool {
-    match event {
-        Event::Char(c) => if c.is_numeric() {
-            return true;
-        },
-        _ => {}
-    }
-    false
-}
-
-fn is_alphabete(event: &Event) -> bool {
-    match event {
-        Event::Char(c) => if c.is_alphabetic() {
-            return true;
-        },
-        _ => {}
-    }
-    false
-}
-
-fn get_number(seq: &Vec<usize>) -> usize {
-    let mut ans = 0usize;
-    let mut len = seq.len() as u32;
-    for i in seq.into_iter() {
-        ans += i * 10usize.pow(len - 1);
-        len -= 1;
-    }
-    ans
-}
-
-fn should_intercept(event: &Event) -> bool {
-    match event {
-        Event::Char('g') => return true,
-        Event::Char('G') => return true,
-        _ => {}
-    }
-    false
-}
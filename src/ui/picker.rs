@@ -0,0 +1,83 @@
+//! Overlay picking an item among many by typing a fuzzy pattern.
+use std::rc::Rc;
+
+use cursive::event::Key;
+use cursive::traits::{Boxable, Identifiable, Scrollable};
+use cursive::views::{Dialog, EditView, LinearLayout, OnEventView, SelectView};
+use cursive::Cursive;
+
+use crate::utils::fuzzy;
+
+/// Maximum number of items listed at once.
+const MAX_SHOWN: usize = 200;
+
+/// Shows `items` as `(label, value)` pairs, in the given order, keeping the ones whose label
+/// matches the pattern typed. `Up` and `Down` move in the list, `Enter` closes the overlay and
+/// runs `on_pick` with the selected value.
+pub fn pick<T, F>(s: &mut Cursive, title: &str, items: Vec<(String, T)>, on_pick: F)
+where
+    T: Clone + 'static,
+    F: Fn(&mut Cursive, T) + 'static,
+{
+    let items = Rc::new(items);
+    let on_pick = Rc::new(on_pick);
+
+    let mut list = SelectView::<T>::new();
+    fill_list(&mut list, &items, "");
+    let l_pick = on_pick.clone();
+    list.set_on_submit(move |s, value: &T| {
+        s.pop_layer();
+        l_pick(s, value.clone());
+    });
+
+    let e_items = items.clone();
+    let query = EditView::new()
+        .on_edit(move |s, pattern, _| {
+            s.call_on_id("picker/list", |view: &mut SelectView<T>| {
+                fill_list(view, &e_items, pattern)
+            });
+        }).on_submit(move |s, _| {
+            let value = s
+                .call_on_id("picker/list", |view: &mut SelectView<T>| view.selection())
+                .and_then(|value| value);
+            if let Some(value) = value {
+                s.pop_layer();
+                on_pick(s, (*value).clone());
+            }
+        });
+    let query = OnEventView::new(query)
+        .on_pre_event(Key::Down, |s| move_selection::<T>(s, true))
+        .on_pre_event(Key::Up, |s| move_selection::<T>(s, false));
+
+    let layout = LinearLayout::vertical().child(query).child(
+        list.with_id("picker/list")
+            .scrollable()
+            .max_height(15),
+    );
+    s.add_layer(
+        Dialog::around(layout.min_width(60))
+            .title(title)
+            .dismiss_button("Close"),
+    );
+}
+
+fn fill_list<T: Clone + 'static>(view: &mut SelectView<T>, items: &[(String, T)], pattern: &str) {
+    view.clear();
+    let matching = items
+        .iter()
+        .filter(|(label, _)| fuzzy::fuzzy_match(pattern, label).is_some())
+        .take(MAX_SHOWN);
+    for (label, value) in matching {
+        view.add_item(label.as_str(), value.clone());
+    }
+}
+
+fn move_selection<T: 'static>(s: &mut Cursive, down: bool) {
+    s.call_on_id("picker/list", |view: &mut SelectView<T>| {
+        if down {
+            view.select_down(1);
+        } else {
+            view.select_up(1);
+        }
+    });
+}
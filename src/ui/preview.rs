@@ -0,0 +1,240 @@
+//! View of the `preview` pane: lines of styled text, optionally numbered and soft wrapped,
+//! which can be scrolled while the focus stays in the `current` column.
+use std::cmp::min;
+
+use cursive::theme::{ColorStyle, Effect};
+use cursive::vec::Vec2;
+use cursive::view::View;
+use cursive::Printer;
+use unicode_width::UnicodeWidthChar;
+
+/// A run of text drawn with the same style.
+#[derive(Clone, Debug)]
+pub struct Span {
+    pub text: String,
+    pub color: Option<ColorStyle>,
+    pub effect: Option<Effect>,
+}
+
+impl Span {
+    pub fn plain<S: Into<String>>(text: S) -> Self {
+        Self::styled(text, None, None)
+    }
+
+    pub fn styled<S>(text: S, color: Option<ColorStyle>, effect: Option<Effect>) -> Self
+    where
+        S: Into<String>,
+    {
+        Span {
+            text: text.into(),
+            color,
+            effect,
+        }
+    }
+}
+
+/// A line of the preview, made of spans.
+pub type Line = Vec<Span>;
+
+/// A row of the screen: the pieces of a line which fit, as `(column, span, text)`.
+struct Row {
+    line: usize,
+    first: bool,
+    pieces: Vec<(usize, usize, String)>,
+}
+
+pub struct PreviewView {
+    lines: Vec<Line>,
+    /// Lines shown after `lines`, never numbered.
+    footer: Vec<Line>,
+    numbered: bool,
+    wrap: bool,
+    /// First row shown.
+    top: usize,
+    size: Vec2,
+    /// Incremented whenever other lines are shown.
+    generation: usize,
+}
+
+impl Default for PreviewView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PreviewView {
+    pub fn new() -> Self {
+        PreviewView {
+            lines: Vec::new(),
+            footer: Vec::new(),
+            numbered: false,
+            wrap: true,
+            top: 0,
+            size: Vec2::zero(),
+            generation: 0,
+        }
+    }
+
+    /// Shows `lines` from the top, preceded by their number if `numbered` is set.
+    pub fn set_lines(&mut self, lines: Vec<Line>, numbered: bool) {
+        self.lines = lines;
+        self.footer.clear();
+        self.numbered = numbered;
+        self.top = 0;
+        self.generation += 1;
+    }
+
+    /// Replaces the lines shown by the same lines styled differently, keeping the scroll.
+    pub fn restyle(&mut self, lines: Vec<Line>) {
+        self.lines = lines;
+        self.top = min(self.top, self.max_top());
+    }
+
+    /// Shows `footer` after the lines, without numbers, until other lines are shown.
+    pub fn set_footer(&mut self, footer: Vec<Line>) {
+        self.footer = footer;
+        self.top = min(self.top, self.max_top());
+    }
+
+    /// Returns a number which changes whenever `set_lines` shows other lines.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Shows `text` unstyled, without line numbers.
+    pub fn set_message<S: AsRef<str>>(&mut self, text: S) {
+        let lines = text.as_ref().lines().map(|line| vec![Span::plain(line)]).collect();
+        self.set_lines(lines, false);
+    }
+
+    /// Wraps the lines wider than the pane if `wrap` is set, cuts them otherwise.
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
+        self.top = min(self.top, self.max_top());
+    }
+
+    /// Scrolls `count` rows down.
+    pub fn scroll_down(&mut self, count: usize) {
+        self.top = min(self.top.saturating_add(count), self.max_top());
+    }
+
+    /// Scrolls `count` rows up.
+    pub fn scroll_up(&mut self, count: usize) {
+        self.top = self.top.saturating_sub(count);
+    }
+
+    /// Scrolls the least needed for the start of `line` to be shown.
+    pub fn reveal(&mut self, line: usize) {
+        let row = match self.rows().iter().position(|row| row.line == line) {
+            Some(row) => row,
+            None => return,
+        };
+        if row < self.top {
+            self.top = row;
+        } else if row >= self.top + self.size.y {
+            self.top = row + 1 - self.size.y;
+        }
+    }
+
+    /// Returns the size of the pane, zero until it is first laid out.
+    pub fn size(&self) -> Vec2 {
+        self.size
+    }
+
+    /// Returns the number of rows scrolled by half a page.
+    pub fn half_page(&self) -> usize {
+        (self.size.y / 2).max(1)
+    }
+
+    fn max_top(&self) -> usize {
+        self.rows().len().saturating_sub(self.size.y)
+    }
+
+    /// Returns the width of the line numbers, including the space after them.
+    fn gutter(&self) -> usize {
+        if self.numbered {
+            self.lines.len().to_string().len() + 1
+        } else {
+            0
+        }
+    }
+
+    /// Splits the lines into the rows of the screen.
+    fn rows(&self) -> Vec<Row> {
+        let width = self.size.x.saturating_sub(self.gutter()).max(1);
+        let mut rows = Vec::with_capacity(self.lines.len() + self.footer.len());
+        for (index, line) in self.lines.iter().chain(&self.footer).enumerate() {
+            let mut row = Row {
+                line: index,
+                first: true,
+                pieces: Vec::new(),
+            };
+            let mut x = 0;
+            'spans: for (span_index, span) in line.iter().enumerate() {
+                for c in span.text.chars() {
+                    let w = c.width().unwrap_or(0);
+                    if x + w > width {
+                        if !self.wrap {
+                            break 'spans;
+                        }
+                        let next = Row {
+                            line: index,
+                            first: false,
+                            pieces: Vec::new(),
+                        };
+                        rows.push(::std::mem::replace(&mut row, next));
+                        x = 0;
+                    }
+                    match row.pieces.last_mut() {
+                        Some(piece) if piece.1 == span_index => piece.2.push(c),
+                        _ => row.pieces.push((x, span_index, c.to_string())),
+                    }
+                    x += w;
+                }
+            }
+            rows.push(row);
+        }
+        rows
+    }
+}
+
+impl View for PreviewView {
+    fn draw(&self, printer: &Printer) {
+        let gutter = self.gutter();
+        let rows = self.rows();
+        for (y, row) in rows.iter().skip(self.top).take(printer.size.y).enumerate() {
+            if self.numbered && row.first && row.line < self.lines.len() {
+                let number = format!("{:>1$}", row.line + 1, gutter - 1);
+                printer.with_color(ColorStyle::secondary(), |printer| {
+                    printer.print((0, y), &number)
+                });
+            }
+            let line = match self.lines.get(row.line) {
+                Some(line) => line,
+                None => &self.footer[row.line - self.lines.len()],
+            };
+            for (x, span_index, text) in &row.pieces {
+                let span = &line[*span_index];
+                let print = |printer: &Printer| match span.effect {
+                    Some(effect) => {
+                        printer.with_effect(effect, |printer| printer.print((gutter + x, y), text))
+                    }
+                    None => printer.print((gutter + x, y), text),
+                };
+                match span.color {
+                    Some(color) => printer.with_color(color, print),
+                    None => print(printer),
+                }
+            }
+        }
+    }
+
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        constraint
+    }
+
+    fn layout(&mut self, size: Vec2) {
+        self.size = size;
+        self.top = min(self.top, self.max_top());
+    }
+}
@@ -0,0 +1,192 @@
+//! Prompts asking the user for confirmation, a choice, a key or a line of text, either as a popup
+//! dialog or as a single line at the bottom of the screen, according to
+//! `ConfigOptions::show_popup`.
+use std::rc::Rc;
+
+use cursive::direction::Direction;
+use cursive::event::{Event, EventResult, Key};
+use cursive::traits::{Boxable, Identifiable};
+use cursive::view::{Position, View, ViewWrapper};
+use cursive::views::{Dialog, EditView, LayerPosition, LinearLayout, OnEventView, TextView};
+use cursive::Cursive;
+
+/// A choice offered by `choose`: its key, its label and the callback run when it is picked.
+pub type Choice<'a> = (char, &'a str, Rc<dyn Fn(&mut Cursive)>);
+
+/// Callback run with the character typed in `key`.
+type KeyCallback = Rc<dyn Fn(&mut Cursive, char)>;
+
+/// Asks `message` and runs `on_yes` if the user accepts.
+///
+/// In the status line variant, `y` accepts while `n` and `Esc` decline.
+pub fn confirm<F>(s: &mut Cursive, message: &str, popup: bool, on_yes: F)
+where
+    F: Fn(&mut Cursive) + 'static,
+{
+    choose(s, message, popup, vec![('y', "Yes", Rc::new(on_yes))]);
+}
+
+/// Asks `message`, offering one button per choice. Each choice is a key for the status line
+/// variant, a label for the popup variant, and the callback to run when it is picked.
+///
+/// Declining (`n`, `Esc` or the `Cancel` button) simply closes the prompt.
+pub fn choose(
+    s: &mut Cursive,
+    message: &str,
+    popup: bool,
+    choices: Vec<Choice>,
+) {
+    if popup {
+        let mut dialog = Dialog::text(message).title("Confirm");
+        for (_, label, cb) in choices {
+            dialog.add_button(label, move |s| {
+                s.pop_layer();
+                cb(s);
+            });
+        }
+        let view = dialog.dismiss_button("Cancel");
+        let on_key: KeyCallback = Rc::new(|_, _| ());
+        s.add_layer(KeyReader { view, on_key, accepted: Some(Vec::new()) });
+        return;
+    }
+    let keys: Vec<String> = choices
+        .iter()
+        .map(|(key, label, _)| format!("{}: {}", key, label))
+        .collect();
+    let line = TextView::new(format!("{} [{}, n: Cancel]", message, keys.join(", ")));
+    let callbacks: Vec<_> = choices.into_iter().map(|(key, _, cb)| (key, cb)).collect();
+    let mut accepted: Vec<char> = callbacks.iter().map(|(key, _)| *key).collect();
+    accepted.push('n');
+    let on_key: KeyCallback = Rc::new(move |s, key| {
+        if let Some((_, cb)) = callbacks.iter().find(|(k, _)| *k == key) {
+            cb(s);
+        }
+    });
+    let view = LinearLayout::horizontal().child(line);
+    s.add_layer(KeyReader { view, on_key, accepted: Some(accepted) });
+    to_bottom(s);
+}
+
+/// Asks `message` with an editable line holding `initial`, the cursor at byte `cursor`, and
+/// runs `on_submit` with the line once `Enter` is pressed. `Esc` cancels.
+pub fn input<F>(
+    s: &mut Cursive,
+    message: &str,
+    initial: &str,
+    cursor: usize,
+    popup: bool,
+    on_submit: F,
+) where
+    F: Fn(&mut Cursive, &str) + 'static,
+{
+    let on_submit = Rc::new(on_submit);
+    let e_submit = on_submit.clone();
+    let mut edit = EditView::new().content(initial).on_submit(move |s, line| {
+        s.pop_layer();
+        e_submit(s, line);
+    });
+    edit.set_cursor(cursor);
+    let edit = edit.with_id("prompt/input");
+    if popup {
+        let dialog = Dialog::around(edit.min_width(40))
+            .title(message)
+            .button("Ok", move |s| {
+                let line = s
+                    .call_on_id("prompt/input", |view: &mut EditView| view.get_content())
+                    .map(|line| (*line).clone())
+                    .unwrap_or_default();
+                s.pop_layer();
+                on_submit(s, &line);
+            }).dismiss_button("Cancel");
+        s.add_layer(dialog);
+        return;
+    }
+    let line = LinearLayout::horizontal()
+        .child(TextView::new(format!("{} ", message)))
+        .child(edit.full_width());
+    s.add_layer(OnEventView::new(line).on_pre_event(Key::Esc, |s| {
+        s.pop_layer();
+    }));
+    to_bottom(s);
+}
+
+/// Asks `message`, showing `content` along with it, and runs `on_key` with the next character
+/// typed. `Esc` cancels.
+pub fn key<F>(s: &mut Cursive, message: &str, content: &str, popup: bool, on_key: F)
+where
+    F: Fn(&mut Cursive, char) + 'static,
+{
+    let on_key: KeyCallback = Rc::new(on_key);
+    if popup {
+        let dialog = Dialog::text(content).title(message).dismiss_button("Cancel");
+        s.add_layer(KeyReader { view: dialog, on_key, accepted: None });
+        return;
+    }
+    let lines = LinearLayout::vertical()
+        .child(TextView::new(content))
+        .child(TextView::new(message));
+    s.add_layer(KeyReader { view: lines, on_key, accepted: None });
+    to_bottom(s);
+}
+
+/// Wraps a view, closing the layer with the first accepted character typed, or with `Esc`.
+///
+/// Keys not handled by the view are consumed, so they never reach the bindings of the
+/// listing below while the prompt is shown.
+struct KeyReader<V> {
+    view: V,
+    on_key: KeyCallback,
+    /// The characters closing the layer, or `None` for any character.
+    accepted: Option<Vec<char>>,
+}
+
+impl<V: View> ViewWrapper for KeyReader<V> {
+    type V = V;
+
+    fn with_view<F, R>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&Self::V) -> R,
+    {
+        Some(f(&self.view))
+    }
+
+    fn with_view_mut<F, R>(&mut self, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut Self::V) -> R,
+    {
+        Some(f(&mut self.view))
+    }
+
+    fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        match event {
+            Event::Char(c) if self.accepted.as_ref().map_or(true, |keys| keys.contains(&c)) => {
+                let on_key = self.on_key.clone();
+                EventResult::with_cb(move |s| {
+                    s.pop_layer();
+                    on_key(s, c);
+                })
+            }
+            Event::Key(Key::Esc) => EventResult::with_cb(|s| {
+                s.pop_layer();
+            }),
+            Event::Mouse { .. } | Event::Refresh | Event::WindowResize | Event::Exit => {
+                self.view.on_event(event)
+            }
+            event => match self.view.on_event(event) {
+                EventResult::Ignored => EventResult::Consumed(None),
+                result => result,
+            },
+        }
+    }
+
+    fn wrap_take_focus(&mut self, _: Direction) -> bool {
+        true
+    }
+}
+
+/// Moves the front layer to the bottom of the screen.
+fn to_bottom(s: &mut Cursive) {
+    let screen = s.screen_mut();
+    let pos = screen.offset().saturating_add((9000, 9000));
+    screen.reposition_layer(LayerPosition::FromFront(0), Position::absolute(pos));
+}
@@ -1,6 +1,28 @@
 use crate::error::*;
+use crate::utils::jumplist::JumpList;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Icon of the directories in the listings.
+const DIR_ICON: char = '\u{e5ff}';
+/// Icon of the files in the listings.
+const FILE_ICON: char = '\u{e612}';
+
+/// Returns the label of the entry at `path` in the listings: `name` preceded by an icon.
+pub fn label(path: &Path, name: &str) -> String {
+    let icon = if path.is_dir() { DIR_ICON } else { FILE_ICON };
+    format!(" {} {}", icon, name)
+}
+
+/// Entries found by `:find` under `c_view`, listed instead of the entries of `c_view`.
+#[derive(Debug, Clone)]
+pub struct Found {
+    /// The criteria, as typed.
+    pub query: String,
+    pub paths: Vec<PathBuf>,
+    /// Index of the focused entry.
+    pub focused: usize,
+}
 
 /// Struct to hold a collection of 3 views, according to miller's columns. First, being the
 /// previous directory, then second directory, followed by preview window.
@@ -17,6 +39,10 @@ pub struct Tab {
     // Selected
     // TODO Currently, based on index, need to change to PathBuf
     pub focused: HashMap<PathBuf, usize>,
+    /// Directories visited in the tab, walked with `C-o` and `C-i`.
+    pub jumps: JumpList,
+    /// Results of `:find`, shown until the tab leaves them.
+    pub found: Option<Found>,
     // pub p_focused: usize,
     // pub c_focused: Option<usize>,
     // preview_selected: Vec<usize>,
@@ -46,11 +72,22 @@ impl Tab {
             c_view,
 
             focused: HashMap::new(),
+            jumps: JumpList::new(path.to_path_buf()),
+            found: None,
             // p_focused: 0,
             // c_focused: None,
         })
     }
 
+    /// Shows `path` in the tab, keeping the focused entries and the jump list.
+    pub fn change_dir(&mut self, path: &PathBuf) -> Result<()> {
+        let tab = Tab::from(self.title, path)?;
+        self.p_view = tab.p_view;
+        self.c_view = tab.c_view;
+        self.found = None;
+        Ok(())
+    }
+
     pub fn go_back(&mut self) {
         let temp_path = PathBuf::from(&self.p_view);
         match temp_path.to_str() {
@@ -72,11 +109,14 @@ impl Tab {
             }
             None => {}
         }
+        self.jumps.visit(self.c_view.clone());
         debug!("{:?}, {:?}", self.p_view, self.c_view);
     }
 
     pub fn go_forward(&mut self, path: PathBuf) {
+        self.found = None;
         self.c_view = PathBuf::from(&path);
         self.p_view = path.parent().unwrap().to_path_buf();
+        self.jumps.visit(path);
     }
 }
@@ -0,0 +1,156 @@
+//! Bookmarks naming directories with a single character, saved to
+//! `$XDG_CONFIG_HOME/marcos/bookmarks.toml`.
+use std::collections::BTreeMap;
+use std::fs as stdfs;
+use std::path::{Path, PathBuf};
+
+use dirs;
+use toml;
+
+use crate::error::*;
+
+/// Key of the bookmark holding the directory shown before the last jump.
+pub const LAST: char = '`';
+
+/// Bookmarks created by the user, along with the ones predefined in `config.toml`.
+///
+/// A bookmark created by the user hides a predefined bookmark with the same key.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Bookmarks {
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    #[serde(skip)]
+    predefined: BTreeMap<char, PathBuf>,
+    #[serde(default, flatten)]
+    marks: BTreeMap<String, PathBuf>,
+}
+
+impl Bookmarks {
+    /// Loads the bookmarks from the config directory, or starts with none if there are none.
+    /// `predefined` holds the bookmarks of the `[bookmarks]` table of `config.toml`; invalid
+    /// keys are skipped.
+    pub fn load(predefined: Vec<(String, PathBuf)>) -> Result<Self> {
+        let config_path: PathBuf = dirs::config_dir().ok_or(ErrorKind::DirNotFound {
+            dirname: String::from("CONFIG_DIR"),
+        })?;
+        let path = config_path.join("marcos").join("bookmarks.toml");
+        let mut bookmarks = if path.exists() {
+            let content = stdfs::read_to_string(&path)?;
+            toml::from_str(&content).unwrap_or_else(|e| {
+                warn!("Discarding unreadable bookmarks {:?}: {}", path, e);
+                Bookmarks::default()
+            })
+        } else {
+            Bookmarks::default()
+        };
+        let marks = ::std::mem::take(&mut bookmarks.marks);
+        bookmarks.marks = marks
+            .into_iter()
+            .filter(|(key, _)| match single(key) {
+                Some(c) => c == LAST || check_key(c).is_ok(),
+                None => false,
+            }).collect();
+        for (key, dir) in predefined {
+            match single(&key) {
+                Some(c) if check_key(c).is_ok() => {
+                    bookmarks.predefined.insert(c, dir);
+                }
+                _ => warn!("Skipping invalid bookmark {:?} of config.toml", key),
+            }
+        }
+        bookmarks.path = Some(path);
+        Ok(bookmarks)
+    }
+
+    /// Saves the bookmarks created by the user to the file they were loaded from.
+    pub fn save(&self) -> Result<()> {
+        if let Some(ref path) = self.path {
+            if let Some(parent) = path.parent() {
+                stdfs::create_dir_all(parent)?;
+            }
+            let content = toml::to_string(self).map_err(|e| ErrorKind::InvalidOperation {
+                reason: format!("Cannot save bookmarks: {}", e),
+            })?;
+            stdfs::write(path, content)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the directory of the bookmark `key`.
+    pub fn get(&self, key: char) -> Option<&Path> {
+        self.marks
+            .get(&key.to_string())
+            .or_else(|| self.predefined.get(&key))
+            .map(|path| path.as_path())
+    }
+
+    /// Sets the bookmark `key` to `dir`.
+    pub fn set(&mut self, key: char, dir: &Path) -> Result<()> {
+        check_key(key)?;
+        self.marks.insert(key.to_string(), dir.to_path_buf());
+        self.save()
+    }
+
+    /// Remembers `dir` as the directory shown before the last jump, i.e. bookmark `` ` ``.
+    pub fn set_last(&mut self, dir: &Path) -> Result<()> {
+        if self.get(LAST) == Some(dir) {
+            return Ok(());
+        }
+        self.marks.insert(LAST.to_string(), dir.to_path_buf());
+        self.save()
+    }
+
+    /// Deletes the bookmark `key`. Predefined bookmarks can only be deleted from `config.toml`.
+    pub fn remove(&mut self, key: char) -> Result<()> {
+        if self.marks.remove(&key.to_string()).is_some() {
+            return self.save();
+        }
+        let reason = if self.predefined.contains_key(&key) {
+            format!("Bookmark {} is defined in config.toml", key)
+        } else {
+            format!("No bookmark {}", key)
+        };
+        Err(ErrorKind::InvalidOperation { reason }.into())
+    }
+
+    /// Returns every bookmark ordered by key, telling whether it is predefined.
+    pub fn list(&self) -> Vec<(char, PathBuf, bool)> {
+        let mut list: BTreeMap<char, (PathBuf, bool)> = self
+            .predefined
+            .iter()
+            .map(|(key, dir)| (*key, (dir.clone(), true)))
+            .collect();
+        for (key, dir) in &self.marks {
+            if let Some(c) = single(key) {
+                list.insert(c, (dir.clone(), false));
+            }
+        }
+        list.into_iter()
+            .map(|(key, (dir, predefined))| (key, dir, predefined))
+            .collect()
+    }
+}
+
+/// Checks that `key` can name a bookmark: any visible character but `` ` ``.
+pub fn check_key(key: char) -> Result<()> {
+    if key == LAST {
+        return Err(ErrorKind::InvalidOperation {
+            reason: format!("{} is the last position, it cannot be set", LAST),
+        }.into());
+    }
+    if key.is_whitespace() || key.is_control() {
+        return Err(ErrorKind::InvalidOperation {
+            reason: format!("Invalid bookmark key {:?}", key),
+        }.into());
+    }
+    Ok(())
+}
+
+/// Returns the character of `key` if it is made of exactly one.
+fn single(key: &str) -> Option<char> {
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
@@ -1,7 +1,17 @@
 //! Funtions to help in assisting filter of entries
 
+use std::fs as stdfs;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use regex::Regex;
+use users::get_user_by_name;
 use walkdir::DirEntry;
 
+use crate::error::*;
+
 /// Returns true if entry is hidden, irrespective of type(file or directory)
 pub fn is_hidden(entry: &DirEntry) -> bool {
     entry
@@ -10,3 +20,357 @@ pub fn is_hidden(entry: &DirEntry) -> bool {
         .map(|s| s.starts_with("."))
         .unwrap_or(false)
 }
+
+/// Returns true if entry should be listed, i.e. it is not hidden or hidden entries are shown.
+pub fn is_visible(entry: &DirEntry, show_hidden: bool) -> bool {
+    show_hidden || !is_hidden(entry)
+}
+
+/// Type of an entry, as matched by `type:`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EntryType {
+    File,
+    Dir,
+    Link,
+}
+
+/// A condition on an entry, written as:
+///
+/// * a glob matched against the file name, e.g. `*.log` (`*`, `?` and `[...]` are supported),
+/// * `re:<regex>`, a regular expression searched in the file name,
+/// * `type:<f|d|l>`, for files, directories or symbolic links,
+/// * `larger:<size>` or `smaller:<size>`, for files, e.g. `larger:10M`,
+/// * `newer:<age>` or `older:<age>`, comparing the modification time, e.g. `newer:2d`,
+/// * `owner:<user>`, a user name or id,
+/// * `perm:<mode>`, octal permission bits: `perm:644` matches them exactly, `perm:-111` matches
+///   entries with all of them set and `perm:/022` entries with any of them set.
+///
+/// Sizes are in bytes unless suffixed by `k`, `M`, `G` or `T` (powers of 1024). Ages are
+/// in seconds unless suffixed by `s`, `m`, `h`, `d` or `w`.
+#[derive(Clone, Debug)]
+pub enum Criterion {
+    Glob(Regex),
+    Regex(Regex),
+    Type(EntryType),
+    Larger(u64),
+    Smaller(u64),
+    Newer(Duration),
+    Older(Duration),
+    Owner(u32),
+    Perm(Perm),
+}
+
+/// Permission bits matched by `perm:`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Perm {
+    Exact(u32),
+    All(u32),
+    Any(u32),
+}
+
+impl PartialEq for Criterion {
+    fn eq(&self, other: &Criterion) -> bool {
+        match (self, other) {
+            (Criterion::Glob(a), Criterion::Glob(b))
+            | (Criterion::Regex(a), Criterion::Regex(b)) => a.as_str() == b.as_str(),
+            (Criterion::Type(a), Criterion::Type(b)) => a == b,
+            (Criterion::Larger(a), Criterion::Larger(b))
+            | (Criterion::Smaller(a), Criterion::Smaller(b)) => a == b,
+            (Criterion::Newer(a), Criterion::Newer(b))
+            | (Criterion::Older(a), Criterion::Older(b)) => a == b,
+            (Criterion::Owner(a), Criterion::Owner(b)) => a == b,
+            (Criterion::Perm(a), Criterion::Perm(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Criterion {
+    type Err = Error;
+
+    fn from_str(arg: &str) -> Result<Self> {
+        let (kind, value) = match arg.find(':') {
+            Some(i) => (&arg[..i], &arg[i + 1..]),
+            None => ("", arg),
+        };
+        match kind {
+            "re" => Regex::new(value)
+                .map(Criterion::Regex)
+                .map_err(|e| invalid(&format!("Invalid pattern: {}", e))),
+            "type" => match value {
+                "f" | "file" => Ok(Criterion::Type(EntryType::File)),
+                "d" | "dir" => Ok(Criterion::Type(EntryType::Dir)),
+                "l" | "link" => Ok(Criterion::Type(EntryType::Link)),
+                _ => Err(invalid(&format!("Unknown type: {} (f, d, l)", value))),
+            },
+            "larger" => parse_size(value).map(Criterion::Larger),
+            "smaller" => parse_size(value).map(Criterion::Smaller),
+            "newer" => parse_age(value).map(Criterion::Newer),
+            "older" => parse_age(value).map(Criterion::Older),
+            "owner" => match value.parse::<u32>() {
+                Ok(uid) => Ok(Criterion::Owner(uid)),
+                Err(_) => get_user_by_name(value)
+                    .map(|user| Criterion::Owner(user.uid()))
+                    .ok_or_else(|| invalid(&format!("Unknown user: {}", value))),
+            },
+            "perm" => parse_perm(value).map(Criterion::Perm),
+            // Not a known prefix, so the colon is part of the glob.
+            _ => Ok(Criterion::Glob(glob(arg)?)),
+        }
+    }
+}
+
+impl Criterion {
+    /// Returns `true` if the entry at `path` satisfies the condition. Entries whose metadata
+    /// cannot be read only match by name.
+    pub fn matches(&self, path: &Path) -> bool {
+        let name = || {
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+        match self {
+            Criterion::Glob(regex) | Criterion::Regex(regex) => regex.is_match(&name()),
+            Criterion::Type(EntryType::Link) => stdfs::symlink_metadata(path)
+                .map(|meta| meta.file_type().is_symlink())
+                .unwrap_or(false),
+            Criterion::Type(EntryType::Dir) => path.is_dir(),
+            Criterion::Type(EntryType::File) => path.is_file(),
+            Criterion::Larger(size) => file_size(path).map_or(false, |len| len > *size),
+            Criterion::Smaller(size) => file_size(path).map_or(false, |len| len < *size),
+            Criterion::Newer(age) => modified_since(path).map_or(false, |a| a < *age),
+            Criterion::Older(age) => modified_since(path).map_or(false, |a| a > *age),
+            Criterion::Owner(uid) => path.metadata().map_or(false, |meta| meta.uid() == *uid),
+            Criterion::Perm(perm) => path.metadata().map_or(false, |meta| {
+                let mode = meta.mode() & 0o7777;
+                match *perm {
+                    Perm::Exact(bits) => mode == bits,
+                    Perm::All(bits) => mode & bits == bits,
+                    Perm::Any(bits) => mode & bits != 0,
+                }
+            }),
+        }
+    }
+}
+
+/// Translates a glob into a regular expression matching whole names. `*` matches
+/// any run of characters, `/` included.
+pub fn glob(pattern: &str) -> Result<Regex> {
+    let mut source = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => source.push_str(".*"),
+            '?' => source.push('.'),
+            '[' => {
+                source.push('[');
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                    source.push('^');
+                }
+                loop {
+                    match chars.next() {
+                        Some(']') => break,
+                        Some('\\') => source.push_str("\\\\"),
+                        Some(c) => source.push(c),
+                        None => return Err(invalid(&format!("Unterminated [ in {}", pattern))),
+                    }
+                }
+                source.push(']');
+            }
+            c => source.push_str(&::regex::escape(&c.to_string())),
+        }
+    }
+    source.push('$');
+    Regex::new(&source).map_err(|e| invalid(&format!("Invalid pattern: {}", e)))
+}
+
+/// Parses a size such as `512`, `4k` or `10M`.
+pub fn parse_size(value: &str) -> Result<u64> {
+    let (number, unit) = split_unit(value);
+    let shift = match unit {
+        "" | "b" | "B" => 0,
+        "k" | "K" => 10,
+        "m" | "M" => 20,
+        "g" | "G" => 30,
+        "t" | "T" => 40,
+        _ => return Err(invalid(&format!("Invalid size: {}", value))),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(1 << shift))
+        .ok_or_else(|| invalid(&format!("Invalid size: {}", value)))
+}
+
+/// Parses an age such as `90`, `30m`, `2d` or `1w`.
+pub fn parse_age(value: &str) -> Result<Duration> {
+    let (number, unit) = split_unit(value);
+    let seconds = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(invalid(&format!("Invalid age: {} (s, m, h, d, w)", value))),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(seconds))
+        .map(Duration::from_secs)
+        .ok_or_else(|| invalid(&format!("Invalid age: {}", value)))
+}
+
+/// Parses permission bits such as `644`, `-111` or `/022`.
+fn parse_perm(value: &str) -> Result<Perm> {
+    let (kind, digits): (fn(u32) -> Perm, &str) = if let Some(digits) = value.strip_prefix('-') {
+        (Perm::All, digits)
+    } else if let Some(digits) = value.strip_prefix('/') {
+        (Perm::Any, digits)
+    } else {
+        (Perm::Exact, value)
+    };
+    match u32::from_str_radix(digits, 8) {
+        Ok(bits) if !digits.is_empty() && bits <= 0o7777 => Ok(kind(bits)),
+        _ => Err(invalid(&format!("Invalid permissions: {} (octal, e.g. 644)", value))),
+    }
+}
+
+/// Splits `value` into its leading digits and the unit which follows them.
+fn split_unit(value: &str) -> (&str, &str) {
+    let digits = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    (&value[..digits], &value[digits..])
+}
+
+/// Returns the size of the file at `path`, or `None` if it is not a file.
+fn file_size(path: &Path) -> Option<u64> {
+    let meta = path.metadata().ok()?;
+    if meta.is_file() {
+        Some(meta.len())
+    } else {
+        None
+    }
+}
+
+/// Returns the time elapsed since the entry at `path` was modified.
+fn modified_since(path: &Path) -> Option<Duration> {
+    let modified = path.symlink_metadata().ok()?.modified().ok()?;
+    // Entries modified in the future are as new as can be.
+    Some(SystemTime::now().duration_since(modified).unwrap_or(Duration::from_secs(0)))
+}
+
+fn invalid(reason: &str) -> Error {
+    ErrorKind::InvalidCommand {
+        reason: reason.to_string(),
+    }.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs_match_whole_names() {
+        let regex = glob("*.log").unwrap();
+        assert!(regex.is_match("error.log"));
+        assert!(!regex.is_match("error.log.1"));
+        let regex = glob("file?.[ch]").unwrap();
+        assert!(regex.is_match("file1.c"));
+        assert!(regex.is_match("file2.h"));
+        assert!(!regex.is_match("file10.c"));
+        assert!(glob("a.b").unwrap().is_match("a.b"));
+        assert!(!glob("a.b").unwrap().is_match("axb"));
+    }
+
+    #[test]
+    fn globs_negate_classes_and_reject_unterminated_ones() {
+        let regex = glob("[!abc]*").unwrap();
+        assert!(regex.is_match("dog"));
+        assert!(!regex.is_match("cat"));
+        assert!(glob("[abc").is_err());
+        assert!(glob("x[!").is_err());
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("4k").unwrap(), 4 << 10);
+        assert_eq!(parse_size("10M").unwrap(), 10 << 20);
+        assert_eq!(parse_size("1T").unwrap(), 1 << 40);
+        assert!(parse_size("").is_err());
+        assert!(parse_size("3x").is_err());
+        // 2^24 TiB does not fit in 64 bits.
+        assert!(parse_size("16777216T").is_err());
+        assert!(parse_size("99999999999999999999").is_err());
+    }
+
+    #[test]
+    fn parses_ages() {
+        assert_eq!(parse_age("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_age("30m").unwrap(), Duration::from_secs(30 * 60));
+        assert_eq!(parse_age("2d").unwrap(), Duration::from_secs(2 * 24 * 60 * 60));
+        assert_eq!(parse_age("1w").unwrap(), Duration::from_secs(7 * 24 * 60 * 60));
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("5y").is_err());
+        assert!(parse_age("99999999999999999w").is_err());
+    }
+
+    #[test]
+    fn parses_permissions() {
+        assert_eq!(parse_perm("644").unwrap(), Perm::Exact(0o644));
+        assert_eq!(parse_perm("-111").unwrap(), Perm::All(0o111));
+        assert_eq!(parse_perm("/022").unwrap(), Perm::Any(0o022));
+        assert!(parse_perm("-").is_err());
+        assert!(parse_perm("/").is_err());
+        assert!(parse_perm("789").is_err());
+        assert!(parse_perm("17777").is_err());
+    }
+
+    #[test]
+    fn parses_criteria() {
+        assert_eq!("type:d".parse::<Criterion>().unwrap(), Criterion::Type(EntryType::Dir));
+        assert_eq!("larger:1k".parse::<Criterion>().unwrap(), Criterion::Larger(1024));
+        assert_eq!("perm:/022".parse::<Criterion>().unwrap(), Criterion::Perm(Perm::Any(0o22)));
+        assert_eq!("owner:0".parse::<Criterion>().unwrap(), Criterion::Owner(0));
+        assert_eq!(
+            "re:^a.*z$".parse::<Criterion>().unwrap(),
+            Criterion::Regex(Regex::new("^a.*z$").unwrap())
+        );
+        assert!("type:x".parse::<Criterion>().is_err());
+        assert!("re:(".parse::<Criterion>().is_err());
+    }
+
+    #[test]
+    fn keeps_unknown_prefixes_in_the_glob() {
+        let criterion = "x:*.txt".parse::<Criterion>().unwrap();
+        assert_eq!(criterion, Criterion::Glob(glob("x:*.txt").unwrap()));
+        assert!(criterion.matches(Path::new("/tmp/x:notes.txt")));
+        assert!(!criterion.matches(Path::new("/tmp/notes.txt")));
+    }
+
+    #[test]
+    fn matches_permissions_and_types() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = ::std::env::temp_dir().join(format!("marcos-filter-{}", ::std::process::id()));
+        stdfs::create_dir_all(&dir).unwrap();
+        let file = dir.join("script");
+        stdfs::write(&file, b"#!/bin/sh\n").unwrap();
+        stdfs::set_permissions(&file, stdfs::Permissions::from_mode(0o744)).unwrap();
+
+        assert!(Criterion::Perm(Perm::Exact(0o744)).matches(&file));
+        assert!(!Criterion::Perm(Perm::All(0o111)).matches(&file));
+        assert!(Criterion::Perm(Perm::Any(0o111)).matches(&file));
+        assert!(!Criterion::Perm(Perm::Any(0o022)).matches(&file));
+        assert!(Criterion::Type(EntryType::File).matches(&file));
+        assert!(Criterion::Type(EntryType::Dir).matches(&dir));
+        assert!(Criterion::Smaller(1024).matches(&file));
+        assert!(!Criterion::Larger(1024).matches(&file));
+        assert!(Criterion::Newer(Duration::from_secs(60 * 60)).matches(&file));
+
+        stdfs::remove_dir_all(&dir).unwrap();
+    }
+}
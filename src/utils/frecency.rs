@@ -0,0 +1,271 @@
+//! Database of the visited directories, ranked by frecency, i.e. by how often and how recently
+//! they were visited, and saved to `$XDG_DATA_HOME/marcos/frecency.toml`.
+use std::fs as stdfs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use dirs;
+use regex::Regex;
+use toml;
+
+use crate::error::*;
+use crate::utils::filter;
+
+/// Once the ranks add up to more than this, every rank is aged.
+const MAX_TOTAL_RANK: f64 = 9000.0;
+/// Factor applied to every rank when aging. Entries falling below a rank of 1 are forgotten.
+const AGING: f64 = 0.99;
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+/// A visited directory, with the number of visits (aged) and the time of the last one.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Entry {
+    path: PathBuf,
+    rank: f64,
+    /// Seconds since the epoch.
+    time: u64,
+}
+
+/// The visited directories, along with the patterns of the directories never recorded.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Frecency {
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    #[serde(skip)]
+    exclude: Vec<Regex>,
+    #[serde(default)]
+    dirs: Vec<Entry>,
+}
+
+impl Frecency {
+    /// Loads the database from the data directory, or starts an empty one if there is none.
+    ///
+    /// Directories matching any glob of `exclude` (e.g. `/tmp/*` or `~/.cache*`) are neither
+    /// recorded nor suggested. Invalid globs are skipped.
+    pub fn load(exclude: &[String]) -> Result<Self> {
+        let data_path: PathBuf = dirs::data_dir().ok_or(ErrorKind::DirNotFound {
+            dirname: String::from("DATA_DIR"),
+        })?;
+        let path = data_path.join("marcos").join("frecency.toml");
+        let mut frecency = if path.exists() {
+            let content = stdfs::read_to_string(&path)?;
+            toml::from_str(&content).unwrap_or_else(|e| {
+                warn!("Discarding unreadable frecency database {:?}: {}", path, e);
+                Frecency::default()
+            })
+        } else {
+            Frecency::default()
+        };
+        let home = dirs::home_dir().map(|home| home.to_string_lossy().into_owned());
+        for pattern in exclude {
+            let pattern = match home {
+                Some(ref home) if pattern == "~" || pattern.starts_with("~/") => {
+                    format!("{}{}", home, &pattern[1..])
+                }
+                _ => pattern.clone(),
+            };
+            match filter::glob(&pattern) {
+                Ok(regex) => frecency.exclude.push(regex),
+                Err(e) => warn!("Skipping frecency exclusion {:?}: {}", pattern, e),
+            }
+        }
+        frecency.path = Some(path);
+        Ok(frecency)
+    }
+
+    /// Saves the database to the file it was loaded from.
+    pub fn save(&self) -> Result<()> {
+        if let Some(ref path) = self.path {
+            if let Some(parent) = path.parent() {
+                stdfs::create_dir_all(parent)?;
+            }
+            let content = toml::to_string(self).map_err(|e| ErrorKind::InvalidOperation {
+                reason: format!("Cannot save frecency database: {}", e),
+            })?;
+            stdfs::write(path, content)?;
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if `dir` matches an exclusion pattern.
+    pub fn is_excluded(&self, dir: &Path) -> bool {
+        let dir = dir.to_string_lossy();
+        self.exclude.iter().any(|regex| regex.is_match(&dir))
+    }
+
+    /// Records a visit to `dir`, unless it is excluded, aging the ranks if they grew too high.
+    pub fn visit(&mut self, dir: &Path) -> Result<()> {
+        if self.is_excluded(dir) {
+            return Ok(());
+        }
+        let time = now();
+        match self.dirs.iter_mut().find(|entry| entry.path == dir) {
+            Some(entry) => {
+                entry.rank += 1.0;
+                entry.time = time;
+            }
+            None => self.dirs.push(Entry {
+                path: dir.to_path_buf(),
+                rank: 1.0,
+                time,
+            }),
+        }
+        let total: f64 = self.dirs.iter().map(|entry| entry.rank).sum();
+        if total > MAX_TOTAL_RANK {
+            for entry in &mut self.dirs {
+                entry.rank *= AGING;
+            }
+            // The directory just visited is kept, however low its rank.
+            self.dirs.retain(|entry| entry.rank >= 1.0 || entry.path == dir);
+        }
+        self.save()
+    }
+
+    /// Returns the existing directories which are not excluded, best ranked first.
+    pub fn ranked(&self) -> Vec<PathBuf> {
+        let now = now();
+        let mut ranked: Vec<(f64, &Path)> = self
+            .dirs
+            .iter()
+            .filter(|entry| entry.path.is_dir() && !self.is_excluded(&entry.path))
+            .map(|entry| (score(entry, now), entry.path.as_path()))
+            .collect();
+        ranked.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(::std::cmp::Ordering::Equal));
+        ranked.into_iter().map(|(_, path)| path.to_path_buf()).collect()
+    }
+
+    /// Returns the ranked directories whose path holds every fragment, in order. Fragments are
+    /// matched ignoring case unless one of them has an uppercase letter.
+    pub fn find(&self, fragments: &[String]) -> Vec<PathBuf> {
+        let ignore_case = !fragments.iter().any(|f| f.chars().any(char::is_uppercase));
+        let fragments: Vec<String> = fragments
+            .iter()
+            .map(|f| if ignore_case { f.to_lowercase() } else { f.clone() })
+            .collect();
+        self.ranked()
+            .into_iter()
+            .filter(|dir| {
+                let mut path = dir.to_string_lossy().into_owned();
+                if ignore_case {
+                    path = path.to_lowercase();
+                }
+                let mut rest = path.as_str();
+                fragments.iter().all(|fragment| match rest.find(fragment.as_str()) {
+                    Some(i) => {
+                        rest = &rest[i + fragment.len()..];
+                        true
+                    }
+                    None => false,
+                })
+            }).collect()
+    }
+}
+
+/// Weighs the rank of `entry` by how recently it was visited.
+fn score(entry: &Entry, now: u64) -> f64 {
+    let elapsed = now.saturating_sub(entry.time);
+    let weight = if elapsed < HOUR {
+        4.0
+    } else if elapsed < DAY {
+        2.0
+    } else if elapsed < WEEK {
+        0.5
+    } else {
+        0.25
+    };
+    entry.rank * weight
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, rank: f64, time: u64) -> Entry {
+        Entry {
+            path: PathBuf::from(path),
+            rank,
+            time,
+        }
+    }
+
+    #[test]
+    fn weighs_ranks_by_recency() {
+        let now = 10 * WEEK;
+        assert_eq!(score(&entry("/a", 2.0, now), now), 8.0);
+        assert_eq!(score(&entry("/a", 2.0, now - 2 * HOUR), now), 4.0);
+        assert_eq!(score(&entry("/a", 2.0, now - 2 * DAY), now), 1.0);
+        assert_eq!(score(&entry("/a", 2.0, now - 2 * WEEK), now), 0.5);
+        // Visits in the future count as recent ones.
+        assert_eq!(score(&entry("/a", 2.0, now + DAY), now), 8.0);
+    }
+
+    #[test]
+    fn counts_visits() {
+        let mut frecency = Frecency::default();
+        frecency.visit(Path::new("/a")).unwrap();
+        frecency.visit(Path::new("/b")).unwrap();
+        frecency.visit(Path::new("/a")).unwrap();
+        assert_eq!(frecency.dirs.len(), 2);
+        assert_eq!(frecency.dirs[0].rank, 2.0);
+        assert_eq!(frecency.dirs[1].rank, 1.0);
+    }
+
+    #[test]
+    fn ages_ranks_and_forgets_the_lowest() {
+        let mut frecency = Frecency::default();
+        frecency.dirs.push(entry("/busy", MAX_TOTAL_RANK, 0));
+        frecency.dirs.push(entry("/rare", 1.0, 0));
+        frecency.visit(Path::new("/new")).unwrap();
+
+        assert_eq!(frecency.dirs.len(), 2);
+        assert_eq!(frecency.dirs[0].path, Path::new("/busy"));
+        assert!((frecency.dirs[0].rank - MAX_TOTAL_RANK * AGING).abs() < 1e-9);
+        // The directory just visited is kept even though its rank fell below 1.
+        assert_eq!(frecency.dirs[1].path, Path::new("/new"));
+        assert!(frecency.dirs[1].rank < 1.0);
+    }
+
+    #[test]
+    fn skips_excluded_directories() {
+        let mut frecency = Frecency::default();
+        frecency.exclude.push(filter::glob("/tmp/*").unwrap());
+        frecency.visit(Path::new("/tmp/build")).unwrap();
+        frecency.visit(Path::new("/srv")).unwrap();
+        assert_eq!(frecency.dirs.len(), 1);
+        assert!(frecency.is_excluded(Path::new("/tmp/build/deep")));
+        assert!(!frecency.is_excluded(Path::new("/srv")));
+    }
+
+    #[test]
+    fn ranks_and_finds_existing_directories() {
+        let root = ::std::env::temp_dir().join(format!("marcos-frecency-{}", ::std::process::id()));
+        let old = root.join("Projects").join("old");
+        let new = root.join("projects").join("new");
+        stdfs::create_dir_all(&old).unwrap();
+        stdfs::create_dir_all(&new).unwrap();
+        let now = now();
+        let mut frecency = Frecency::default();
+        frecency.dirs.push(entry(&old.to_string_lossy(), 10.0, now - 2 * WEEK));
+        frecency.dirs.push(entry(&new.to_string_lossy(), 1.0, now));
+        frecency.dirs.push(entry(&root.join("gone").to_string_lossy(), 50.0, now));
+
+        assert_eq!(frecency.ranked(), vec![new.clone(), old.clone()]);
+        let fragments = |f: &[&str]| f.iter().map(|f| f.to_string()).collect::<Vec<String>>();
+        assert_eq!(frecency.find(&fragments(&["proj"])), vec![new.clone(), old.clone()]);
+        assert_eq!(frecency.find(&fragments(&["Proj"])), vec![old.clone()]);
+        assert_eq!(frecency.find(&fragments(&["proj", "old"])), vec![old.clone()]);
+        assert!(frecency.find(&fragments(&["old", "proj"])).is_empty());
+
+        stdfs::remove_dir_all(&root).unwrap();
+    }
+}
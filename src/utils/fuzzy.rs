@@ -0,0 +1,159 @@
+//! Fuzzy matching of a pattern against candidates, as typed in the fuzzy overlays.
+
+/// Bonus for a character matched right after the previous one.
+const CONSECUTIVE: i64 = 16;
+/// Bonus for a character matched at the start of a word, e.g. after `/` or `_`.
+const WORD_START: i64 = 8;
+/// Penalty for each character skipped between two matched characters.
+const GAP: i64 = 1;
+
+/// Matches `pattern` against `candidate`: every character of the pattern must appear in the
+/// candidate, in order. The match ignores case unless the pattern has an uppercase letter.
+///
+/// Among the ways the pattern can be matched, the best scoring one is kept, so that `fb`
+/// matches the `fb` at the end of `foo_bar_fb` rather than the `f` and `b` of `foo_bar`.
+///
+/// Returns a score, higher for better matches, along with the byte offsets of the matched
+/// characters of the candidate, or `None` if the pattern does not match.
+pub fn fuzzy_match(pattern: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+    let ignore_case = !pattern.chars().any(char::is_uppercase);
+    let fold = |c: char| if ignore_case { c.to_lowercase().next().unwrap_or(c) } else { c };
+    let wanted: Vec<char> = pattern
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(fold)
+        .collect();
+    let chars: Vec<(usize, char)> = candidate.char_indices().collect();
+    let folded: Vec<char> = chars.iter().map(|&(_, c)| fold(c)).collect();
+    if !is_subsequence(&wanted, &folded) {
+        return None;
+    }
+    if wanted.is_empty() {
+        return Some((0, Vec::new()));
+    }
+    // Score of a character matched at each index, before the bonus or penalty depending on
+    // where the previous one was matched.
+    let base: Vec<i64> = (0..chars.len())
+        .map(|index| {
+            let word_start = index == 0 || is_separator(chars[index - 1].1);
+            1 + if word_start { WORD_START } else { 0 }
+        }).collect();
+
+    // best[i][j] is the best score of the first `i + 1` pattern characters, the last one
+    // being matched at index `j`, and from[i][j] the index where the previous one was.
+    let mut best: Vec<Vec<Option<i64>>> = Vec::with_capacity(wanted.len());
+    let mut from: Vec<Vec<usize>> = Vec::with_capacity(wanted.len());
+    best.push(
+        (0..chars.len())
+            .map(|j| if folded[j] == wanted[0] { Some(base[j]) } else { None })
+            .collect(),
+    );
+    from.push(vec![0; chars.len()]);
+    for i in 1..wanted.len() {
+        let previous = &best[i - 1];
+        let mut row = vec![None; chars.len()];
+        let mut links = vec![0; chars.len()];
+        // Best of `previous[k] + GAP * k` over the indices `k` at least two before `j`, so
+        // that the gap penalty up to `j` is subtracted once.
+        let mut gapped: Option<(i64, usize)> = None;
+        for j in 1..chars.len() {
+            if j >= 2 {
+                if let Some(score) = previous[j - 2] {
+                    let value = score + GAP * (j - 2) as i64;
+                    if gapped.map_or(true, |(best, _)| value > best) {
+                        gapped = Some((value, j - 2));
+                    }
+                }
+            }
+            if folded[j] != wanted[i] {
+                continue;
+            }
+            let consecutive = previous[j - 1].map(|score| (score + CONSECUTIVE, j - 1));
+            let gapped = gapped.map(|(value, k)| (value - GAP * (j - 1) as i64, k));
+            let link = match (consecutive, gapped) {
+                (Some(a), Some(b)) => Some(if b.0 > a.0 { b } else { a }),
+                (a, b) => a.or(b),
+            };
+            if let Some((score, k)) = link {
+                row[j] = Some(score + base[j]);
+                links[j] = k;
+            }
+        }
+        best.push(row);
+        from.push(links);
+    }
+
+    let last = wanted.len() - 1;
+    let (score, mut index) = best[last]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, score)| score.map(|score| (score, j)))
+        .fold(None, |acc: Option<(i64, usize)>, (score, j)| match acc {
+            Some((best, _)) if best >= score => acc,
+            _ => Some((score, j)),
+        })?;
+    let mut positions = vec![0; wanted.len()];
+    for i in (0..wanted.len()).rev() {
+        positions[i] = chars[index].0;
+        index = from[i][index];
+    }
+    Some((score, positions))
+}
+
+/// Returns `true` if every character of `wanted` appears in `chars`, in order.
+fn is_subsequence(wanted: &[char], chars: &[char]) -> bool {
+    let mut rest = chars.iter();
+    wanted.iter().all(|w| rest.any(|c| c == w))
+}
+
+fn is_separator(c: char) -> bool {
+    c == '/' || c == '_' || c == '-' || c == '.' || c.is_whitespace()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(pattern: &str, candidate: &str) -> i64 {
+        fuzzy_match(pattern, candidate).unwrap().0
+    }
+
+    #[test]
+    fn matches_characters_in_order() {
+        assert_eq!(fuzzy_match("abc", "a_b_c").map(|m| m.1), Some(vec![0, 2, 4]));
+        assert!(fuzzy_match("cba", "a_b_c").is_none());
+        assert!(fuzzy_match("abcd", "abc").is_none());
+        assert_eq!(fuzzy_match("", "abc"), Some((0, Vec::new())));
+        assert_eq!(fuzzy_match("a c", "abc").map(|m| m.1), Some(vec![0, 2]));
+    }
+
+    #[test]
+    fn ignores_case_unless_the_pattern_has_an_uppercase_letter() {
+        assert!(fuzzy_match("readme", "README.md").is_some());
+        assert!(fuzzy_match("ReadMe", "README.md").is_none());
+        assert!(fuzzy_match("README", "README.md").is_some());
+    }
+
+    #[test]
+    fn returns_byte_offsets() {
+        assert_eq!(fuzzy_match("éb", "aéb").map(|m| m.1), Some(vec![1, 3]));
+    }
+
+    #[test]
+    fn keeps_the_best_alignment() {
+        let (score, positions) = fuzzy_match("fb", "foo_bar_fb").unwrap();
+        assert_eq!(positions, vec![8, 9]);
+        assert_eq!(score, 2 + WORD_START + CONSECUTIVE);
+        assert_eq!(fuzzy_match("src", "s/src").map(|m| m.1), Some(vec![2, 3, 4]));
+        assert_eq!(fuzzy_match("ab", "xab_ab").map(|m| m.1), Some(vec![4, 5]));
+    }
+
+    #[test]
+    fn ranks_consecutive_and_word_start_matches_first() {
+        assert!(score("fb", "foo_bar_fb") > score("fb", "foo_bar"));
+        assert!(score("main", "src/main.rs") > score("main", "src/my_animation.rs"));
+        assert!(score("bar", "foo/bar") > score("bar", "foobar"));
+        assert!(score("ab", "a_b") > score("ab", "axxb"));
+        assert!(score("ab", "axb") > score("ab", "axxb"));
+    }
+}
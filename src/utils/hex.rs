@@ -0,0 +1,114 @@
+//! Functions to show binary data as a hexadecimal dump, and to search bytes in it.
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::error::*;
+
+/// Number of bytes shown per row of a dump.
+pub const ROW_SIZE: usize = 16;
+/// Column of the first hexadecimal byte of a row.
+const HEX_START: usize = 10;
+/// Column of the first character of a row.
+const ASCII_START: usize = HEX_START + 3 * ROW_SIZE + 3;
+/// Number of bytes read at once by `search`.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Returns a row of dump: the offset of `bytes`, then at most `ROW_SIZE` of them in
+/// hexadecimal and as characters, non printable ones shown as `.`.
+///
+/// `00000010  2f 6c 69 62 36 34 2f 6c  64 2d 6c 69 6e 75 78 2d  |/lib64/ld-linux-|`
+pub fn row(offset: u64, bytes: &[u8]) -> String {
+    let bytes = &bytes[..bytes.len().min(ROW_SIZE)];
+    let mut hex = String::with_capacity(3 * ROW_SIZE + 1);
+    for i in 0..ROW_SIZE {
+        if i == ROW_SIZE / 2 {
+            hex.push(' ');
+        }
+        match bytes.get(i) {
+            Some(byte) => hex.push_str(&format!("{:02x} ", byte)),
+            None => hex.push_str("   "),
+        }
+    }
+    let ascii: String = bytes.iter().map(|&byte| printable(byte)).collect();
+    format!("{:08x}  {} |{}|", offset, hex, ascii)
+}
+
+/// Returns the byte as shown in the character part of a row.
+pub fn printable(byte: u8) -> char {
+    if byte.is_ascii_graphic() || byte == b' ' {
+        byte as char
+    } else {
+        '.'
+    }
+}
+
+/// Returns the columns of the byte at `index` in a row: that of its hexadecimal digits, and
+/// that of its character.
+pub fn columns(index: usize) -> (usize, usize) {
+    let gap = if index >= ROW_SIZE / 2 { 1 } else { 0 };
+    (HEX_START + 3 * index + gap, ASCII_START + index)
+}
+
+/// Parses a byte pattern: hexadecimal bytes, separated by spaces or not (`7f 45 4c 46` or
+/// `7f454c46`), or text between double quotes (`"ELF"`).
+pub fn parse_pattern(text: &str) -> Result<Vec<u8>> {
+    let text = text.trim();
+    if text.len() >= 2 && text.starts_with('"') && text.ends_with('"') {
+        return Ok(text.as_bytes()[1..text.len() - 1].to_vec());
+    }
+    let digits: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    let invalid = || -> Error {
+        ErrorKind::InvalidCommand {
+            reason: format!("Invalid byte pattern: {}", text),
+        }.into()
+    };
+    if digits.is_empty() || digits.len() % 2 != 0 || !digits.is_ascii() {
+        return Err(invalid());
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|_| invalid()))
+        .collect()
+}
+
+/// Parses an offset, in hexadecimal if prefixed by `0x`, in decimal otherwise.
+pub fn parse_offset(text: &str) -> Result<u64> {
+    let text = text.trim();
+    let parsed = if text.starts_with("0x") || text.starts_with("0X") {
+        u64::from_str_radix(&text[2..], 16)
+    } else {
+        text.parse()
+    };
+    parsed.map_err(|_| {
+        ErrorKind::InvalidCommand {
+            reason: format!("Invalid offset: {}", text),
+        }.into()
+    })
+}
+
+/// Returns the offset of the first occurrence of `pattern` in `reader` at or after `from`.
+pub fn search<R>(reader: &mut R, pattern: &[u8], from: u64) -> io::Result<Option<u64>>
+where
+    R: Read + Seek,
+{
+    if pattern.is_empty() {
+        return Ok(None);
+    }
+    let mut start = from;
+    let mut buffer = Vec::with_capacity(CHUNK_SIZE + pattern.len());
+    loop {
+        reader.seek(SeekFrom::Start(start))?;
+        buffer.clear();
+        reader
+            .by_ref()
+            .take((CHUNK_SIZE + pattern.len() - 1) as u64)
+            .read_to_end(&mut buffer)?;
+        if let Some(i) = buffer.windows(pattern.len()).position(|w| w == pattern) {
+            return Ok(Some(start + i as u64));
+        }
+        if buffer.len() < CHUNK_SIZE + pattern.len() - 1 {
+            return Ok(None);
+        }
+        // Chunks overlap, so that matches across two of them are found.
+        start += CHUNK_SIZE as u64;
+    }
+}
@@ -0,0 +1,294 @@
+//! Lightweight syntax highlighting of source files for the `preview` pane.
+//!
+//! Each supported language is described by a `Syntax`: its comments, string quotes, keywords
+//! and types. Lines are split into runs of text of the same `Class`, carrying block comments
+//! and strings over to the next lines.
+use std::path::Path;
+
+/// Kind of a run of highlighted text.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Class {
+    Plain,
+    Keyword,
+    Type,
+    String,
+    Number,
+    Comment,
+}
+
+/// Lexical rules of a language.
+pub struct Syntax {
+    pub name: &'static str,
+    /// File extensions, or full file names, of the language.
+    extensions: &'static [&'static str],
+    /// Interpreters named by the shebang of scripts of the language.
+    interpreters: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    /// `'` starts a lifetime rather than a character when not closed two characters later.
+    lifetimes: bool,
+    /// Lines starting with `[` are section headers, highlighted as keywords.
+    sections: bool,
+    keywords: &'static [&'static str],
+    types: &'static [&'static str],
+    /// Identifiers starting with an uppercase letter are types.
+    capitalized_types: bool,
+}
+
+static SYNTAXES: &[Syntax] = &[
+    Syntax {
+        name: "rust",
+        extensions: &["rs"],
+        interpreters: &[],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"'],
+        lifetimes: true,
+        sections: false,
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+            "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+            "trait", "true", "type", "unsafe", "use", "where", "while",
+        ],
+        types: &[
+            "bool", "char", "f32", "f64", "i8", "i16", "i32", "i64", "i128", "isize", "str",
+            "u8", "u16", "u32", "u64", "u128", "usize",
+        ],
+        capitalized_types: true,
+    },
+    Syntax {
+        name: "toml",
+        extensions: &["toml", "ini", "cfg", "conf"],
+        interpreters: &[],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        lifetimes: false,
+        sections: true,
+        keywords: &["true", "false"],
+        types: &[],
+        capitalized_types: false,
+    },
+    Syntax {
+        name: "shell",
+        extensions: &["sh", "bash", "zsh", "bashrc", "zshrc", "profile"],
+        interpreters: &["sh", "bash", "zsh", "dash", "ksh"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        lifetimes: false,
+        sections: false,
+        keywords: &[
+            "case", "do", "done", "elif", "else", "esac", "exit", "export", "fi", "for",
+            "function", "if", "in", "local", "return", "then", "until", "while",
+        ],
+        types: &[],
+        capitalized_types: false,
+    },
+    Syntax {
+        name: "python",
+        extensions: &["py"],
+        interpreters: &["python", "python2", "python3"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        lifetimes: false,
+        sections: false,
+        keywords: &[
+            "and", "as", "assert", "break", "class", "continue", "def", "del", "elif", "else",
+            "except", "False", "finally", "for", "from", "global", "if", "import", "in", "is",
+            "lambda", "None", "not", "or", "pass", "raise", "return", "True", "try", "while",
+            "with", "yield",
+        ],
+        types: &["bool", "dict", "float", "int", "list", "object", "set", "str", "tuple"],
+        capitalized_types: true,
+    },
+    Syntax {
+        name: "c",
+        extensions: &["c", "h", "cc", "cpp", "hpp"],
+        interpreters: &[],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        lifetimes: false,
+        sections: false,
+        keywords: &[
+            "break", "case", "class", "const", "continue", "default", "do", "else", "enum",
+            "extern", "for", "goto", "if", "namespace", "return", "sizeof", "static", "struct",
+            "switch", "template", "typedef", "union", "while",
+        ],
+        types: &[
+            "bool", "char", "double", "float", "int", "long", "short", "signed", "unsigned",
+            "void",
+        ],
+        capitalized_types: false,
+    },
+];
+
+/// Returns the syntax of the file at `path`, found from its extension or name, or else from
+/// the shebang of its `first_line`.
+pub fn detect(path: &Path, first_line: &str) -> Option<&'static Syntax> {
+    let name = path.file_name()?.to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_else(|| name.trim_start_matches('.').to_string());
+    if let Some(syntax) = SYNTAXES
+        .iter()
+        .find(|syntax| syntax.extensions.contains(&extension.as_str()))
+    {
+        return Some(syntax);
+    }
+    if !first_line.starts_with("#!") {
+        return None;
+    }
+    // `#!/bin/sh` or `#!/usr/bin/env python3`
+    let mut words = first_line[2..].split_whitespace();
+    let mut interpreter = words.next()?.rsplit('/').next()?;
+    if interpreter == "env" {
+        interpreter = words.next()?;
+    }
+    SYNTAXES
+        .iter()
+        .find(|syntax| syntax.interpreters.contains(&interpreter))
+}
+
+/// State carried from a line to the next one.
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Code,
+    Comment,
+    String(char),
+}
+
+/// Splits each of `lines` into runs of text of the same class.
+pub fn highlight<S: AsRef<str>>(syntax: &Syntax, lines: &[S]) -> Vec<Vec<(Class, String)>> {
+    let mut state = State::Code;
+    lines
+        .iter()
+        .map(|line| highlight_line(syntax, line.as_ref(), &mut state))
+        .collect()
+}
+
+fn highlight_line(syntax: &Syntax, line: &str, state: &mut State) -> Vec<(Class, String)> {
+    let mut runs: Vec<(Class, String)> = Vec::new();
+    let mut push = |class: Class, text: &str| match runs.last_mut() {
+        Some(run) if run.0 == class => run.1.push_str(text),
+        _ => runs.push((class, text.to_string())),
+    };
+    if syntax.sections && *state == State::Code && line.trim_start().starts_with('[') {
+        push(Class::Keyword, line);
+        return runs;
+    }
+    let mut rest = line;
+    while !rest.is_empty() {
+        match *state {
+            State::Comment => {
+                let end = syntax.block_comment.map_or("", |(_, end)| end);
+                match rest.find(end) {
+                    Some(i) => {
+                        push(Class::Comment, &rest[..i + end.len()]);
+                        rest = &rest[i + end.len()..];
+                        *state = State::Code;
+                    }
+                    None => {
+                        push(Class::Comment, rest);
+                        rest = "";
+                    }
+                }
+            }
+            State::String(quote) => {
+                let (end, closed) = string_end(rest, quote);
+                push(Class::String, &rest[..end]);
+                if closed {
+                    *state = State::Code;
+                }
+                rest = &rest[end..];
+            }
+            State::Code => {
+                if let Some((start, _)) = syntax.block_comment {
+                    if rest.starts_with(start) {
+                        push(Class::Comment, start);
+                        rest = &rest[start.len()..];
+                        *state = State::Comment;
+                        continue;
+                    }
+                }
+                if syntax.line_comments.iter().any(|c| rest.starts_with(c)) {
+                    push(Class::Comment, rest);
+                    break;
+                }
+                let c = rest.chars().next().unwrap_or(' ');
+                if syntax.quotes.contains(&c) || (c == '\'' && syntax.lifetimes) {
+                    if c == '\'' && syntax.lifetimes && !is_char_literal(rest) {
+                        push(Class::Plain, "'");
+                        rest = &rest[1..];
+                        continue;
+                    }
+                    push(Class::String, &rest[..1]);
+                    rest = &rest[1..];
+                    *state = State::String(c);
+                    continue;
+                }
+                if c.is_ascii_digit() {
+                    let end = word_end(rest, true);
+                    push(Class::Number, &rest[..end]);
+                    rest = &rest[end..];
+                    continue;
+                }
+                if c.is_alphabetic() || c == '_' {
+                    let end = word_end(rest, false);
+                    let word = &rest[..end];
+                    let class = if syntax.keywords.contains(&word) {
+                        Class::Keyword
+                    } else if syntax.types.contains(&word)
+                        || (syntax.capitalized_types && c.is_uppercase())
+                    {
+                        Class::Type
+                    } else {
+                        Class::Plain
+                    };
+                    push(class, word);
+                    rest = &rest[end..];
+                    continue;
+                }
+                push(Class::Plain, &rest[..c.len_utf8()]);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    runs
+}
+
+/// Returns the length of the part of `text` in a string closed by `quote`, the
+/// quote included, skipping escaped characters, and whether the string is closed. The whole
+/// text is in the string if it is not.
+fn string_end(text: &str, quote: char) -> (usize, bool) {
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == quote {
+            return (i + c.len_utf8(), true);
+        }
+    }
+    (text.len(), false)
+}
+
+/// Tells a character literal such as `'a'` or `'\n'` from a lifetime such as `'a`.
+fn is_char_literal(text: &str) -> bool {
+    let mut chars = text.chars().skip(1);
+    matches!((chars.next(), chars.next()), (Some('\\'), _) | (Some(_), Some('\'')))
+}
+
+/// Returns the length of the identifier, or the number if `number` is set, starting
+/// `text`.
+fn word_end(text: &str, number: bool) -> usize {
+    text.char_indices()
+        .find(|(_, c)| !(c.is_alphanumeric() || *c == '_' || (number && *c == '.')))
+        .map_or(text.len(), |(i, _)| i)
+}
@@ -0,0 +1,126 @@
+//! History of the lines entered in the consoles, one list per prompt, saved to
+//! `$XDG_DATA_HOME/marcos/history.toml`.
+use std::collections::HashMap;
+use std::fs as stdfs;
+use std::path::PathBuf;
+
+use dirs;
+use toml;
+
+use crate::error::*;
+
+/// Maximum number of lines kept for each prompt.
+const MAX_LINES: usize = 200;
+
+/// Lines entered in the consoles, oldest first, indexed by the name of the prompt.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct History {
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    #[serde(default, flatten)]
+    prompts: HashMap<String, Vec<String>>,
+}
+
+impl History {
+    /// Loads the history from the data directory, or starts an empty one if there is none.
+    pub fn load() -> Result<Self> {
+        let data_path: PathBuf = dirs::data_dir().ok_or(ErrorKind::DirNotFound {
+            dirname: String::from("DATA_DIR"),
+        })?;
+        let path = data_path.join("marcos").join("history.toml");
+        let mut history = if path.exists() {
+            let content = stdfs::read_to_string(&path)?;
+            toml::from_str(&content).unwrap_or_else(|e| {
+                warn!("Discarding unreadable history {:?}: {}", path, e);
+                History::default()
+            })
+        } else {
+            History::default()
+        };
+        history.path = Some(path);
+        Ok(history)
+    }
+
+    /// Saves the history to the file it was loaded from.
+    pub fn save(&self) -> Result<()> {
+        if let Some(ref path) = self.path {
+            if let Some(parent) = path.parent() {
+                stdfs::create_dir_all(parent)?;
+            }
+            let content = toml::to_string(self).map_err(|e| ErrorKind::InvalidOperation {
+                reason: format!("Cannot save history: {}", e),
+            })?;
+            stdfs::write(path, content)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the lines entered in `prompt`, oldest first.
+    pub fn lines(&self, prompt: &str) -> &[String] {
+        self.prompts.get(prompt).map(|lines| &lines[..]).unwrap_or(&[])
+    }
+
+    /// Adds `line` to the history of `prompt`, moving it to the end if it is already there.
+    /// Blank lines are ignored.
+    pub fn push(&mut self, prompt: &str, line: &str) -> Result<()> {
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+        let lines = self.prompts.entry(prompt.to_string()).or_default();
+        lines.retain(|l| l != line);
+        lines.push(line.to_string());
+        if lines.len() > MAX_LINES {
+            let excess = lines.len() - MAX_LINES;
+            lines.drain(..excess);
+        }
+        self.save()
+    }
+}
+
+/// Position in the history while recalling lines with Up and Down.
+///
+/// Only the lines starting with what was typed before the first recall are visited.
+#[derive(Debug, Default)]
+pub struct Recall {
+    position: Option<usize>,
+    draft: String,
+}
+
+impl Recall {
+    /// Returns the previous matching line, if any. `current` is the content of the console.
+    pub fn previous(&mut self, lines: &[String], current: &str) -> Option<String> {
+        let end = match self.position {
+            Some(position) => position,
+            None => {
+                self.draft = current.to_string();
+                lines.len()
+            }
+        };
+        let found = lines[..end].iter().rposition(|l| l.starts_with(&self.draft))?;
+        self.position = Some(found);
+        Some(lines[found].clone())
+    }
+
+    /// Returns the next matching line, or the draft once the most recent line is passed.
+    pub fn next(&mut self, lines: &[String]) -> Option<String> {
+        let start = self.position? + 1;
+        let found = lines[start.min(lines.len())..]
+            .iter()
+            .position(|l| l.starts_with(&self.draft));
+        match found {
+            Some(offset) => {
+                self.position = Some(start + offset);
+                Some(lines[start + offset].clone())
+            }
+            None => {
+                self.position = None;
+                Some(self.draft.clone())
+            }
+        }
+    }
+
+    /// Forgets the position, e.g. once the user edits the recalled line.
+    pub fn reset(&mut self) {
+        self.position = None;
+    }
+}
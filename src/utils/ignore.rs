@@ -0,0 +1,114 @@
+//! Rules of the `.gitignore` and `.ignore` files found while walking a tree.
+//!
+//! Supported syntax: blank lines and `#` comments are skipped, `!` negates a pattern, a trailing
+//! `/` only matches directories, and a pattern holding another `/` is anchored to the directory
+//! of its file. Other patterns match the name of an entry at any depth. Globs are those of
+//! `filter::glob`, so `**` behaves like `*`.
+use std::collections::HashMap;
+use std::fs as stdfs;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+use crate::utils::filter;
+
+/// Names of the files holding ignore rules, in increasing order of precedence.
+const IGNORE_FILES: &[&str] = &[".gitignore", ".ignore"];
+
+struct Rule {
+    regex: Regex,
+    negate: bool,
+    dir_only: bool,
+    anchored: bool,
+}
+
+/// Ignore rules of the directories under `root`, read as they are needed.
+pub struct IgnoreRules {
+    root: PathBuf,
+    rules: HashMap<PathBuf, Vec<Rule>>,
+}
+
+impl IgnoreRules {
+    /// Applies the ignore files of `root` and of the directories below it. Files above `root`
+    /// are not read.
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            rules: HashMap::new(),
+        }
+    }
+
+    /// Returns `true` if the entry at `path`, under the root, is ignored. Rules of deeper
+    /// directories, and later rules of the same directory, take precedence.
+    pub fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        let relative = match path.strip_prefix(&self.root) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => return false,
+        };
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let mut ignored = false;
+        let mut dir = self.root.clone();
+        let components: Vec<_> = relative.components().collect();
+        for depth in 0..components.len() {
+            if depth > 0 {
+                dir.push(components[depth - 1]);
+            }
+            if !self.rules.contains_key(&dir) {
+                let rules = read_rules(&dir);
+                self.rules.insert(dir.clone(), rules);
+            }
+            let below: PathBuf = components[depth..].iter().collect();
+            let below = below.to_string_lossy();
+            for rule in &self.rules[&dir] {
+                if rule.dir_only && !is_dir {
+                    continue;
+                }
+                let subject = if rule.anchored { &below } else { &name };
+                if rule.regex.is_match(subject) {
+                    ignored = !rule.negate;
+                }
+            }
+        }
+        ignored
+    }
+}
+
+/// Reads the rules of the ignore files of `dir`, skipping invalid patterns.
+fn read_rules(dir: &Path) -> Vec<Rule> {
+    let mut rules = Vec::new();
+    for file in IGNORE_FILES {
+        let content = match stdfs::read_to_string(dir.join(file)) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        for line in content.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (negate, line) = match line.strip_prefix('!') {
+                Some(line) => (true, line),
+                None => (false, line),
+            };
+            let (dir_only, line) = match line.strip_suffix('/') {
+                Some(line) => (true, line),
+                None => (false, line),
+            };
+            let anchored = line.contains('/');
+            let pattern = line.trim_start_matches('/');
+            match filter::glob(pattern) {
+                Ok(regex) => rules.push(Rule {
+                    regex,
+                    negate,
+                    dir_only,
+                    anchored,
+                }),
+                Err(e) => debug!("Skipping ignore pattern {:?} of {:?}: {}", line, dir, e),
+            }
+        }
+    }
+    rules
+}
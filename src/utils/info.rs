@@ -5,6 +5,8 @@ use std::path::Path;
 use std::ffi::OsString;
 
 use systemstat::{Platform, System};
+
+use crate::config::options::Size;
 use uname::uname;
 use users::get_current_username;
 
@@ -27,3 +29,23 @@ pub fn disk_info<P: AsRef<Path> + Display>(path: P) -> String {
     };
     mount_info
 }
+
+/// Returns `bytes` in a human readable form, counted in bytes (`1.5 KiB`) or in bits
+/// (`12.0 Kib`) according to `size`.
+pub fn format_size(bytes: u64, size: Size) -> String {
+    let (mut value, unit) = match size {
+        Size::Bytes => (bytes as f64, "B"),
+        Size::Bits => (bytes as f64 * 8.0, "b"),
+    };
+    let prefixes = ["", "Ki", "Mi", "Gi", "Ti", "Pi"];
+    let mut prefix = 0;
+    while value >= 1024.0 && prefix + 1 < prefixes.len() {
+        value /= 1024.0;
+        prefix += 1;
+    }
+    if prefix == 0 {
+        format!("{} {}", value, unit)
+    } else {
+        format!("{:.1} {}{}", value, prefixes[prefix], unit)
+    }
+}
@@ -0,0 +1,181 @@
+//! History of the directories visited in a tab, walked back and forth like vim's jump list.
+use std::path::{Path, PathBuf};
+
+/// Maximum number of directories kept in a jump list.
+const MAX_JUMPS: usize = 100;
+
+/// Directories visited in a tab, oldest first, along with the position in the list.
+///
+/// Visiting a directory after going back drops the directories ahead, like a browser does.
+#[derive(Debug, Clone)]
+pub struct JumpList {
+    entries: Vec<PathBuf>,
+    position: usize,
+}
+
+impl JumpList {
+    /// Starts a jump list at `dir`.
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            entries: vec![dir],
+            position: 0,
+        }
+    }
+
+    /// Returns the directory at the current position.
+    pub fn current(&self) -> &Path {
+        &self.entries[self.position]
+    }
+
+    /// Records a visit to `dir`, unless it is the current directory.
+    pub fn visit(&mut self, dir: PathBuf) {
+        if self.current() == dir {
+            return;
+        }
+        self.entries.truncate(self.position + 1);
+        self.entries.push(dir);
+        if self.entries.len() > MAX_JUMPS {
+            let excess = self.entries.len() - MAX_JUMPS;
+            self.entries.drain(..excess);
+        }
+        self.position = self.entries.len() - 1;
+    }
+
+    /// Moves `count` directories back, skipping the ones which no longer exist, and returns the
+    /// directory reached, or `None` if there is no such directory.
+    pub fn back(&mut self, count: usize) -> Option<PathBuf> {
+        let candidates: Vec<usize> = (0..self.position).rev().collect();
+        self.step(&candidates, count)
+    }
+
+    /// Moves `count` directories forward, skipping the ones which no longer exist.
+    pub fn forward(&mut self, count: usize) -> Option<PathBuf> {
+        let candidates: Vec<usize> = (self.position + 1..self.entries.len()).collect();
+        self.step(&candidates, count)
+    }
+
+    /// Moves to the directory at `index`, as returned by `recent`.
+    pub fn go_to(&mut self, index: usize) -> Option<PathBuf> {
+        let dir = self.entries.get(index)?.clone();
+        self.position = index;
+        Some(dir)
+    }
+
+    /// Returns the visited directories, most recent first, each along with its index. A
+    /// directory visited several times is only listed at its latest visit.
+    pub fn recent(&self) -> Vec<(usize, &Path)> {
+        let mut recent: Vec<(usize, &Path)> = Vec::new();
+        for (index, dir) in self.entries.iter().enumerate().rev() {
+            if !recent.iter().any(|(_, seen)| *seen == dir.as_path()) {
+                recent.push((index, dir));
+            }
+        }
+        recent
+    }
+
+    /// Returns the index of the current position.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    fn step(&mut self, candidates: &[usize], count: usize) -> Option<PathBuf> {
+        let existing: Vec<usize> = candidates
+            .iter()
+            .cloned()
+            .filter(|index| self.entries[*index].is_dir())
+            .collect();
+        let index = *existing.get(count.max(1) - 1).or_else(|| existing.last())?;
+        self.go_to(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Creates directories `a` to `e` in a fresh temporary directory.
+    fn dirs(name: &str) -> (PathBuf, Vec<PathBuf>) {
+        let root = ::std::env::temp_dir()
+            .join(format!("marcos-jumplist-{}-{}", name, ::std::process::id()));
+        let dirs: Vec<PathBuf> = ["a", "b", "c", "d", "e"].iter().map(|n| root.join(n)).collect();
+        for dir in &dirs {
+            fs::create_dir_all(dir).unwrap();
+        }
+        (root, dirs)
+    }
+
+    #[test]
+    fn walks_back_and_forth() {
+        let (root, dirs) = dirs("walk");
+        let mut jumps = JumpList::new(dirs[0].clone());
+        jumps.visit(dirs[1].clone());
+        jumps.visit(dirs[1].clone());
+        jumps.visit(dirs[2].clone());
+        assert_eq!(jumps.position(), 2);
+
+        assert_eq!(jumps.back(1), Some(dirs[1].clone()));
+        assert_eq!(jumps.back(5), Some(dirs[0].clone()));
+        assert_eq!(jumps.back(1), None);
+        assert_eq!(jumps.current(), dirs[0].as_path());
+        assert_eq!(jumps.forward(2), Some(dirs[2].clone()));
+        assert_eq!(jumps.forward(1), None);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn drops_the_directories_ahead_on_a_visit() {
+        let (root, dirs) = dirs("visit");
+        let mut jumps = JumpList::new(dirs[0].clone());
+        jumps.visit(dirs[1].clone());
+        jumps.visit(dirs[2].clone());
+        jumps.back(2);
+        jumps.visit(dirs[3].clone());
+        assert_eq!(jumps.forward(1), None);
+        assert_eq!(jumps.back(1), Some(dirs[0].clone()));
+        assert_eq!(jumps.forward(1), Some(dirs[3].clone()));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn skips_removed_directories() {
+        let (root, dirs) = dirs("removed");
+        let mut jumps = JumpList::new(dirs[0].clone());
+        jumps.visit(dirs[1].clone());
+        jumps.visit(dirs[2].clone());
+        fs::remove_dir(&dirs[1]).unwrap();
+        assert_eq!(jumps.back(1), Some(dirs[0].clone()));
+        assert_eq!(jumps.forward(1), Some(dirs[2].clone()));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn lists_recent_directories_once() {
+        let dirs: Vec<PathBuf> = ["/a", "/b", "/c"].iter().map(PathBuf::from).collect();
+        let mut jumps = JumpList::new(dirs[0].clone());
+        jumps.visit(dirs[1].clone());
+        jumps.visit(dirs[0].clone());
+        jumps.visit(dirs[2].clone());
+        assert_eq!(
+            jumps.recent(),
+            vec![(3, dirs[2].as_path()), (2, dirs[0].as_path()), (1, dirs[1].as_path())]
+        );
+        assert_eq!(jumps.go_to(1), Some(dirs[1].clone()));
+        assert_eq!(jumps.position(), 1);
+        assert_eq!(jumps.go_to(9), None);
+    }
+
+    #[test]
+    fn keeps_the_latest_jumps() {
+        let mut jumps = JumpList::new(PathBuf::from("/0"));
+        for i in 1..MAX_JUMPS + 10 {
+            jumps.visit(PathBuf::from(format!("/{}", i)));
+        }
+        assert_eq!(jumps.position(), MAX_JUMPS - 1);
+        assert_eq!(jumps.current(), Path::new(&format!("/{}", MAX_JUMPS + 9)));
+        assert_eq!(jumps.recent().last().unwrap().1, Path::new("/10"));
+    }
+}
@@ -1,3 +1,15 @@
+pub mod bookmarks;
 pub mod filter;
+pub mod frecency;
+pub mod fuzzy;
+pub mod highlight;
+pub mod hex;
+pub mod history;
+pub mod ignore;
 pub mod info;
+pub mod jumplist;
 pub mod logger;
+pub mod picture;
+pub mod search;
+pub mod sort;
+pub mod text;
@@ -0,0 +1,76 @@
+//! Functions to draw pictures with characters: each cell shows two pixels, the upper one as the
+//! colour of `▀` and the lower one as its background.
+use std::path::Path;
+
+use image::{imageops, DynamicImage};
+
+use crate::error::*;
+
+/// Character drawn in each cell.
+pub const UPPER_HALF: &str = "\u{2580}";
+/// Extensions of the pictures which can be decoded.
+const EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp"];
+/// Levels of the colour cube of 256-colour terminals.
+const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+pub type Rgb = (u8, u8, u8);
+
+/// Colours of the upper and the lower pixel of a cell. The last row of pictures of odd height
+/// has no lower pixels.
+pub type Cell = (Rgb, Option<Rgb>);
+
+/// Tells whether `path` is a picture which can be drawn, from its extension.
+pub fn is_picture(path: &Path) -> bool {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .map_or(false, |extension| EXTENSIONS.contains(&extension.as_str()))
+}
+
+/// Decodes the picture at `path` and returns its cells, scaled down to fit in `width` columns
+/// and `height` rows.
+pub fn render(path: &Path, width: usize, height: usize) -> Result<Vec<Vec<Cell>>> {
+    let picture = image::open(path).map_err(|e| ErrorKind::InvalidOperation {
+        reason: format!("Cannot decode {}: {}", path.display(), e),
+    })?;
+    Ok(cells(&picture, width, height))
+}
+
+/// Returns the cells of `picture`, scaled down to fit in `width` columns and `height` rows,
+/// keeping its aspect ratio. Pictures smaller than that are not scaled up. Transparent pixels
+/// are blended with black.
+pub fn cells(picture: &DynamicImage, width: usize, height: usize) -> Vec<Vec<Cell>> {
+    let mut pixels = picture.to_rgba();
+    let (max_width, max_height) = (width.max(1) as f64, (2 * height.max(1)) as f64);
+    let (w, h) = pixels.dimensions();
+    let scale = (max_width / f64::from(w)).min(max_height / f64::from(h));
+    if scale < 1.0 {
+        let w = ((f64::from(w) * scale).round() as u32).max(1);
+        let h = ((f64::from(h) * scale).round() as u32).max(1);
+        pixels = imageops::thumbnail(&pixels, w, h);
+    }
+    let (w, h) = pixels.dimensions();
+    let rgb = |x: u32, y: u32| {
+        let [r, g, b, a] = pixels.get_pixel(x, y).data;
+        let blend = |c: u8| (u16::from(c) * u16::from(a) / 255) as u8;
+        (blend(r), blend(g), blend(b))
+    };
+    (0..h)
+        .step_by(2)
+        .map(|y| {
+            (0..w)
+                .map(|x| (rgb(x, y), if y + 1 < h { Some(rgb(x, y + 1)) } else { None }))
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns the closest colour of the colour cube of 256-colour terminals, as levels from 0 to
+/// 5 of red, green and blue.
+pub fn low_res((r, g, b): Rgb) -> (u8, u8, u8) {
+    let level = |c: u8| {
+        (0..LEVELS.len())
+            .min_by_key(|&i| (i16::from(LEVELS[i]) - i16::from(c)).abs())
+            .unwrap_or(0) as u8
+    };
+    (level(r), level(g), level(b))
+}
@@ -0,0 +1,55 @@
+//! Functions to search the entries of a listing
+
+use regex::{Regex, RegexBuilder};
+
+use crate::error::*;
+
+/// Compiled search pattern.
+///
+/// The search is case insensitive unless the pattern contains an uppercase letter
+/// (smart-case). The pattern is taken literally unless `regex` is set.
+#[derive(Clone, Debug)]
+pub struct Matcher {
+    regex: Regex,
+}
+
+impl Matcher {
+    /// Compiles `pattern`, as a regular expression if `regex` is set.
+    pub fn new(pattern: &str, regex: bool) -> Result<Self> {
+        let source = if regex {
+            pattern.to_string()
+        } else {
+            ::regex::escape(pattern)
+        };
+        let regex = RegexBuilder::new(&source)
+            .case_insensitive(!pattern.chars().any(char::is_uppercase))
+            .build()
+            .map_err(|e| ErrorKind::InvalidCommand {
+                reason: format!("Invalid pattern: {}", e),
+            })?;
+        Ok(Matcher { regex })
+    }
+
+    /// Returns `true` if `text` contains a match.
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+
+    /// Returns the byte ranges of the non-empty matches in `text`.
+    pub fn ranges(&self, text: &str) -> Vec<(usize, usize)> {
+        self.regex
+            .find_iter(text)
+            .filter(|m| m.start() < m.end())
+            .map(|m| (m.start(), m.end()))
+            .collect()
+    }
+}
+
+/// The last search, repeated by `n` and `N`.
+#[derive(Clone, Debug)]
+pub struct Search {
+    pub pattern: String,
+    pub matcher: Matcher,
+    /// `true` for `/`, `false` for `?`.
+    pub forward: bool,
+}
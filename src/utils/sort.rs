@@ -0,0 +1,56 @@
+//! Functions to sort the entries of a directory
+
+use std::cmp::Ordering;
+use std::path::Path;
+
+use alphanumeric_sort::compare_os_str;
+use walkdir::{DirEntry, WalkDir};
+
+use crate::config::options::{ConfigOptions, SortBy};
+use crate::utils::filter;
+
+/// Returns the entries of `dir` as listed in the columns: directories first, then files, each
+/// sorted according to `options`. Hidden entries are left out unless `show_hidden` is set.
+pub fn listing(dir: &Path, options: &ConfigOptions) -> Vec<DirEntry> {
+    let (by, reverse, show_hidden) = (options.sort, options.sort_reverse, options.show_hidden);
+    let (mut dirs, mut files): (Vec<DirEntry>, Vec<DirEntry>) = WalkDir::new(dir)
+        .min_depth(1)
+        .max_depth(1)
+        .sort_by(move |a, b| compare(a, b, by, reverse))
+        .into_iter()
+        .filter_entry(|e| filter::is_visible(e, show_hidden))
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir() || entry.path().is_file())
+        .partition(|entry| entry.path().is_dir());
+    dirs.append(&mut files);
+    dirs
+}
+
+/// Compares two entries according to `by`, falling back to their names.
+///
+/// Names and extensions are sorted alphanumerically, sizes largest first and modification
+/// times newest first. `reverse` inverts the whole order.
+pub fn compare(a: &DirEntry, b: &DirEntry, by: SortBy, reverse: bool) -> Ordering {
+    let ordering = match by {
+        SortBy::Name => Ordering::Equal,
+        SortBy::Size => size(b).cmp(&size(a)),
+        SortBy::Modified => modified(b).cmp(&modified(a)),
+        SortBy::Extension => compare_os_str(
+            a.path().extension().unwrap_or_default(),
+            b.path().extension().unwrap_or_default(),
+        ),
+    }.then_with(|| compare_os_str(a.file_name(), b.file_name()));
+    if reverse {
+        ordering.reverse()
+    } else {
+        ordering
+    }
+}
+
+fn size(entry: &DirEntry) -> u64 {
+    entry.metadata().map(|m| m.len()).unwrap_or(0)
+}
+
+fn modified(entry: &DirEntry) -> Option<::std::time::SystemTime> {
+    entry.metadata().ok().and_then(|m| m.modified().ok())
+}
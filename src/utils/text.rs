@@ -0,0 +1,36 @@
+//! Functions to prepare the contents of files for the `preview` pane.
+use std::fs as stdfs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use unicode_width::UnicodeWidthChar;
+
+/// Returns the first `max_size` bytes of the file at `path`, along with whether the file holds
+/// more.
+pub fn read_head(path: &Path, max_size: usize) -> io::Result<(Vec<u8>, bool)> {
+    let mut bytes = Vec::new();
+    stdfs::File::open(path)?
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut bytes)?;
+    let truncated = bytes.len() > max_size;
+    bytes.truncate(max_size);
+    Ok((bytes, truncated))
+}
+
+/// Replaces the tabs of `line` by spaces, up to the next multiple of `width` columns.
+pub fn expand_tabs(line: &str, width: usize) -> String {
+    let width = width.max(1);
+    let mut expanded = String::with_capacity(line.len());
+    let mut column = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = width - column % width;
+            expanded.extend(::std::iter::repeat(' ').take(spaces));
+            column += spaces;
+        } else {
+            expanded.push(c);
+            column += c.width().unwrap_or(0);
+        }
+    }
+    expanded
+}